@@ -1,44 +1,106 @@
-use std::{path::PathBuf, str::FromStr};
+use std::path::PathBuf;
 
 use anyhow::{Result, anyhow};
 use clap::Args;
 use starknet::core::types::Felt;
+use url::Url;
 
-fn parse_felt(s: &str) -> Result<Felt> {
-    Felt::from_str(s).map_err(|_| anyhow!("Could not convert {s} to Felt"))
-}
+use super::{parse_felt, parse_url};
 
 #[derive(Clone, Debug, Args)]
 pub struct AccountParams {
-    /// Account address of the liquidator account
-    #[clap(long, value_parser = parse_felt, value_name = "LIQUIDATOR ACCOUNT ADDRESS", env = "ACCOUNT_ADDRESS")]
-    pub account_address: Felt,
+    /// Account addresses of the liquidator accounts, comma separated
+    #[clap(long = "account-address", value_parser = parse_felt, value_delimiter = ',', required = true, value_name = "LIQUIDATOR ACCOUNT ADDRESS", env = "ACCOUNT_ADDRESS")]
+    pub account_addresses: Vec<Felt>,
+
+    /// Private keys of the liquidator accounts, comma separated & in the same order
+    /// as the addresses
+    #[clap(long = "private-key", value_parser = parse_felt, value_delimiter = ',', value_name = "LIQUIDATOR PRIVATE KEY", env = "PRIVATE_KEY")]
+    pub private_keys: Vec<Felt>,
+
+    /// Keystore paths of the liquidator accounts, in the same order as the addresses
+    #[clap(long = "keystore-path", value_name = "LIQUIDATOR KEYSTORE")]
+    pub keystore_paths: Vec<PathBuf>,
+
+    /// Keystore passwords of the liquidator accounts, in the same order as the addresses
+    #[clap(
+        long = "keystore-password",
+        value_name = "LIQUIDATOR KEYSTORE PASSWORD"
+    )]
+    pub keystore_passwords: Vec<String>,
 
-    /// Private key of the liquidator account
-    #[clap(long, value_parser = parse_felt, value_name = "LIQUIDATOR PRIVATE KEY", env = "PRIVATE_KEY")]
-    pub private_key: Option<Felt>,
+    /// Url of the remote signing service holding the keys of the liquidator accounts
+    #[clap(long, value_parser = parse_url, value_name = "REMOTE SIGNER URL", env = "REMOTE_SIGNER_URL")]
+    pub remote_signer_url: Option<Url>,
 
-    /// Keystore path for the liquidator account
-    #[clap(long, value_name = "LIQUIDATOR KEYSTORE")]
-    pub keystore_path: Option<PathBuf>,
+    /// Token authenticating the requests to the remote signing service
+    #[clap(long, value_name = "REMOTE SIGNER TOKEN", env = "REMOTE_SIGNER_TOKEN")]
+    pub remote_signer_token: Option<String>,
+}
 
-    /// Keystore password for the liquidator account
-    #[clap(long, value_name = "LIQUIDATOR KEYSTORE PASSWORD")]
-    pub keystore_password: Option<String>,
+/// Key of a single liquidator account.
+#[derive(Clone, Debug)]
+pub enum AccountKey {
+    PrivateKey(Felt),
+    Keystore {
+        path: PathBuf,
+        password: String,
+    },
+    Remote {
+        url: Url,
+        auth_token: Option<String>,
+    },
 }
 
 impl AccountParams {
     pub fn validate(&self) -> Result<()> {
+        let accounts = self.account_addresses.len();
+        if self.remote_signer_url.is_some() {
+            if !self.private_keys.is_empty() || !self.keystore_paths.is_empty() {
+                return Err(anyhow!(
+                    "The liquidator accounts keys can't be provided along with a remote signer."
+                ));
+            }
+            return Ok(());
+        }
         match (
-            &self.private_key,
-            &self.keystore_path,
-            &self.keystore_password,
+            self.private_keys.len(),
+            self.keystore_paths.len(),
+            self.keystore_passwords.len(),
         ) {
-            (Some(_), None, None) => Ok(()),
-            (None, Some(_), Some(_)) => Ok(()),
+            (keys, 0, 0) if keys == accounts => Ok(()),
+            (0, paths, passwords) if paths == accounts && passwords == accounts => Ok(()),
             _ => Err(anyhow!(
-                "Missing liquidator account key. Use either (--private-key) or (--keystore-path + --keystore-password)."
+                "Missing liquidator account key. Use either (--private-key), (--keystore-path + --keystore-password), once per account address, or (--remote-signer-url)."
             )),
         }
     }
+
+    /// Returns the address & the key of every liquidator account.
+    pub fn accounts(&self) -> Vec<(Felt, AccountKey)> {
+        let keys: Vec<AccountKey> = if let Some(url) = &self.remote_signer_url {
+            vec![
+                AccountKey::Remote {
+                    url: url.clone(),
+                    auth_token: self.remote_signer_token.clone(),
+                };
+                self.account_addresses.len()
+            ]
+        } else if self.private_keys.is_empty() {
+            self.keystore_paths
+                .iter()
+                .zip(&self.keystore_passwords)
+                .map(|(path, password)| AccountKey::Keystore {
+                    path: path.clone(),
+                    password: password.clone(),
+                })
+                .collect()
+        } else {
+            self.private_keys
+                .iter()
+                .map(|key| AccountKey::PrivateKey(*key))
+                .collect()
+        };
+        self.account_addresses.iter().copied().zip(keys).collect()
+    }
 }
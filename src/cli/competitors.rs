@@ -0,0 +1,72 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::{
+    storages::{Storage, json::JsonStorage},
+    types::competition::CompetitionSummary,
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct CompetitorsCmd {
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+}
+
+impl CompetitorsCmd {
+    /// Prints how often & how fast the competitors liquidated the monitored positions
+    /// before the bot, from the stored liquidations.
+    pub async fn run(self) -> Result<()> {
+        let mut storage = JsonStorage::new(self.storage_path.to_str().unwrap_or_default());
+        storage.load().await?;
+        let records = storage.get_observed_liquidations();
+
+        if records.is_empty() {
+            println!("No liquidation observed yet.");
+            return Ok(());
+        }
+
+        print_summaries(&CompetitionSummary::by_liquidator(&records));
+        println!();
+
+        let total = CompetitionSummary::total(&records);
+        println!(
+            "Total: {} liquidations, {} won & {} lost ({} attempted, {} never seen liquidable)",
+            total.liquidations, total.won, total.lost, total.lost_attempted, total.lost_unseen
+        );
+        println!(
+            "Blocks from seeing a lost position liquidable to its liquidation: median {}, p90 {}, max {}",
+            as_string(total.lost_delay_percentile(50)),
+            as_string(total.lost_delay_percentile(90)),
+            as_string(total.lost_delays.last().copied()),
+        );
+        Ok(())
+    }
+}
+
+fn print_summaries(summaries: &BTreeMap<String, CompetitionSummary>) {
+    let width = summaries.keys().map(String::len).max().unwrap_or(0).max(12);
+    println!(
+        "{:<width$} {:>14} {:>8} {:>8} {:>12} {:>14}",
+        "Liquidator", "Liquidations", "Won", "Lost", "Attempted", "Median delay"
+    );
+    for (liquidator, summary) in summaries {
+        println!(
+            "{:<width$} {:>14} {:>8} {:>8} {:>12} {:>14}",
+            liquidator,
+            summary.liquidations,
+            summary.won,
+            summary.lost,
+            summary.lost_attempted,
+            as_string(summary.lost_delay_percentile(50)),
+        );
+    }
+}
+
+fn as_string(value: Option<u64>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
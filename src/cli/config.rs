@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+use bigdecimal::BigDecimal;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use starknet::{
+    core::types::{BlockId, BlockTag, Felt},
+    providers::Provider,
+};
+
+use crate::{
+    config::RawConfig,
+    types::price_source::PragmaOracle,
+    utils::erc20::{fetch_decimals, fetch_symbol},
+};
+
+use super::NetworkParams;
+
+#[derive(Clone, Debug, Args)]
+pub struct ConfigCmd {
+    #[command(subcommand)]
+    pub command: ConfigSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum ConfigSubcommand {
+    /// Checks the configuration file against the on-chain state.
+    Check(CheckConfigCmd),
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct CheckConfigCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub network_params: NetworkParams,
+}
+
+/// Results of the configuration checks.
+#[derive(Default)]
+struct CheckReport {
+    failures: usize,
+}
+
+impl CheckReport {
+    fn ok(&mut self, message: String) {
+        println!("  {} {message}", "✓".green());
+    }
+
+    fn fail(&mut self, message: String) {
+        self.failures += 1;
+        println!("  {} {message}", "✗".red());
+    }
+}
+
+impl ConfigCmd {
+    pub async fn run(self) -> Result<()> {
+        match self.command {
+            ConfigSubcommand::Check(cmd) => cmd.run().await,
+        }
+    }
+}
+
+impl CheckConfigCmd {
+    /// Runs all the checks & fails if any of them failed.
+    pub async fn run(self) -> Result<()> {
+        let network = &self.network_params.network;
+        let raw_config = RawConfig::from_file(&self.network_params.config_path)?;
+        let rpc_client = self.network_params.rpc_client();
+        let mut report = CheckReport::default();
+
+        println!("Contracts on {network}:");
+        let network_config = raw_config.network(network)?;
+        let contracts = [
+            ("Singleton", &network_config.singleton_address),
+            ("Extension", &network_config.extension_address),
+            ("Liquidate", &network_config.liquidate_address),
+            ("Pragma oracle", &network_config.pragma_oracle_address),
+        ];
+        for (name, address) in contracts {
+            let Ok(address) = Felt::from_hex(address) else {
+                report.fail(format!("{name} address {address} is not a valid felt"));
+                continue;
+            };
+            match rpc_client
+                .get_class_hash_at(BlockId::Tag(BlockTag::Latest), address)
+                .await
+            {
+                Ok(class_hash) => report.ok(format!(
+                    "{name} {address:#x} is deployed (class hash {class_hash:#x})"
+                )),
+                Err(e) => report.fail(format!("{name} {address:#x} is not deployed: {e}")),
+            }
+        }
+
+        let pragma_oracle = PragmaOracle::new(
+            rpc_client.clone(),
+            Felt::from_hex(&network_config.pragma_oracle_address).unwrap_or_default(),
+        );
+        let mut tickers: HashMap<String, Vec<String>> = HashMap::new();
+
+        for asset in raw_config.assets.iter() {
+            println!("{} ({}):", asset.ticker, asset.name);
+            tickers
+                .entry(asset.ticker.to_lowercase())
+                .or_default()
+                .push(asset.ticker.clone());
+
+            let address = match asset.address(network) {
+                Ok(address) => address,
+                Err(e) => {
+                    report.fail(format!(
+                        "Invalid {network} address, the asset is ignored by the bot: {e}"
+                    ));
+                    continue;
+                }
+            };
+
+            match fetch_decimals(&rpc_client, address).await {
+                Ok(decimals) if decimals == asset.decimals => {
+                    report.ok(format!("{decimals} decimals"))
+                }
+                Ok(decimals) => report.fail(format!(
+                    "Decimals mismatch: {} in config, {decimals} on-chain",
+                    asset.decimals
+                )),
+                Err(e) => report.fail(format!("Could not fetch decimals of {address:#x}: {e}")),
+            }
+
+            match fetch_symbol(&rpc_client, address).await {
+                Ok(symbol) if symbol.eq_ignore_ascii_case(&asset.ticker) => {
+                    report.ok(format!("Symbol {symbol}"))
+                }
+                Ok(symbol) => report.fail(format!(
+                    "Symbol mismatch: {} in config, {symbol} on-chain",
+                    asset.ticker
+                )),
+                Err(e) => report.fail(format!("Could not fetch symbol of {address:#x}: {e}")),
+            }
+
+            let pair = asset.oracle.pair_id(&asset.ticker);
+            match pragma_oracle
+                .get_pair_price(&pair, asset.oracle.aggregation)
+                .await
+            {
+                Ok(oracle_price) if oracle_price.price > BigDecimal::from(0) => report.ok(format!(
+                    "Pragma feed {pair} ({:?}) at {}",
+                    asset.oracle.aggregation,
+                    oracle_price.price.round(4)
+                )),
+                Ok(_) => report.fail(format!("Pragma feed {pair} returned a zero price")),
+                Err(e) => report.fail(format!("No Pragma feed for {pair}: {e}")),
+            }
+        }
+
+        println!("Tickers:");
+        let duplicates: Vec<Vec<String>> = tickers
+            .into_values()
+            .filter(|tickers| tickers.len() > 1)
+            .collect();
+        if duplicates.is_empty() {
+            report.ok("No duplicated ticker".to_string());
+        }
+        for duplicate in duplicates {
+            report.fail(format!(
+                "Duplicated ticker (prices are keyed case-insensitively): {}",
+                duplicate.join(", ")
+            ));
+        }
+
+        if report.failures > 0 {
+            bail!("{} configuration check(s) failed", report.failures);
+        }
+        println!("\nAll configuration checks passed!");
+        Ok(())
+    }
+}
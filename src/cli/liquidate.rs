@@ -0,0 +1,265 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{Result, anyhow, bail};
+use bigdecimal::BigDecimal;
+use clap::Args;
+use starknet::core::types::Felt;
+
+use crate::{
+    bindings::liquidate::{Liquidate, Swap},
+    config::Config,
+    services::oracle::LatestOraclePrices,
+    storages::{Storage, json::JsonStorage},
+    types::{
+        account::StarknetAccount,
+        asset::Asset,
+        fee_strategy,
+        liquidation::{LiquidationOutcome, LiquidationRecord},
+        position::Position,
+        route::RouteProviders,
+        transaction::{TxFinalStatus, TxStatus, TxTracker},
+    },
+    utils::constants::FEE_TOKEN_TICKER,
+};
+
+use super::{NetworkParams, account::AccountParams, parse_felt};
+
+/// Ekubo weights are scaled by 1e18.
+const ROUTE_WEIGHT_DECIMALS: i64 = 18;
+
+#[derive(Clone, Debug, Args)]
+pub struct LiquidateCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub account_params: AccountParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub network_params: NetworkParams,
+
+    /// Storage file path, where the liquidation attempt is recorded.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+
+    /// Pool id of the position.
+    #[clap(long, value_parser = parse_felt, value_name = "POOL ID")]
+    pub pool: Felt,
+
+    /// Collateral asset address.
+    #[clap(long, value_parser = parse_felt, value_name = "COLLATERAL")]
+    pub collateral: Felt,
+
+    /// Debt asset address.
+    #[clap(long, value_parser = parse_felt, value_name = "DEBT")]
+    pub debt: Felt,
+
+    /// Address of the position owner.
+    #[clap(long, value_parser = parse_felt, value_name = "USER")]
+    pub user: Felt,
+
+    /// Skips the confirmation prompt.
+    #[clap(long, short)]
+    pub yes: bool,
+}
+
+impl LiquidateCmd {
+    /// Liquidates a single position, after showing its state & asking for confirmation.
+    pub async fn run(self) -> Result<()> {
+        self.account_params.validate()?;
+        let config = self.network_params.config()?;
+        let rpc_client = self.network_params.rpc_client();
+        let account = StarknetAccount::from_params(
+            rpc_client.clone(),
+            config.chain_id,
+            self.account_params.clone(),
+        )?;
+        let liquidate_contract =
+            Arc::new(Liquidate::new(config.liquidate_address, account.0.clone()));
+        let route_providers =
+            RouteProviders::from_config(&config, &reqwest::Client::new(), &rpc_client);
+
+        let collateral = Asset::from_address(&config, self.collateral)
+            .ok_or_else(|| anyhow!("Unknown collateral asset {:#x}", self.collateral))?;
+        let debt = Asset::from_address(&config, self.debt)
+            .ok_or_else(|| anyhow!("Unknown debt asset {:#x}", self.debt))?;
+        let mut position = Position {
+            user_address: self.user,
+            pool_id: self.pool,
+            collateral,
+            debt,
+            lltv: BigDecimal::default(),
+            ..Default::default()
+        };
+
+        let oracle_prices = LatestOraclePrices::fetch(&config, &rpc_client).await?;
+        position
+            .update(&rpc_client, &config.singleton_address)
+            .await?;
+        if position.is_closed() {
+            bail!("{position} is closed, nothing to liquidate.");
+        }
+
+        let ltv = position.ltv(&oracle_prices).await?;
+        println!("{position}");
+        println!(
+            "  LTV: {}% / LLTV: {}%",
+            (&ltv * BigDecimal::from(100)).round(2),
+            (&position.lltv * BigDecimal::from(100)).round(2)
+        );
+        let (is_collateralized, vesu_ltv) = position
+            .vesu_collateralization(&config, &rpc_client)
+            .await?;
+        println!(
+            "  Vesu LTV: {}%",
+            (&vesu_ltv * BigDecimal::from(100)).round(2)
+        );
+        if ltv < position.lltv || is_collateralized {
+            println!("  ⚠️  The position does not look liquidable, the transaction may revert.");
+        }
+
+        let route = position.get_liquidation_route(&route_providers).await?;
+        println!("  Route:");
+        print_route(&config, &route);
+
+        let liquidation_factor = position
+            .fetch_liquidation_factors(&config, rpc_client.clone())
+            .await?;
+        let profit = position.estimated_liquidation_profit(&liquidation_factor, &oracle_prices)?;
+        let withdraw_swap = match position
+            .get_withdraw_swap(
+                &config,
+                &route_providers,
+                &liquidation_factor,
+                &route,
+                &oracle_prices,
+            )
+            .await
+        {
+            Ok(Some(withdraw_swap)) => {
+                println!(
+                    "  Withdraw route (min {} out):",
+                    withdraw_swap.min_amount_out
+                );
+                print_route(&config, &withdraw_swap.route);
+                Some(withdraw_swap)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                println!("  ⚠️  The collateral is kept, no withdraw swap: {e}");
+                None
+            }
+        };
+        let liquidation_tx = position.get_vesu_liquidate_tx(
+            &liquidate_contract,
+            route,
+            withdraw_swap,
+            &account.account_address(),
+        );
+        let estimate = account.estimate_gas(&[liquidation_tx.clone()]).await?;
+        let fee_price = oracle_prices.price_of(FEE_TOKEN_TICKER);
+        let fee_bid =
+            fee_strategy::bid(&config.fees, &estimate, Some(&profit), fee_price.as_ref())?;
+        let fee = fee_bid.max_fee_amount();
+        let fee_price = fee_price.unwrap_or_default();
+        println!(
+            "  Expected profit: {} USD (max fee: {} {FEE_TOKEN_TICKER} ≈ {} USD, tip {})",
+            (&profit - &fee * &fee_price).round(2),
+            fee.round(4),
+            (&fee * &fee_price).round(2),
+            fee_bid.tip,
+        );
+
+        if !self.yes && !confirm("Proceed with the liquidation?")? {
+            println!("Aborted.");
+            return Ok(());
+        }
+
+        let mut storage = JsonStorage::new(self.storage_path.to_str().unwrap_or_default());
+        storage.load().await?;
+
+        let tx_hash = match account
+            .execute_txs_with_bid(&[liquidation_tx], &fee_bid)
+            .await
+        {
+            Ok(tx_hash) => tx_hash,
+            Err(e) => {
+                let record =
+                    LiquidationRecord::failed(&position, None, e.to_string(), &oracle_prices)
+                        .with_account(account.account_address());
+                storage.add_liquidation(record).await?;
+                return Err(e);
+            }
+        };
+        println!("  Sent transaction {tx_hash:#064x}");
+
+        let tx_tracker = TxTracker::new(rpc_client.clone());
+        let record = match tx_tracker.follow(tx_hash, &config.transactions).await {
+            Ok(TxFinalStatus::Included(receipt)) => {
+                LiquidationRecord::from_receipt(&position, &config, &receipt, &oracle_prices)
+            }
+            Ok(TxFinalStatus::Dropped) => LiquidationRecord::failed(
+                &position,
+                Some(tx_hash),
+                TxStatus::Dropped.to_string(),
+                &oracle_prices,
+            ),
+            Err(e) => {
+                LiquidationRecord::failed(&position, Some(tx_hash), e.to_string(), &oracle_prices)
+            }
+        };
+        let record = record.with_account(account.account_address());
+        let outcome = record.outcome.clone();
+        storage.add_liquidation(record).await?;
+
+        match outcome {
+            LiquidationOutcome::Succeeded => {
+                println!("  ✅ Liquidated position #{}!", position.key());
+                Ok(())
+            }
+            outcome => Err(anyhow!("Liquidation {outcome}")),
+        }
+    }
+}
+
+/// Prints the swaps of an Ekubo route with their weights.
+fn print_route(config: &Config, (swaps, weights): &(Vec<Swap>, Vec<u128>)) {
+    let token_name = |address: &Felt| {
+        config
+            .get_asset_ticker_for_address(address)
+            .unwrap_or_else(|| format!("{address:#x}"))
+    };
+    for (swap, weight) in swaps.iter().zip(weights) {
+        let hops: Vec<String> = swap
+            .route
+            .iter()
+            .map(|node| {
+                format!(
+                    "{}/{} (fee {:#x}, tick spacing {})",
+                    token_name(&node.pool_key.token0.0),
+                    token_name(&node.pool_key.token1.0),
+                    node.pool_key.fee,
+                    node.pool_key.tick_spacing
+                )
+            })
+            .collect();
+        println!(
+            "    {}% via {}",
+            (BigDecimal::new((*weight).into(), ROUTE_WEIGHT_DECIMALS) * BigDecimal::from(100))
+                .round(2),
+            hops.join(" -> ")
+        );
+    }
+}
+
+/// Asks a yes/no question on stdin, defaulting to no.
+fn confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
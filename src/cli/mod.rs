@@ -1,21 +1,101 @@
 pub mod account;
-
-use std::{env, path::PathBuf};
+pub mod competitors;
+pub mod config;
+pub mod liquidate;
+pub mod pnl;
+pub mod positions;
+pub mod prices;
+
+use std::{convert::Infallible, env, fmt, path::PathBuf, str::FromStr, sync::Arc};
 use url::Url;
 
 use anyhow::{Result, anyhow};
-use strum::Display;
+use starknet::{
+    core::types::Felt,
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
 
 use account::AccountParams;
+use competitors::CompetitorsCmd;
+use config::ConfigCmd;
+use liquidate::LiquidateCmd;
+use pnl::PnlCmd;
+use positions::PositionsCmd;
+use prices::PricesCmd;
 
-use crate::config::LiquidationMode;
+use crate::config::{Config, LiquidationMode};
 
 fn parse_url(s: &str) -> Result<Url> {
     s.parse()
         .map_err(|_| anyhow!("Could not convert {s} to Url"))
 }
 
-#[derive(Clone, Debug, clap::Parser)]
+fn parse_felt(s: &str) -> Result<Felt> {
+    Felt::from_str(s).map_err(|_| anyhow!("Could not convert {s} to Felt"))
+}
+
+#[derive(Debug, clap::Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Runs the liquidator bot.
+    Run(RunCmd),
+    /// Prints the realized PnL of the recorded liquidations.
+    Pnl(PnlCmd),
+    /// Prints how the competitors beat the bot to the liquidations.
+    Competitors(CompetitorsCmd),
+    /// Inspects the positions tracked by the bot.
+    Positions(PositionsCmd),
+    /// Prints the price history recorded by the bot.
+    Prices(PricesCmd),
+    /// Liquidates a single position, without running the bot.
+    Liquidate(LiquidateCmd),
+    /// Validates the configuration file.
+    Config(ConfigCmd),
+}
+
+/// Parameters needed by the commands reading the chain state.
+#[derive(Clone, Debug, clap::Args)]
+pub struct NetworkParams {
+    /// The network chain configuration.
+    #[clap(long, short, value_name = "NETWORK NAME")]
+    pub network: NetworkName,
+
+    /// The rpc endpoint url.
+    #[clap(long, value_parser = parse_url, value_name = "RPC URL")]
+    pub rpc_url: Url,
+
+    /// Configuration file path.
+    #[clap(long, default_value = "config.yaml", value_name = "VESU CONFIG PATH")]
+    pub config_path: PathBuf,
+}
+
+impl NetworkParams {
+    pub fn rpc_client(&self) -> Arc<JsonRpcClient<HttpTransport>> {
+        Arc::new(JsonRpcClient::new(HttpTransport::new(self.rpc_url.clone())))
+    }
+
+    pub fn config(&self) -> Result<Config> {
+        let config = Config::new(&self.network, LiquidationMode::Full, &self.config_path)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Output format of the inspection commands.
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+#[derive(Clone, Debug, clap::Args)]
 pub struct RunCmd {
     #[allow(missing_docs)]
     #[clap(flatten)]
@@ -37,9 +117,10 @@ pub struct RunCmd {
     #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
     pub storage_path: Option<PathBuf>,
 
-    /// The block you want to start syncing from.
+    /// The block you want to start syncing from. Defaults to the starting block
+    /// of the network in the configuration file.
     #[clap(long, short, value_name = "BLOCK NUMBER")]
-    pub starting_block: u64,
+    pub starting_block: Option<u64>,
 
     /// Apibara API Key for indexing.
     #[clap(long, value_name = "APIBARA API KEY")]
@@ -50,10 +131,6 @@ pub struct RunCmd {
     pub liquidation_mode: LiquidationMode,
 }
 
-/// First blocks with Vesu activity. Not necessary to index before.
-const FIRST_MAINNET_BLOCK: u64 = 1439949;
-const FIRST_SEPOLIA_BLOCK: u64 = 77860;
-
 impl RunCmd {
     pub fn validate(&mut self) -> Result<()> {
         self.account_params.validate()?;
@@ -65,30 +142,31 @@ impl RunCmd {
                 "Apibara API Key is missing. Please provide at least one via command line arguments or environment variable."
             ));
         }
-
-        match self.network {
-            NetworkName::Mainnet => {
-                if self.starting_block <= FIRST_MAINNET_BLOCK {
-                    self.starting_block = FIRST_MAINNET_BLOCK;
-                }
-            }
-            NetworkName::Sepolia => {
-                if self.starting_block <= FIRST_SEPOLIA_BLOCK {
-                    self.starting_block = FIRST_SEPOLIA_BLOCK;
-                }
-            }
-        }
         Ok(())
     }
 }
 
-/// Starknet network name.
-#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Display)]
-pub enum NetworkName {
-    #[strum(serialize = "Mainnet")]
-    #[value(alias("mainnet"))]
-    Mainnet,
-    #[strum(serialize = "Sepolia")]
-    #[value(alias("sepolia"))]
-    Sepolia,
+/// Starknet network name, as defined in the `vesu` section of the configuration file
+/// (e.g `mainnet`, `sepolia` or a custom network like `devnet`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NetworkName(pub String);
+
+impl NetworkName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for NetworkName {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_lowercase()))
+    }
+}
+
+impl fmt::Display for NetworkName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
@@ -0,0 +1,65 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::{
+    storages::{Storage, json::JsonStorage},
+    types::liquidation::PnlSummary,
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct PnlCmd {
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+}
+
+impl PnlCmd {
+    /// Prints the realized PnL per asset & per day from the stored liquidations.
+    pub async fn run(self) -> Result<()> {
+        let mut storage = JsonStorage::new(self.storage_path.to_str().unwrap_or_default());
+        storage.load().await?;
+        let records = storage.get_liquidations();
+
+        if records.is_empty() {
+            println!("No liquidation recorded yet.");
+            return Ok(());
+        }
+
+        print_summaries("Asset", &PnlSummary::by_asset(&records));
+        println!();
+        print_summaries("Day", &PnlSummary::by_day(&records));
+        println!();
+        print_summaries("Account", &PnlSummary::by_account(&records));
+        println!();
+
+        let total = PnlSummary::total(&records);
+        println!(
+            "Total: {} liquidations over {} attempts => {} USD",
+            total.liquidations,
+            total.attempts,
+            total.pnl_usd.round(2)
+        );
+        Ok(())
+    }
+}
+
+fn print_summaries(label: &str, summaries: &BTreeMap<String, PnlSummary>) {
+    let width = summaries.keys().map(String::len).max().unwrap_or(0).max(12);
+    println!(
+        "{:<width$} {:>10} {:>14} {:>14} {:>14} {:>14}",
+        label, "Attempts", "Liquidations", "Profit (USD)", "Fees (USD)", "PnL (USD)"
+    );
+    for (key, summary) in summaries {
+        println!(
+            "{:<width$} {:>10} {:>14} {:>14} {:>14} {:>14}",
+            key,
+            summary.attempts,
+            summary.liquidations,
+            summary.residual_usd.round(2).to_string(),
+            summary.fees_usd.round(2).to_string(),
+            summary.pnl_usd.round(2).to_string(),
+        );
+    }
+}
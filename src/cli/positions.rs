@@ -0,0 +1,427 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use bigdecimal::BigDecimal;
+use clap::{Args, Subcommand};
+use futures_util::{StreamExt, stream};
+use serde::Serialize;
+use starknet::{
+    core::types::Felt,
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+
+use crate::{
+    services::oracle::LatestOraclePrices,
+    storages::{Storage, json::JsonStorage},
+    types::{
+        asset::Asset,
+        position::{POSITIONS_BATCH_SIZE, Position},
+    },
+};
+
+use super::{NetworkParams, OutputFormat, parse_felt};
+
+/// Number of batches of positions refreshed concurrently.
+const REFRESH_CONCURRENCY: usize = 4;
+
+#[derive(Clone, Debug, Args)]
+pub struct PositionsCmd {
+    #[command(subcommand)]
+    pub command: PositionsSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum PositionsSubcommand {
+    /// Lists the tracked positions with their current LTV.
+    List(ListPositionsCmd),
+    /// Shows a single position with its current LTV.
+    Show(ShowPositionCmd),
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ListPositionsCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub network_params: NetworkParams,
+
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+
+    /// Only list the positions of this pool.
+    #[clap(long, value_parser = parse_felt, value_name = "POOL ID")]
+    pub pool: Option<Felt>,
+
+    /// Only list the positions of this asset pair, e.g `ETH/USDC`.
+    #[clap(long, value_name = "COLLATERAL/DEBT")]
+    pub pair: Option<String>,
+
+    /// Only list the positions with a LTV above this ratio, e.g `0.8`.
+    #[clap(long, value_name = "LTV")]
+    pub min_ltv: Option<BigDecimal>,
+
+    /// Only list the positions in this liquidation state, e.g `quarantined`.
+    #[clap(long, value_name = "STATE")]
+    pub state: Option<String>,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Table, value_name = "FORMAT")]
+    pub output: OutputFormat,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ShowPositionCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub network_params: NetworkParams,
+
+    /// Address of the position owner.
+    #[clap(value_parser = parse_felt, value_name = "USER")]
+    pub user: Felt,
+
+    /// Pool id of the position.
+    #[clap(value_parser = parse_felt, value_name = "POOL ID")]
+    pub pool: Felt,
+
+    /// Collateral asset address.
+    #[clap(value_parser = parse_felt, value_name = "COLLATERAL")]
+    pub collateral: Felt,
+
+    /// Debt asset address.
+    #[clap(value_parser = parse_felt, value_name = "DEBT")]
+    pub debt: Felt,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Table, value_name = "FORMAT")]
+    pub output: OutputFormat,
+}
+
+/// A refreshed position along with its current LTV.
+#[derive(Clone, Debug, Serialize)]
+pub struct PositionReport {
+    pub key: u64,
+    #[serde(flatten)]
+    pub position: Position,
+    pub ltv: Option<BigDecimal>,
+    pub is_liquidable: bool,
+    /// Error of the refresh of the position, whose amounts are then the stored ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_error: Option<String>,
+}
+
+impl PositionReport {
+    pub async fn new(position: Position, oracle_prices: &LatestOraclePrices) -> Self {
+        let ltv = position.ltv(oracle_prices).await.ok();
+        let is_liquidable = position.lltv != BigDecimal::default()
+            && ltv.as_ref().is_some_and(|ltv| *ltv >= position.lltv);
+        Self {
+            key: position.key(),
+            position,
+            ltv,
+            is_liquidable,
+            refresh_error: None,
+        }
+    }
+
+    /// Reports a position that could not be refreshed, without its LTV.
+    pub fn failed(position: Position, error: &anyhow::Error) -> Self {
+        Self {
+            key: position.key(),
+            position,
+            ltv: None,
+            is_liquidable: false,
+            refresh_error: Some(error.to_string()),
+        }
+    }
+}
+
+impl PositionsCmd {
+    pub async fn run(self) -> Result<()> {
+        match self.command {
+            PositionsSubcommand::List(cmd) => cmd.run().await,
+            PositionsSubcommand::Show(cmd) => cmd.run().await,
+        }
+    }
+}
+
+impl ListPositionsCmd {
+    pub async fn run(self) -> Result<()> {
+        let config = self.network_params.config()?;
+        let rpc_client = self.network_params.rpc_client();
+
+        let mut storage = JsonStorage::new(self.storage_path.to_str().unwrap_or_default());
+        storage.load().await?;
+
+        let pair = self.pair.as_deref().map(parse_pair).transpose()?;
+        let positions: Vec<Position> = storage
+            .get_positions()
+            .into_values()
+            .filter(|p| self.pool.is_none_or(|pool| p.pool_id == pool))
+            .filter(|p| {
+                self.state
+                    .as_deref()
+                    .is_none_or(|state| p.lifecycle.state.name().eq_ignore_ascii_case(state))
+            })
+            .filter(|p| {
+                pair.as_ref().is_none_or(|(collateral, debt)| {
+                    p.collateral.name.eq_ignore_ascii_case(collateral)
+                        && p.debt.name.eq_ignore_ascii_case(debt)
+                })
+            })
+            .collect();
+
+        let oracle_prices = LatestOraclePrices::fetch(&config, &rpc_client).await?;
+        let reports = position_reports(
+            positions,
+            &config.singleton_address,
+            &rpc_client,
+            &oracle_prices,
+            self.min_ltv.as_ref(),
+        )
+        .await;
+
+        print_reports(&reports, self.output)
+    }
+}
+
+impl ShowPositionCmd {
+    pub async fn run(self) -> Result<()> {
+        let config = self.network_params.config()?;
+        let rpc_client = self.network_params.rpc_client();
+
+        let collateral = Asset::from_address(&config, self.collateral)
+            .ok_or_else(|| anyhow!("Unknown collateral asset {:#x}", self.collateral))?;
+        let debt = Asset::from_address(&config, self.debt)
+            .ok_or_else(|| anyhow!("Unknown debt asset {:#x}", self.debt))?;
+        let mut position = Position {
+            user_address: self.user,
+            pool_id: self.pool,
+            collateral,
+            debt,
+            lltv: BigDecimal::default(),
+            ..Default::default()
+        };
+
+        let oracle_prices = LatestOraclePrices::fetch(&config, &rpc_client).await?;
+        position
+            .update(&rpc_client, &config.singleton_address)
+            .await?;
+
+        let report = PositionReport::new(position, &oracle_prices).await;
+        print_reports(&[report], self.output)
+    }
+}
+
+/// Parses a `COLLATERAL/DEBT` pair of tickers.
+fn parse_pair(pair: &str) -> Result<(String, String)> {
+    pair.split_once('/')
+        .map(|(collateral, debt)| (collateral.to_string(), debt.to_string()))
+        .ok_or_else(|| anyhow!("Invalid pair {pair}, expected COLLATERAL/DEBT"))
+}
+
+/// Refreshes the positions & reports those with a LTV above `min_ltv`, the highest
+/// LTV first. The positions that could not be refreshed are always reported, last,
+/// along with their error.
+async fn position_reports(
+    positions: Vec<Position>,
+    singleton_address: &Felt,
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    oracle_prices: &LatestOraclePrices,
+    min_ltv: Option<&BigDecimal>,
+) -> Vec<PositionReport> {
+    let positions = refresh_positions(positions, singleton_address, rpc_client).await;
+
+    let mut reports = Vec::with_capacity(positions.len());
+    for (position, refresh) in positions {
+        let report = match refresh {
+            Ok(()) => PositionReport::new(position, oracle_prices).await,
+            Err(e) => {
+                reports.push(PositionReport::failed(position, &e));
+                continue;
+            }
+        };
+        let above_min_ltv = match (min_ltv, &report.ltv) {
+            (None, _) => true,
+            (Some(min_ltv), Some(ltv)) => ltv > min_ltv,
+            (Some(_), None) => false,
+        };
+        if above_min_ltv {
+            reports.push(report);
+        }
+    }
+    reports.sort_by(|a, b| {
+        (a.refresh_error.is_some(), &b.ltv).cmp(&(b.refresh_error.is_some(), &a.ltv))
+    });
+    reports
+}
+
+/// Refreshes the amounts & LLTV of the positions from the chain, by batches, along
+/// with the result of their refresh.
+async fn refresh_positions(
+    positions: Vec<Position>,
+    singleton_address: &Felt,
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+) -> Vec<(Position, Result<()>)> {
+    let batches: Vec<Vec<Position>> = positions
+        .chunks(POSITIONS_BATCH_SIZE)
+        .map(<[Position]>::to_vec)
+        .collect();
+    stream::iter(batches)
+        .map(|mut batch| async move {
+            let results = Position::update_batch(&mut batch, rpc_client, singleton_address).await;
+            batch.into_iter().zip(results).collect::<Vec<_>>()
+        })
+        .buffer_unordered(REFRESH_CONCURRENCY)
+        .flat_map(stream::iter)
+        .collect()
+        .await
+}
+
+fn print_reports(reports: &[PositionReport], output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(reports)?),
+        OutputFormat::Table => {
+            println!(
+                "{:<22} {:<68} {:<68} {:>24} {:>24} {:>9} {:>9} {:>10} {:<16} {:>8}",
+                "Key",
+                "Pool",
+                "User",
+                "Collateral",
+                "Debt",
+                "LTV",
+                "LLTV",
+                "Liquidable",
+                "State",
+                "Attempts"
+            );
+            for report in reports {
+                let position = &report.position;
+                println!(
+                    "{:<22} {:<68} {:<68} {:>24} {:>24} {:>9} {:>9} {:>10} {:<16} {:>8}",
+                    report.key,
+                    format!("{:#064x}", position.pool_id),
+                    format!("{:#064x}", position.user_address),
+                    format!(
+                        "{} {}",
+                        position.collateral.amount.round(4),
+                        position.collateral.name
+                    ),
+                    format!("{} {}", position.debt.amount.round(4), position.debt.name),
+                    report
+                        .ltv
+                        .as_ref()
+                        .map(as_percentage)
+                        .unwrap_or_else(|| "-".to_string()),
+                    as_percentage(&position.lltv),
+                    if report.is_liquidable { "yes" } else { "no" },
+                    position.lifecycle.state.name(),
+                    format!(
+                        "{}/{}",
+                        position.lifecycle.consecutive_failures, position.lifecycle.attempts
+                    ),
+                );
+                if let Some(error) = &report.refresh_error {
+                    println!("    refresh failed, stored amounts shown: {error}");
+                }
+                if let Some(error) = &position.lifecycle.last_error {
+                    println!("    last error: {error}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn as_percentage(ratio: &BigDecimal) -> String {
+    format!("{}%", (ratio * BigDecimal::from(100)).round(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use crate::{
+        config::{VESU_LTV_CONFIG_SELECTOR, VESU_POSITION_UNSAFE_SELECTOR},
+        services::oracle::{LatestOraclePrices, OraclePrice},
+        types::{asset::Asset, position::Position},
+        utils::test_utils::serve_json_rpc,
+    };
+
+    use super::position_reports;
+
+    fn position(user_address: u64) -> Position {
+        Position {
+            user_address: Felt::from(user_address),
+            pool_id: Felt::ONE,
+            collateral: Asset::new("ETH".to_string(), Felt::from(0xe), 18),
+            debt: Asset::new("USDC".to_string(), Felt::from(0xd), 6),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_position_reports() {
+        // Collateral of 2 ETH for every user, with a debt of 1000 USDC per unit of
+        // their address. The singleton fails to read the position of the user 0xbad.
+        let rpc_client = serve_json_rpc(|_, params| {
+            let request = &params["request"];
+            let selector =
+                Felt::from_hex(request["entry_point_selector"].as_str().unwrap()).unwrap();
+            let user = Felt::from_hex(request["calldata"][3].as_str().unwrap_or("0x0")).unwrap();
+            if selector == *VESU_POSITION_UNSAFE_SELECTOR {
+                if user == Felt::from(0xbad) {
+                    return Err(json!({ "code": 40, "message": "Contract error" }));
+                }
+                let debt = Felt::from(1_000_000_000_u64) * user;
+                Ok(json!([
+                    "0x0",
+                    "0x0",
+                    "0x0",
+                    "0x0",
+                    "0x1bc16d674ec80000",
+                    "0x0",
+                    debt.to_hex_string()
+                ]))
+            } else if selector == *VESU_LTV_CONFIG_SELECTOR {
+                // LLTV of 80%.
+                Ok(json!(["0xb1a2bc2ec500000"]))
+            } else {
+                Err(json!({ "code": 21, "message": "Invalid message selector" }))
+            }
+        })
+        .await;
+        let oracle_prices = LatestOraclePrices::default();
+        for (ticker, price) in [("eth", 2000), ("usdc", 1)] {
+            let price = OraclePrice {
+                price: BigDecimal::from(price),
+                ..Default::default()
+            };
+            oracle_prices.0.insert(ticker.to_string(), price);
+        }
+
+        let positions = vec![position(1), position(0xbad), position(3), position(4)];
+        let min_ltv = BigDecimal::from_str("0.5").unwrap();
+        let reports = position_reports(
+            positions,
+            &Felt::TWO,
+            &rpc_client,
+            &oracle_prices,
+            Some(&min_ltv),
+        )
+        .await;
+
+        // The position at a LTV of 25% is filtered out, the failed one is reported last.
+        let users: Vec<Felt> = reports.iter().map(|r| r.position.user_address).collect();
+        assert_eq!(users, vec![Felt::from(4), Felt::from(3), Felt::from(0xbad)]);
+        assert_eq!(reports[0].ltv, Some(BigDecimal::from(1)));
+        assert!(reports[0].is_liquidable);
+        assert_eq!(reports[1].ltv, Some(BigDecimal::from_str("0.75").unwrap()));
+        assert!(!reports[1].is_liquidable);
+        assert!(reports[2].ltv.is_none());
+        assert!(reports[2].refresh_error.is_some());
+    }
+}
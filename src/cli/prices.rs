@@ -0,0 +1,125 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use bigdecimal::BigDecimal;
+use chrono::DateTime;
+use clap::{Args, Subcommand};
+use serde::Serialize;
+
+use crate::{
+    storages::{Storage, json::JsonStorage},
+    types::price_history::{PriceHistory, PricePoint},
+};
+
+use super::OutputFormat;
+
+#[derive(Clone, Debug, Args)]
+pub struct PricesCmd {
+    #[command(subcommand)]
+    pub command: PricesSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum PricesSubcommand {
+    /// Shows the recorded prices of an asset with their volatility & velocity.
+    History(PriceHistoryCmd),
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct PriceHistoryCmd {
+    /// Ticker of the asset, e.g `ETH`.
+    #[clap(value_name = "TICKER")]
+    pub ticker: String,
+
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+
+    /// Only show the latest prices.
+    #[clap(long, default_value_t = 20, value_name = "LIMIT")]
+    pub limit: usize,
+
+    /// Window of the volatility & velocity estimates, in seconds.
+    #[clap(long, default_value_t = 300, value_name = "SECONDS")]
+    pub window: u64,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Table, value_name = "FORMAT")]
+    pub output: OutputFormat,
+}
+
+/// The recorded prices of an asset along with their volatility & velocity.
+#[derive(Clone, Debug, Serialize)]
+pub struct PriceHistoryReport {
+    pub ticker: String,
+    pub window_secs: u64,
+    pub volatility: Option<BigDecimal>,
+    pub velocity: Option<BigDecimal>,
+    pub points: Vec<PricePoint>,
+}
+
+impl PricesCmd {
+    pub async fn run(self) -> Result<()> {
+        match self.command {
+            PricesSubcommand::History(cmd) => cmd.run().await,
+        }
+    }
+}
+
+impl PriceHistoryCmd {
+    pub async fn run(self) -> Result<()> {
+        let mut storage = JsonStorage::new(self.storage_path.to_str().unwrap_or_default());
+        storage.load().await?;
+        let history = PriceHistory::from_points(storage.get_price_history());
+
+        let mut points = history.points(&self.ticker);
+        if points.is_empty() {
+            println!("No price recorded for {} yet.", self.ticker);
+            return Ok(());
+        }
+        let window = Duration::from_secs(self.window);
+        let report = PriceHistoryReport {
+            ticker: self.ticker.to_uppercase(),
+            window_secs: self.window,
+            volatility: history.volatility(&self.ticker, window),
+            velocity: history.velocity(&self.ticker, window),
+            points: points.split_off(points.len().saturating_sub(self.limit)),
+        };
+
+        match self.output {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            OutputFormat::Table => print_report(&report),
+        }
+        Ok(())
+    }
+}
+
+fn print_report(report: &PriceHistoryReport) {
+    println!("{:<12} {:<22} {:>20}", "Block", "Time (UTC)", "Price (USD)");
+    for point in &report.points {
+        let time = DateTime::from_timestamp(point.timestamp as i64, 0)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<12} {:<22} {:>20}",
+            point.block_number,
+            time,
+            point.price.round(6).to_string()
+        );
+    }
+    println!();
+    println!(
+        "{} over the last {}s: volatility {} /√s, velocity {} /s",
+        report.ticker,
+        report.window_secs,
+        as_string(&report.volatility),
+        as_string(&report.velocity)
+    );
+}
+
+fn as_string(value: &Option<BigDecimal>) -> String {
+    value
+        .as_ref()
+        .map(|value| value.round(8).to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
@@ -1,14 +1,25 @@
 use std::fs;
-use std::{collections::HashMap, path::PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use bigdecimal::{BigDecimal, FromPrimitive};
+use cainome::cairo_serde::ContractAddress;
 use clap::ValueEnum;
+use dashmap::DashMap;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
-use starknet::core::utils::get_selector_from_name;
+use starknet::core::utils::{cairo_short_string_to_felt, get_selector_from_name};
 
+use crate::bindings::liquidate::PoolKey;
 use crate::cli::{NetworkName, RunCmd};
+use crate::services::oracle::AggregationMode;
+use crate::utils::constants::FEE_TOKEN_TICKER;
 
 // Contract selectors
 lazy_static! {
@@ -18,10 +29,24 @@ lazy_static! {
     pub static ref VESU_POSITION_UNSAFE_SELECTOR: Felt =
         get_selector_from_name("position_unsafe").unwrap();
     pub static ref VESU_LTV_CONFIG_SELECTOR: Felt = get_selector_from_name("ltv_config").unwrap();
+    pub static ref VESU_CHECK_COLLATERALIZATION_SELECTOR: Felt =
+        get_selector_from_name("check_collateralization").unwrap();
+    pub static ref VESU_PRICE_SELECTOR: Felt = get_selector_from_name("price").unwrap();
     pub static ref FLASH_LOAN_SELECTOR: Felt = get_selector_from_name("flash_loan").unwrap();
     pub static ref LIQUIDATE_SELECTOR: Felt = get_selector_from_name("liquidate_position").unwrap();
     pub static ref LIQUIDATION_CONFIG_SELECTOR: Felt =
         get_selector_from_name("liquidation_config").unwrap();
+    pub static ref LIQUIDATE_POSITION_EVENT: Felt =
+        get_selector_from_name("LiquidatePosition").unwrap();
+    pub static ref ERC20_DECIMALS_SELECTOR: Felt = get_selector_from_name("decimals").unwrap();
+    pub static ref ERC20_SYMBOL_SELECTOR: Felt = get_selector_from_name("symbol").unwrap();
+    pub static ref ERC20_BALANCE_OF_SELECTOR: Felt = get_selector_from_name("balance_of").unwrap();
+    pub static ref ERC20_TRANSFER_SELECTOR: Felt = get_selector_from_name("transfer").unwrap();
+    pub static ref ERC20_TRANSFER_EVENT: Felt = get_selector_from_name("Transfer").unwrap();
+    pub static ref EKUBO_GET_POOL_PRICE_SELECTOR: Felt =
+        get_selector_from_name("get_pool_price").unwrap();
+    pub static ref EKUBO_GET_POOL_LIQUIDITY_SELECTOR: Felt =
+        get_selector_from_name("get_pool_liquidity").unwrap();
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
@@ -42,64 +67,493 @@ impl LiquidationMode {
 #[derive(Debug, Clone)]
 pub struct Config {
     pub network: NetworkName,
+    pub chain_id: Felt,
+    /// Block from which the Vesu events are indexed when there is no storage.
+    pub starting_block: u64,
+    /// Apibara DNA endpoint of the network.
+    pub indexer_url: String,
     pub singleton_address: Felt,
     pub extension_address: Felt,
     pub liquidate_address: Felt,
     pub pragma_oracle_address: Felt,
+    /// Pragma summary stats contract, needed by the Pragma TWAP price source.
+    pub pragma_summary_stats_address: Option<Felt>,
     pub assets: Vec<Asset>,
-    pub asset_map: HashMap<Felt, Asset>,
+    /// Monitored assets, shared between all the clones of the config so assets
+    /// discovered at runtime are visible by every service.
+    pub asset_map: Arc<DashMap<Felt, Asset>>,
+    pub discovery: DiscoveryRules,
+    /// LTV distance to the LLTV below which a position is considered almost liquidable.
+    pub almost_liquidable_threshold: BigDecimal,
+    /// How the liquidability of the positions is decided.
+    pub liquidability_check: LiquidabilityCheck,
+    /// Logs the positions for which the Pragma prices & Vesu disagree.
+    pub compare_liquidability_checks: bool,
+    pub volatility: VolatilityRules,
+    pub retries: RetryRules,
+    pub route_cache: RouteCacheRules,
+    pub ekubo_quoter: EkuboQuoterRules,
+    pub ekubo_pools: EkuboPoolsRules,
+    /// Route providers, tried in order.
+    pub route_providers: Vec<RouteProviderKind>,
+    pub withdraw: WithdrawRules,
+    pub oracle: OracleRules,
+    pub accounts: AccountsRules,
+    pub sweep: SweepRules,
+    pub fees: FeeRules,
+    pub transactions: TransactionRules,
     pub liquidation_mode: LiquidationMode,
 }
 
+/// Rules for following the submitted transactions.
+#[derive(Debug, Clone)]
+pub struct TransactionRules {
+    /// Interval between two polls of the status of a transaction.
+    pub poll_interval: Duration,
+    /// Time a transaction can stay unknown by the node before being considered dropped.
+    pub drop_timeout: Duration,
+}
+
+/// Rules for the resource bounds & tips of the liquidation transactions.
+#[derive(Debug, Clone)]
+pub struct FeeRules {
+    /// Multiplier applied to the estimated gas amounts.
+    pub gas_multiplier: f64,
+    /// Multiplier applied to the estimated gas prices.
+    pub gas_price_multiplier: f64,
+    /// Tip per L2 gas, in fri.
+    pub tip: u64,
+    /// Expected profit, in USD, from which an opportunity bids the high value tip.
+    pub high_value_profit: BigDecimal,
+    /// Tip per L2 gas of the high value opportunities, in fri.
+    pub high_value_tip: u64,
+    /// Share of the expected profit the max fee of a liquidation can't exceed.
+    pub max_profit_share: BigDecimal,
+}
+
+/// Rules for the sweeps of the liquidation proceeds to the treasury.
+#[derive(Debug, Clone)]
+pub struct SweepRules {
+    /// Address receiving the excess balances. Nothing is swept if None.
+    pub treasury: Option<Felt>,
+    /// Interval between two sweeps.
+    pub interval: Duration,
+    /// Tokens swept, with the amount kept on the liquidator accounts.
+    pub retained: Vec<RetainedToken>,
+}
+
+/// Token swept to the treasury, above the amount kept on the liquidator accounts.
+#[derive(Debug, Clone)]
+pub struct RetainedToken {
+    pub ticker: String,
+    pub address: Felt,
+    pub decimals: i64,
+    pub amount: BigDecimal,
+}
+
+/// Rules for the liquidator accounts the liquidations are spread across.
+#[derive(Debug, Clone)]
+pub struct AccountsRules {
+    /// Token the transaction fees are paid in. The balances aren't checked if unknown.
+    pub fee_token: Option<Felt>,
+    /// Fee token balance below which an account is not used to liquidate. The
+    /// liquidations are paused when all the accounts are below it.
+    pub min_fee_balance: BigDecimal,
+    /// Fee token balance below which a warning is logged.
+    pub low_fee_balance: BigDecimal,
+    /// Runway, at the recent fee usage, below which a warning is logged.
+    pub min_runway: Duration,
+    /// Window of the fee usage the runway is estimated from.
+    pub fee_usage_window: Duration,
+    /// Interval at which the fee balances are fetched.
+    pub balance_check_interval: Duration,
+}
+
+/// Rules for retrying the liquidations of the positions that failed.
+#[derive(Debug, Clone)]
+pub struct RetryRules {
+    /// Cooldown after a first failure, doubled with each consecutive failure.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive failures after which a position is quarantined until it's healthy.
+    pub max_failures: u32,
+}
+
+/// Rules for the estimates of the LTV moves, computed from the price history, that
+/// widen the almost liquidable window.
+#[derive(Debug, Clone)]
+pub struct VolatilityRules {
+    /// Window of the price history the volatility & velocity are computed on.
+    pub window: Duration,
+    /// Horizon over which the LTV moves are anticipated. Nothing is anticipated if zero.
+    pub horizon: Duration,
+}
+
+/// Source of truth used to decide if a position is liquidable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiquidabilityCheck {
+    /// LTV computed from the Pragma prices fetched by the bot.
+    #[default]
+    Pragma,
+    /// LTV computed from the prices of the Vesu extension.
+    VesuPrices,
+    /// Collateralization check of the Vesu singleton.
+    VesuCollateralization,
+}
+
+/// Rules for the swap routes pre-computed for the almost liquidable positions.
+#[derive(Debug, Clone)]
+pub struct RouteCacheRules {
+    /// Duration after which a cached route is refreshed.
+    pub ttl: Duration,
+    /// Relative move of the collateral/debt price after which a cached route is refreshed.
+    pub refresh_price_change: BigDecimal,
+}
+
+/// Rules for the requests to the Ekubo quoter API.
+#[derive(Debug, Clone)]
+pub struct EkuboQuoterRules {
+    /// Base URL of the quoter of the network.
+    pub url: String,
+    pub timeout: Duration,
+    /// Number of retries of a failed request.
+    pub retries: u32,
+}
+
+/// Rules for the direct routes built from known Ekubo pools.
+#[derive(Debug, Clone)]
+pub struct EkuboPoolsRules {
+    pub core_address: Option<Felt>,
+    pub pools: Vec<PoolKey>,
+    /// Maximum share of the pool virtual reserve that can be swapped.
+    pub max_reserve_share: BigDecimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteProviderKind {
+    /// Routes from the Ekubo quoter API.
+    EkuboQuoter,
+    /// Direct single-pool routes from the Ekubo pools of the config.
+    EkuboPools,
+}
+
+/// Rules for the conversion of the seized collateral, through the `withdraw_swap`
+/// of the liquidation.
+#[derive(Debug, Clone)]
+pub struct WithdrawRules {
+    /// Asset the collateral is swapped into. The collateral is kept if None.
+    pub target: Option<Felt>,
+    /// Maximum loss of the swap compared to the oracle prices.
+    pub max_slippage: BigDecimal,
+}
+
+/// Rules for the oracle prices used to liquidate.
+#[derive(Debug, Clone)]
+pub struct OracleRules {
+    /// Maximum age of a price.
+    pub max_price_age: Duration,
+    /// Maximum age of the price of the assets overriding `max_price_age`, by
+    /// lowercase ticker.
+    pub max_price_ages: HashMap<String, Duration>,
+    /// Minimum number of sources aggregated in a price.
+    pub min_sources: u32,
+    /// Weighted sources aggregated into the price of each asset.
+    pub sources: Vec<PriceSourceRules>,
+    /// Relative deviation of a source to the aggregated price above which the
+    /// liquidations of the asset are paused.
+    pub max_deviation: BigDecimal,
+    /// Webhook receiving the price deviation alerts.
+    pub alert_webhook_url: Option<String>,
+    /// Number of prices kept in the history of each asset.
+    pub history_size: usize,
+}
+
+impl OracleRules {
+    /// Returns the maximum age of the price of an asset.
+    pub fn max_price_age_of(&self, ticker: &str) -> Duration {
+        self.max_price_ages
+            .get(&ticker.to_lowercase())
+            .copied()
+            .unwrap_or(self.max_price_age)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PriceSourceRules {
+    pub kind: PriceSourceKind,
+    pub weight: BigDecimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceSourceKind {
+    /// Median price of the Pragma oracle.
+    PragmaMedian,
+    /// Time-weighted average price of the Pragma summary stats, over `window_secs`.
+    PragmaTwap {
+        #[serde(default = "default_twap_window_secs")]
+        window_secs: u64,
+    },
+    /// Spot price of the Ekubo pool of the network between the asset & `quote_asset`.
+    EkuboPool { quote_asset: String },
+    /// Price API returning JSON, `{ticker}` being replaced in the url. The price is
+    /// read at the `price_pointer` JSON pointer of the response.
+    Http { url: String, price_pointer: String },
+}
+
+/// Rules for the assets automatically discovered from the Vesu events.
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryRules {
+    pub enabled: bool,
+    /// If not empty, only those assets can be discovered.
+    pub allow: HashSet<Felt>,
+    pub deny: HashSet<Felt>,
+}
+
+impl DiscoveryRules {
+    /// Returns true if the asset can be discovered & monitored.
+    pub fn allows(&self, address: &Felt) -> bool {
+        self.enabled
+            && !self.deny.contains(address)
+            && (self.allow.is_empty() || self.allow.contains(address))
+    }
+}
+
 impl Config {
     pub fn from_cli(run_cmd: &RunCmd) -> Result<Self> {
         let config_path = run_cmd.config_path.clone().unwrap_or_default();
-        let network = run_cmd.network;
         let liquidation_mode = run_cmd.liquidation_mode;
 
-        Self::new(network, liquidation_mode, &config_path)
+        let config = Self::new(&run_cmd.network, liquidation_mode, &config_path)?;
+        config.validate()?;
+        Ok(config)
     }
 
     pub fn new(
-        network: NetworkName,
+        network: &NetworkName,
         liquidation_mode: LiquidationMode,
         config_path: &PathBuf,
     ) -> Result<Self> {
-        let raw_config: RawConfig = {
-            let config_str = fs::read_to_string(config_path)?;
-            serde_yaml::from_str(&config_str)?
-        };
+        let raw_config = RawConfig::from_file(config_path)?;
 
-        let network_config = match network {
-            NetworkName::Mainnet => &raw_config.vesu.mainnet,
-            NetworkName::Sepolia => &raw_config.vesu.sepolia,
-        };
+        let network_config = raw_config.network(network)?;
 
+        let chain_id = network_config.chain_id()?;
         let singleton_address = Felt::from_hex(&network_config.singleton_address)?;
         let extension_address = Felt::from_hex(&network_config.extension_address)?;
         let liquidate_address = Felt::from_hex(&network_config.liquidate_address)?;
         let pragma_oracle_address = Felt::from_hex(&network_config.pragma_oracle_address)?;
+        let pragma_summary_stats_address = network_config
+            .pragma_summary_stats_address
+            .as_deref()
+            .map(Felt::from_hex)
+            .transpose()?;
+
+        let starting_block = network_config.starting_block;
+        let indexer_url = network_config.indexer_url.clone();
 
-        let assets = raw_config.assets;
+        let assets = raw_config.assets.clone();
         let asset_map = assets
             .iter()
             .filter_map(|asset| {
-                let address = match network {
-                    NetworkName::Mainnet => Felt::from_hex(&asset.mainnet_address),
-                    NetworkName::Sepolia => Felt::from_hex(&asset.sepolia_address),
-                };
-                address.ok().map(|addr| (addr, asset.clone()))
+                asset
+                    .address(network)
+                    .ok()
+                    .map(|addr| (addr, asset.clone()))
             })
             .collect();
 
+        let parse_addresses = |addresses: &[String]| -> Result<HashSet<Felt>> {
+            addresses
+                .iter()
+                .map(|address| Ok(Felt::from_hex(address)?))
+                .collect()
+        };
+        let discovery = DiscoveryRules {
+            enabled: raw_config.discovery.enabled,
+            allow: parse_addresses(&raw_config.discovery.allow)?,
+            deny: parse_addresses(&raw_config.discovery.deny)?,
+        };
+        let almost_liquidable_threshold =
+            BigDecimal::from_f64(raw_config.monitoring.almost_liquidable_threshold)
+                .ok_or_else(|| anyhow!("Invalid almost liquidable threshold"))?;
+        let route_cache = RouteCacheRules {
+            ttl: Duration::from_secs(raw_config.routes.cache_ttl_secs),
+            refresh_price_change: BigDecimal::from_f64(raw_config.routes.refresh_price_change)
+                .ok_or_else(|| anyhow!("Invalid route refresh price change"))?,
+        };
+        let ekubo_quoter = EkuboQuoterRules {
+            url: network_config.ekubo_quoter_url.clone(),
+            timeout: Duration::from_millis(raw_config.routes.quoter_timeout_ms),
+            retries: raw_config.routes.quoter_retries,
+        };
+        let ekubo_pools = EkuboPoolsRules {
+            core_address: network_config
+                .ekubo_core_address
+                .as_deref()
+                .map(Felt::from_hex)
+                .transpose()?,
+            pools: network_config
+                .ekubo_pools
+                .iter()
+                .map(EkuboPoolConfig::pool_key)
+                .collect::<Result<_>>()?,
+            max_reserve_share: BigDecimal::from_f64(
+                raw_config.routes.direct_route_max_reserve_share,
+            )
+            .ok_or_else(|| anyhow!("Invalid direct route max reserve share"))?,
+        };
+        let route_providers = raw_config.routes.providers.clone();
+        let withdraw = WithdrawRules {
+            target: raw_config
+                .withdraw
+                .target_asset
+                .as_ref()
+                .map(|ticker| {
+                    assets
+                        .iter()
+                        .find(|asset| asset.ticker.eq_ignore_ascii_case(ticker))
+                        .ok_or_else(|| anyhow!("Unknown withdraw target asset {ticker}"))?
+                        .address(network)
+                })
+                .transpose()?,
+            max_slippage: BigDecimal::from_f64(raw_config.withdraw.max_slippage)
+                .ok_or_else(|| anyhow!("Invalid withdraw max slippage"))?,
+        };
+
+        let accounts = AccountsRules {
+            fee_token: assets
+                .iter()
+                .find(|asset| asset.ticker.eq_ignore_ascii_case(FEE_TOKEN_TICKER))
+                .and_then(|asset| asset.address(network).ok()),
+            min_fee_balance: BigDecimal::from_f64(raw_config.accounts.min_fee_balance)
+                .ok_or_else(|| anyhow!("Invalid accounts min fee balance"))?,
+            low_fee_balance: BigDecimal::from_f64(raw_config.accounts.low_fee_balance)
+                .ok_or_else(|| anyhow!("Invalid accounts low fee balance"))?,
+            min_runway: Duration::from_secs(raw_config.accounts.min_runway_secs),
+            fee_usage_window: Duration::from_secs(raw_config.accounts.fee_usage_window_secs),
+            balance_check_interval: Duration::from_secs(
+                raw_config.accounts.balance_check_interval_secs,
+            ),
+        };
+
+        let mut retained = raw_config
+            .sweep
+            .retain
+            .iter()
+            .map(|(ticker, amount)| {
+                let asset = assets
+                    .iter()
+                    .find(|asset| asset.ticker.eq_ignore_ascii_case(ticker))
+                    .ok_or_else(|| anyhow!("Unknown swept asset {ticker}"))?;
+                Ok(RetainedToken {
+                    ticker: asset.ticker.clone(),
+                    address: asset.address(network)?,
+                    decimals: asset.decimals,
+                    amount: BigDecimal::from_f64(*amount)
+                        .ok_or_else(|| anyhow!("Invalid retained amount of {ticker}"))?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        retained.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+        let sweep = SweepRules {
+            treasury: raw_config
+                .sweep
+                .treasury_address
+                .as_deref()
+                .map(Felt::from_hex)
+                .transpose()?,
+            interval: Duration::from_secs(raw_config.sweep.interval_secs),
+            retained,
+        };
+
+        let fees = FeeRules {
+            gas_multiplier: raw_config.fees.gas_multiplier,
+            gas_price_multiplier: raw_config.fees.gas_price_multiplier,
+            tip: raw_config.fees.tip,
+            high_value_profit: BigDecimal::from_f64(raw_config.fees.high_value_profit_usd)
+                .ok_or_else(|| anyhow!("Invalid fees high value profit"))?,
+            high_value_tip: raw_config.fees.high_value_tip,
+            max_profit_share: BigDecimal::from_f64(raw_config.fees.max_profit_share)
+                .ok_or_else(|| anyhow!("Invalid fees max profit share"))?,
+        };
+
+        let transactions = TransactionRules {
+            poll_interval: Duration::from_millis(raw_config.transactions.poll_interval_ms),
+            drop_timeout: Duration::from_secs(raw_config.transactions.drop_timeout_secs),
+        };
+
+        let oracle = OracleRules {
+            max_price_age: Duration::from_secs(raw_config.oracle.max_price_age_secs),
+            max_price_ages: assets
+                .iter()
+                .filter_map(|asset| {
+                    asset.oracle.max_price_age_secs.map(|max_price_age_secs| {
+                        (
+                            asset.ticker.to_lowercase(),
+                            Duration::from_secs(max_price_age_secs),
+                        )
+                    })
+                })
+                .collect(),
+            min_sources: raw_config.oracle.min_sources,
+            sources: raw_config
+                .oracle
+                .sources
+                .iter()
+                .map(|source| {
+                    Ok(PriceSourceRules {
+                        kind: source.kind.clone(),
+                        weight: BigDecimal::from_f64(source.weight)
+                            .ok_or_else(|| anyhow!("Invalid price source weight"))?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+            max_deviation: BigDecimal::from_f64(raw_config.oracle.max_deviation)
+                .ok_or_else(|| anyhow!("Invalid oracle max deviation"))?,
+            alert_webhook_url: raw_config.oracle.alert_webhook_url.clone(),
+            history_size: raw_config.oracle.history_size,
+        };
+        let volatility = VolatilityRules {
+            window: Duration::from_secs(raw_config.monitoring.volatility_window_secs),
+            horizon: Duration::from_secs(raw_config.monitoring.volatility_horizon_secs),
+        };
+        let retries = RetryRules {
+            backoff: Duration::from_secs(raw_config.monitoring.retry_backoff_secs),
+            max_backoff: Duration::from_secs(raw_config.monitoring.max_retry_backoff_secs),
+            max_failures: raw_config.monitoring.max_consecutive_failures,
+        };
+
         let config = Config {
-            network,
+            network: network.clone(),
+            chain_id,
+            starting_block,
+            indexer_url,
             singleton_address,
             extension_address,
             liquidate_address,
             pragma_oracle_address,
+            pragma_summary_stats_address,
             assets,
-            asset_map,
+            asset_map: Arc::new(asset_map),
+            discovery,
+            almost_liquidable_threshold,
+            liquidability_check: raw_config.monitoring.liquidability_check,
+            compare_liquidability_checks: raw_config.monitoring.compare_liquidability_checks,
+            volatility,
+            retries,
+            route_cache,
+            ekubo_quoter,
+            ekubo_pools,
+            route_providers,
+            withdraw,
+            oracle,
+            accounts,
+            sweep,
+            fees,
+            transactions,
             liquidation_mode,
         };
 
@@ -115,35 +569,765 @@ impl Config {
     pub fn get_decimal_for_address(&self, address: &Felt) -> Option<i64> {
         self.asset_map.get(address).map(|asset| asset.decimals)
     }
+
+    /// Returns the address & the asset monitored with the provided (case insensitive) ticker.
+    pub fn get_asset_by_ticker(&self, ticker: &str) -> Option<(Felt, Asset)> {
+        self.asset_map
+            .iter()
+            .find(|entry| entry.value().ticker.eq_ignore_ascii_case(ticker))
+            .map(|entry| (*entry.key(), entry.value().clone()))
+    }
+
+    /// Strict validation of the config, on startup & before hot-reloading it.
+    pub fn validate(&self) -> Result<()> {
+        for asset in self.assets.iter() {
+            asset.address(&self.network).map_err(|e| {
+                anyhow!("Invalid {} address for {}: {e}", self.network, asset.ticker)
+            })?;
+            if let Some(quote_asset) = &asset.oracle.quote_asset {
+                anyhow::ensure!(
+                    self.has_ticker(quote_asset)
+                        && !quote_asset.eq_ignore_ascii_case(&asset.ticker),
+                    "Invalid oracle quote asset {quote_asset} for {}",
+                    asset.ticker
+                );
+            }
+        }
+        anyhow::ensure!(
+            self.almost_liquidable_threshold >= BigDecimal::from(0)
+                && self.almost_liquidable_threshold < BigDecimal::from(1),
+            "The almost liquidable threshold must be in [0, 1)"
+        );
+        anyhow::ensure!(
+            self.route_cache.refresh_price_change > BigDecimal::from(0),
+            "The route refresh price change must be positive"
+        );
+        anyhow::ensure!(
+            !self.route_providers.is_empty(),
+            "At least one route provider is needed"
+        );
+        anyhow::ensure!(
+            self.withdraw.max_slippage >= BigDecimal::from(0)
+                && self.withdraw.max_slippage < BigDecimal::from(1),
+            "The withdraw max slippage must be in [0, 1)"
+        );
+        anyhow::ensure!(
+            self.accounts.min_fee_balance >= BigDecimal::from(0),
+            "The accounts min fee balance can't be negative"
+        );
+        anyhow::ensure!(
+            self.accounts.low_fee_balance >= self.accounts.min_fee_balance,
+            "The accounts low fee balance must be above the min fee balance"
+        );
+        anyhow::ensure!(
+            !self.accounts.fee_usage_window.is_zero()
+                && !self.accounts.balance_check_interval.is_zero(),
+            "The accounts fee usage window & balance check interval can't be zero"
+        );
+        anyhow::ensure!(
+            !self.sweep.interval.is_zero(),
+            "The sweep interval can't be zero"
+        );
+        for token in self.sweep.retained.iter() {
+            anyhow::ensure!(
+                token.amount >= BigDecimal::from(0),
+                "The retained amount of {} can't be negative",
+                token.ticker
+            );
+            anyhow::ensure!(
+                Some(token.address) != self.accounts.fee_token
+                    || token.amount >= self.accounts.min_fee_balance,
+                "The retained amount of {} must cover the accounts min fee balance",
+                token.ticker
+            );
+        }
+        anyhow::ensure!(
+            self.retries.backoff <= self.retries.max_backoff,
+            "The retry backoff must be below the max retry backoff"
+        );
+        anyhow::ensure!(
+            self.retries.max_failures > 0,
+            "The max consecutive failures must be at least 1"
+        );
+        anyhow::ensure!(
+            !self.transactions.poll_interval.is_zero(),
+            "The transactions poll interval can't be zero"
+        );
+        anyhow::ensure!(
+            self.transactions.drop_timeout > self.transactions.poll_interval,
+            "The transactions drop timeout must be above the poll interval"
+        );
+        anyhow::ensure!(
+            self.fees.gas_multiplier >= 1.0 && self.fees.gas_price_multiplier >= 1.0,
+            "The fees gas & gas price multipliers must be at least 1"
+        );
+        anyhow::ensure!(
+            self.fees.high_value_tip >= self.fees.tip,
+            "The fees high value tip must be above the tip"
+        );
+        anyhow::ensure!(
+            self.fees.max_profit_share > BigDecimal::from(0)
+                && self.fees.max_profit_share <= BigDecimal::from(1),
+            "The fees max profit share must be in (0, 1]"
+        );
+        anyhow::ensure!(
+            self.oracle.min_sources > 0,
+            "The oracle min sources must be at least 1"
+        );
+        anyhow::ensure!(
+            self.oracle.history_size >= 2,
+            "The oracle history size must be at least 2"
+        );
+        anyhow::ensure!(
+            !self.oracle.sources.is_empty(),
+            "At least one price source is needed"
+        );
+        anyhow::ensure!(
+            self.oracle.max_deviation > BigDecimal::from(0),
+            "The oracle max deviation must be positive"
+        );
+        for source in self.oracle.sources.iter() {
+            anyhow::ensure!(
+                source.weight > BigDecimal::from(0),
+                "The weight of the {:?} price source must be positive",
+                source.kind
+            );
+            match &source.kind {
+                PriceSourceKind::PragmaTwap { .. } => anyhow::ensure!(
+                    self.pragma_summary_stats_address.is_some(),
+                    "The Pragma TWAP price source needs the Pragma summary stats address of {}",
+                    self.network
+                ),
+                PriceSourceKind::EkuboPool { quote_asset } => {
+                    anyhow::ensure!(
+                        self.ekubo_pools.core_address.is_some(),
+                        "The Ekubo pool price source needs the Ekubo core address of {}",
+                        self.network
+                    );
+                    anyhow::ensure!(
+                        self.has_ticker(quote_asset),
+                        "Unknown quote asset {quote_asset} of the Ekubo pool price source"
+                    );
+                }
+                PriceSourceKind::PragmaMedian | PriceSourceKind::Http { .. } => {}
+            }
+        }
+        if self
+            .route_providers
+            .contains(&RouteProviderKind::EkuboPools)
+        {
+            anyhow::ensure!(
+                self.ekubo_pools.core_address.is_some(),
+                "The Ekubo pools route provider needs the Ekubo core address of {}",
+                self.network
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns true if an asset with the same (case insensitive) ticker is monitored.
+    pub fn has_ticker(&self, ticker: &str) -> bool {
+        self.asset_map
+            .iter()
+            .any(|entry| entry.value().ticker.eq_ignore_ascii_case(ticker))
+    }
 }
 
 // Below are the structs that represents the raw config extracted from the yaml file.
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RawConfig {
-    pub vesu: VesuConfig,
+    /// Vesu deployments, by network name.
+    pub vesu: HashMap<String, NetworkConfig>,
     pub assets: Vec<Asset>,
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+    #[serde(default)]
+    pub monitoring: MonitoringConfig,
+    #[serde(default)]
+    pub routes: RoutesConfig,
+    #[serde(default)]
+    pub withdraw: WithdrawConfig,
+    #[serde(default)]
+    pub oracle: OracleConfig,
+    #[serde(default)]
+    pub accounts: AccountsConfig,
+    #[serde(default)]
+    pub sweep: SweepConfig,
+    #[serde(default)]
+    pub fees: FeesConfig,
+    #[serde(default)]
+    pub transactions: TransactionsConfig,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct VesuConfig {
-    pub mainnet: NetworkConfig,
-    pub sepolia: NetworkConfig,
+impl RawConfig {
+    pub fn from_file(config_path: &PathBuf) -> Result<Self> {
+        let config_str = fs::read_to_string(config_path)?;
+        Ok(serde_yaml::from_str(&config_str)?)
+    }
+
+    /// Returns the configuration of the provided network.
+    pub fn network(&self, network: &NetworkName) -> Result<&NetworkConfig> {
+        self.vesu.get(network.as_str()).ok_or_else(|| {
+            let mut known: Vec<&str> = self.vesu.keys().map(String::as_str).collect();
+            known.sort();
+            anyhow!(
+                "Unknown network {network}, expected one of: {}",
+                known.join(", ")
+            )
+        })
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct NetworkConfig {
+    /// Chain id, either as hex or as a short string (e.g `SN_MAIN`).
+    pub chain_id: String,
+    pub starting_block: u64,
+    pub indexer_url: String,
+    pub ekubo_quoter_url: String,
+    #[serde(default)]
+    pub ekubo_core_address: Option<String>,
+    /// Ekubo pools used to build direct routes.
+    #[serde(default)]
+    pub ekubo_pools: Vec<EkuboPoolConfig>,
     pub singleton_address: String,
     pub extension_address: String,
     pub liquidate_address: String,
     pub pragma_oracle_address: String,
+    #[serde(default)]
+    pub pragma_summary_stats_address: Option<String>,
+}
+
+impl NetworkConfig {
+    pub fn chain_id(&self) -> Result<Felt> {
+        if self.chain_id.starts_with("0x") {
+            Ok(Felt::from_hex(&self.chain_id)?)
+        } else {
+            Ok(cairo_short_string_to_felt(&self.chain_id)?)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EkuboPoolConfig {
+    pub token0: String,
+    pub token1: String,
+    pub fee: String,
+    pub tick_spacing: u64,
+    #[serde(default = "default_ekubo_extension")]
+    pub extension: String,
+}
+
+impl EkuboPoolConfig {
+    pub fn pool_key(&self) -> Result<PoolKey> {
+        Ok(PoolKey {
+            token0: ContractAddress(Felt::from_hex(&self.token0)?),
+            token1: ContractAddress(Felt::from_hex(&self.token1)?),
+            fee: u128::from_str_radix(self.fee.trim_start_matches("0x"), 16)?,
+            tick_spacing: self.tick_spacing as u128,
+            extension: ContractAddress(Felt::from_hex(&self.extension)?),
+        })
+    }
+}
+
+fn default_ekubo_extension() -> String {
+    "0x0".to_string()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MonitoringConfig {
+    #[serde(default = "default_almost_liquidable_threshold")]
+    pub almost_liquidable_threshold: f64,
+    #[serde(default)]
+    pub liquidability_check: LiquidabilityCheck,
+    #[serde(default)]
+    pub compare_liquidability_checks: bool,
+    #[serde(default = "default_volatility_window_secs")]
+    pub volatility_window_secs: u64,
+    #[serde(default = "default_volatility_horizon_secs")]
+    pub volatility_horizon_secs: u64,
+    #[serde(default = "default_retry_backoff_secs")]
+    pub retry_backoff_secs: u64,
+    #[serde(default = "default_max_retry_backoff_secs")]
+    pub max_retry_backoff_secs: u64,
+    #[serde(default = "default_max_consecutive_failures")]
+    pub max_consecutive_failures: u32,
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            almost_liquidable_threshold: default_almost_liquidable_threshold(),
+            liquidability_check: LiquidabilityCheck::default(),
+            compare_liquidability_checks: false,
+            volatility_window_secs: default_volatility_window_secs(),
+            volatility_horizon_secs: default_volatility_horizon_secs(),
+            retry_backoff_secs: default_retry_backoff_secs(),
+            max_retry_backoff_secs: default_max_retry_backoff_secs(),
+            max_consecutive_failures: default_max_consecutive_failures(),
+        }
+    }
+}
+
+fn default_almost_liquidable_threshold() -> f64 {
+    0.01
+}
+
+fn default_volatility_window_secs() -> u64 {
+    300
+}
+
+fn default_volatility_horizon_secs() -> u64 {
+    60
+}
+
+fn default_retry_backoff_secs() -> u64 {
+    30
+}
+
+fn default_max_retry_backoff_secs() -> u64 {
+    1800
+}
+
+fn default_max_consecutive_failures() -> u32 {
+    5
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RoutesConfig {
+    #[serde(default = "default_route_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    #[serde(default = "default_route_refresh_price_change")]
+    pub refresh_price_change: f64,
+    #[serde(default = "default_quoter_timeout_ms")]
+    pub quoter_timeout_ms: u64,
+    #[serde(default = "default_quoter_retries")]
+    pub quoter_retries: u32,
+    #[serde(default = "default_route_providers")]
+    pub providers: Vec<RouteProviderKind>,
+    /// Formerly `direct_route_max_price_impact`, still accepted.
+    #[serde(
+        default = "default_direct_route_max_reserve_share",
+        alias = "direct_route_max_price_impact"
+    )]
+    pub direct_route_max_reserve_share: f64,
+}
+
+impl Default for RoutesConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl_secs: default_route_cache_ttl_secs(),
+            refresh_price_change: default_route_refresh_price_change(),
+            quoter_timeout_ms: default_quoter_timeout_ms(),
+            quoter_retries: default_quoter_retries(),
+            providers: default_route_providers(),
+            direct_route_max_reserve_share: default_direct_route_max_reserve_share(),
+        }
+    }
+}
+
+fn default_route_cache_ttl_secs() -> u64 {
+    30
+}
+
+fn default_route_refresh_price_change() -> f64 {
+    0.005
+}
+
+fn default_quoter_timeout_ms() -> u64 {
+    3000
+}
+
+fn default_quoter_retries() -> u32 {
+    2
+}
+
+fn default_route_providers() -> Vec<RouteProviderKind> {
+    vec![
+        RouteProviderKind::EkuboQuoter,
+        RouteProviderKind::EkuboPools,
+    ]
+}
+
+fn default_direct_route_max_reserve_share() -> f64 {
+    0.02
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WithdrawConfig {
+    /// Ticker of the asset the seized collateral is swapped into.
+    #[serde(default)]
+    pub target_asset: Option<String>,
+    #[serde(default = "default_withdraw_max_slippage")]
+    pub max_slippage: f64,
+}
+
+impl Default for WithdrawConfig {
+    fn default() -> Self {
+        Self {
+            target_asset: None,
+            max_slippage: default_withdraw_max_slippage(),
+        }
+    }
+}
+
+fn default_withdraw_max_slippage() -> f64 {
+    0.01
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SweepConfig {
+    /// Address receiving the excess balances of the liquidator accounts.
+    #[serde(default)]
+    pub treasury_address: Option<String>,
+    #[serde(default = "default_sweep_interval_secs")]
+    pub interval_secs: u64,
+    /// Amount kept on the liquidator accounts of each swept token, by ticker.
+    #[serde(default)]
+    pub retain: HashMap<String, f64>,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            treasury_address: None,
+            interval_secs: default_sweep_interval_secs(),
+            retain: HashMap::new(),
+        }
+    }
+}
+
+fn default_sweep_interval_secs() -> u64 {
+    3600
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TransactionsConfig {
+    #[serde(default = "default_tx_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Time a transaction can stay unknown by the node before being considered dropped.
+    #[serde(default = "default_tx_drop_timeout_secs")]
+    pub drop_timeout_secs: u64,
+}
+
+impl Default for TransactionsConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: default_tx_poll_interval_ms(),
+            drop_timeout_secs: default_tx_drop_timeout_secs(),
+        }
+    }
+}
+
+fn default_tx_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_tx_drop_timeout_secs() -> u64 {
+    60
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FeesConfig {
+    #[serde(default = "default_fee_multiplier")]
+    pub gas_multiplier: f64,
+    #[serde(default = "default_fee_multiplier")]
+    pub gas_price_multiplier: f64,
+    /// Tip per L2 gas, in fri.
+    #[serde(default)]
+    pub tip: u64,
+    #[serde(default = "default_high_value_profit_usd")]
+    pub high_value_profit_usd: f64,
+    /// Tip per L2 gas of the high value opportunities, in fri.
+    #[serde(default = "default_high_value_tip")]
+    pub high_value_tip: u64,
+    #[serde(default = "default_max_profit_share")]
+    pub max_profit_share: f64,
+}
+
+impl Default for FeesConfig {
+    fn default() -> Self {
+        Self {
+            gas_multiplier: default_fee_multiplier(),
+            gas_price_multiplier: default_fee_multiplier(),
+            tip: 0,
+            high_value_profit_usd: default_high_value_profit_usd(),
+            high_value_tip: default_high_value_tip(),
+            max_profit_share: default_max_profit_share(),
+        }
+    }
+}
+
+fn default_fee_multiplier() -> f64 {
+    1.5
+}
+
+fn default_high_value_profit_usd() -> f64 {
+    500.0
+}
+
+fn default_high_value_tip() -> u64 {
+    1_000_000_000
+}
+
+fn default_max_profit_share() -> f64 {
+    0.5
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AccountsConfig {
+    /// Fee token balance below which an account is not used to liquidate.
+    #[serde(default = "default_min_fee_balance")]
+    pub min_fee_balance: f64,
+    /// Fee token balance below which a warning is logged.
+    #[serde(default = "default_low_fee_balance")]
+    pub low_fee_balance: f64,
+    #[serde(default = "default_min_runway_secs")]
+    pub min_runway_secs: u64,
+    #[serde(default = "default_fee_usage_window_secs")]
+    pub fee_usage_window_secs: u64,
+    #[serde(default = "default_balance_check_interval_secs")]
+    pub balance_check_interval_secs: u64,
+}
+
+impl Default for AccountsConfig {
+    fn default() -> Self {
+        Self {
+            min_fee_balance: default_min_fee_balance(),
+            low_fee_balance: default_low_fee_balance(),
+            min_runway_secs: default_min_runway_secs(),
+            fee_usage_window_secs: default_fee_usage_window_secs(),
+            balance_check_interval_secs: default_balance_check_interval_secs(),
+        }
+    }
+}
+
+fn default_min_fee_balance() -> f64 {
+    1.0
+}
+
+fn default_low_fee_balance() -> f64 {
+    10.0
+}
+
+fn default_min_runway_secs() -> u64 {
+    86400
+}
+
+fn default_fee_usage_window_secs() -> u64 {
+    86400
+}
+
+fn default_balance_check_interval_secs() -> u64 {
+    60
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OracleConfig {
+    #[serde(default = "default_max_price_age_secs")]
+    pub max_price_age_secs: u64,
+    #[serde(default = "default_min_sources")]
+    pub min_sources: u32,
+    #[serde(default = "default_price_sources")]
+    pub sources: Vec<PriceSourceConfig>,
+    #[serde(default = "default_max_deviation")]
+    pub max_deviation: f64,
+    #[serde(default)]
+    pub alert_webhook_url: Option<String>,
+    #[serde(default = "default_price_history_size")]
+    pub history_size: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PriceSourceConfig {
+    #[serde(flatten)]
+    pub kind: PriceSourceKind,
+    #[serde(default = "default_price_source_weight")]
+    pub weight: f64,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            max_price_age_secs: default_max_price_age_secs(),
+            min_sources: default_min_sources(),
+            sources: default_price_sources(),
+            max_deviation: default_max_deviation(),
+            alert_webhook_url: None,
+            history_size: default_price_history_size(),
+        }
+    }
+}
+
+fn default_max_price_age_secs() -> u64 {
+    300
+}
+
+fn default_min_sources() -> u32 {
+    1
+}
+
+fn default_price_sources() -> Vec<PriceSourceConfig> {
+    vec![PriceSourceConfig {
+        kind: PriceSourceKind::PragmaMedian,
+        weight: default_price_source_weight(),
+    }]
+}
+
+fn default_price_source_weight() -> f64 {
+    1.0
+}
+
+fn default_max_deviation() -> f64 {
+    0.03
+}
+
+fn default_price_history_size() -> usize {
+    1200
+}
+
+fn default_twap_window_secs() -> u64 {
+    3600
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct DiscoveryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(from = "RawAsset")]
 pub struct Asset {
     pub name: String,
     pub ticker: String,
     pub decimals: i64,
-    pub mainnet_address: String,
-    pub sepolia_address: String,
+    /// Addresses of the asset, by network name.
+    pub addresses: HashMap<String, String>,
+    #[serde(default)]
+    pub oracle: AssetOracleConfig,
+}
+
+/// Asset as written in the config file, which also accepts the legacy
+/// `mainnet_address` & `sepolia_address` fields.
+#[derive(Deserialize)]
+struct RawAsset {
+    name: String,
+    ticker: String,
+    decimals: i64,
+    #[serde(default)]
+    addresses: HashMap<String, String>,
+    #[serde(default)]
+    mainnet_address: Option<String>,
+    #[serde(default)]
+    sepolia_address: Option<String>,
+    #[serde(default)]
+    oracle: AssetOracleConfig,
+}
+
+impl From<RawAsset> for Asset {
+    /// Merges the legacy addresses into the addresses by network name, the latter
+    /// taking precedence. An empty legacy address means no address on that network.
+    fn from(raw: RawAsset) -> Self {
+        let mut addresses = raw.addresses;
+        let legacy_addresses = [
+            ("mainnet", raw.mainnet_address),
+            ("sepolia", raw.sepolia_address),
+        ];
+        for (network, address) in legacy_addresses {
+            if let Some(address) = address.filter(|address| !address.is_empty()) {
+                addresses.entry(network.to_string()).or_insert(address);
+            }
+        }
+        Self {
+            name: raw.name,
+            ticker: raw.ticker,
+            decimals: raw.decimals,
+            addresses,
+            oracle: raw.oracle,
+        }
+    }
+}
+
+/// Pragma feed of an asset.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct AssetOracleConfig {
+    /// Pragma pair id, `{TICKER}/USD` if not set.
+    #[serde(default)]
+    pub pair_id: Option<String>,
+    #[serde(default)]
+    pub aggregation: AggregationMode,
+    /// Ticker of the asset the pair is quoted in (e.g `STRK` for `XSTRK/STRK`), the
+    /// price being converted into dollars with the price of that asset.
+    #[serde(default)]
+    pub quote_asset: Option<String>,
+    /// Maximum age of the price, overriding `oracle.max_price_age_secs`.
+    #[serde(default)]
+    pub max_price_age_secs: Option<u64>,
+}
+
+impl AssetOracleConfig {
+    /// Returns the Pragma pair id of the asset with the provided ticker.
+    pub fn pair_id(&self, ticker: &str) -> String {
+        self.pair_id
+            .clone()
+            .unwrap_or_else(|| format!("{}/USD", ticker.to_ascii_uppercase()))
+    }
+}
+
+impl Asset {
+    /// Creates an asset discovered on the provided network.
+    pub fn discovered(network: &NetworkName, address: Felt, symbol: String, decimals: i64) -> Self {
+        Self {
+            name: symbol.clone(),
+            ticker: symbol,
+            decimals,
+            addresses: HashMap::from([(network.to_string(), address.to_hex_string())]),
+            oracle: AssetOracleConfig::default(),
+        }
+    }
+
+    /// Returns the address of the asset on the provided network.
+    pub fn address(&self, network: &NetworkName) -> Result<Felt> {
+        let address = self
+            .addresses
+            .get(network.as_str())
+            .ok_or_else(|| anyhow!("no address on {network}"))?;
+        Ok(Felt::from_hex(address)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::cli::NetworkName;
+
+    use super::Asset;
+
+    #[test]
+    fn test_legacy_asset_addresses() {
+        let asset: Asset = serde_yaml::from_str(
+            r#"
+            name: "Ethereum"
+            ticker: "ETH"
+            decimals: 18
+            mainnet_address: "0x1"
+            sepolia_address: ""
+            addresses:
+              devnet: "0x3"
+            "#,
+        )
+        .unwrap();
+
+        let address = |network: &str| asset.address(&NetworkName(network.to_string()));
+        assert_eq!(address("mainnet").unwrap(), Felt::ONE);
+        assert!(address("sepolia").is_err());
+        assert_eq!(address("devnet").unwrap(), Felt::THREE);
+    }
 }
@@ -16,10 +16,10 @@ use starknet::{
     providers::{JsonRpcClient, jsonrpc::HttpTransport},
 };
 
-use cli::{NetworkName, RunCmd};
+use cli::{Cli, Command, NetworkName, RunCmd};
 use config::Config;
 use services::start_all_services;
-use types::account::StarknetAccount;
+use types::account::AccountPool;
 use utils::setup_tracing;
 
 #[tokio::main]
@@ -27,21 +27,38 @@ async fn main() -> Result<()> {
     let _ = dotenvy::dotenv();
     setup_tracing();
 
-    let mut run_cmd = RunCmd::parse();
+    match Cli::parse().command {
+        Command::Run(run_cmd) => run(run_cmd).await,
+        Command::Pnl(pnl_cmd) => pnl_cmd.run().await,
+        Command::Competitors(competitors_cmd) => competitors_cmd.run().await,
+        Command::Positions(positions_cmd) => positions_cmd.run().await,
+        Command::Prices(prices_cmd) => prices_cmd.run().await,
+        Command::Liquidate(liquidate_cmd) => liquidate_cmd.run().await,
+        Command::Config(config_cmd) => config_cmd.run().await,
+    }
+}
+
+/// Starts the liquidator bot.
+async fn run(mut run_cmd: RunCmd) -> Result<()> {
     run_cmd.validate()?;
 
-    print_app_title(run_cmd.account_params.account_address, run_cmd.network);
+    print_app_title(&run_cmd.account_params.account_addresses, &run_cmd.network);
+
+    let config = Config::from_cli(&run_cmd)?;
 
     let rpc_url = run_cmd.rpc_url.clone();
     let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(rpc_url)));
-    let account = StarknetAccount::from_cli(rpc_client.clone(), run_cmd.clone())?;
+    let accounts = AccountPool::from_cli(rpc_client.clone(), config.chain_id, run_cmd.clone())?;
 
-    let config = Config::from_cli(&run_cmd)?;
-    start_all_services(config, rpc_client, account, run_cmd).await
+    start_all_services(config, rpc_client, accounts, run_cmd).await
 }
 
 /// Prints information about the bot parameters.
-fn print_app_title(account_address: Felt, network: NetworkName) {
+fn print_app_title(account_addresses: &[Felt], network: &NetworkName) {
+    let accounts: Vec<String> = account_addresses
+        .iter()
+        .map(|address| format!("{address:#x}"))
+        .collect();
     println!("\n
 ██╗   ██╗███████╗███████╗██╗   ██╗    ██╗     ██╗ ██████╗ ██╗   ██╗██╗██████╗  █████╗ ████████╗ ██████╗ ██████╗
 ██║   ██║██╔════╝██╔════╝██║   ██║    ██║     ██║██╔═══██╗██║   ██║██║██╔══██╗██╔══██╗╚══██╔══╝██╔═══██╗██╔══██╗
@@ -50,6 +67,6 @@ fn print_app_title(account_address: Felt, network: NetworkName) {
  ╚████╔╝ ███████╗███████║╚██████╔╝    ███████╗██║╚██████╔╝╚██████╔╝██║██████╔╝██║  ██║   ██║   ╚██████╔╝██║  ██║
   ╚═══╝  ╚══════╝╚══════╝ ╚═════╝     ╚══════╝╚═╝ ╚══▀▀═╝  ╚═════╝ ╚═╝╚═════╝ ╚═╝  ╚═╝   ╚═╝    ╚═════╝ ╚═╝  ╚═╝
 
-  🤖 Liquidator 👉 0x{:x}
-  🎯 On {}", account_address, network);
+  🤖 Liquidator 👉 {}
+  🎯 On {}", accounts.join(", "), network);
 }
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use tokio::{sync::watch, task::JoinSet};
+
+use crate::{
+    config::{AccountsRules, Config},
+    types::account::AccountPool,
+    utils::{constants::FEE_TOKEN_TICKER, services::Service},
+};
+
+/// Tracks the fee token balance of the liquidator accounts & their runway at the
+/// recent fee usage. The accounts below the minimum fee balance are skipped by the
+/// liquidations, that are paused while all of them are.
+#[derive(Clone)]
+pub struct BalanceService {
+    config: watch::Receiver<Config>,
+    accounts: AccountPool,
+}
+
+#[async_trait::async_trait]
+impl Service for BalanceService {
+    async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("💰 Balance service started");
+            service.run_forever().await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl BalanceService {
+    pub fn new(config: watch::Receiver<Config>, accounts: AccountPool) -> Self {
+        Self { config, accounts }
+    }
+
+    /// Fetches the fee balances every `balance_check_interval`.
+    pub async fn run_forever(self) -> Result<()> {
+        loop {
+            let rules = self.config.borrow().accounts.clone();
+            match rules.fee_token {
+                Some(_) => self.check_balances(&rules).await,
+                None => tracing::warn!(
+                    "[💰 Balance] No {FEE_TOKEN_TICKER} asset configured, the fee balances are not checked"
+                ),
+            }
+            tokio::time::sleep(rules.balance_check_interval).await;
+        }
+    }
+
+    async fn check_balances(&self, rules: &AccountsRules) {
+        let Some(fee_token) = rules.fee_token else {
+            return;
+        };
+        for account in self.accounts.iter() {
+            let address = account.account_address();
+            let balance = match account.fee_balance(fee_token).await {
+                Ok(balance) => balance,
+                Err(e) => {
+                    tracing::warn!(
+                        "[💰 Balance] Could not fetch the fee balance of {address:#x}: {e}"
+                    );
+                    continue;
+                }
+            };
+            self.accounts.set_fee_balance(address, balance.clone());
+
+            let fees_paid = self
+                .accounts
+                .fees_paid_since(&address, rules.fee_usage_window);
+            let runway = runway(&balance, &fees_paid, rules.fee_usage_window);
+            tracing::info!(
+                target: "metrics",
+                account = %format!("{address:#x}"),
+                fee_balance = balance.to_f64().unwrap_or_default(),
+                fees_paid = fees_paid.to_f64().unwrap_or_default(),
+                runway_secs = runway.map(|runway| runway.as_secs()),
+                "[💰 Balance] {address:#x} holds {} {FEE_TOKEN_TICKER}",
+                balance.round(4)
+            );
+
+            if balance < rules.min_fee_balance {
+                tracing::error!(
+                    "[💰 Balance] 🚨 {address:#x} holds {} {FEE_TOKEN_TICKER}, below the {} floor: it won't liquidate until refilled",
+                    balance.round(4),
+                    rules.min_fee_balance
+                );
+            } else if balance < rules.low_fee_balance {
+                tracing::warn!(
+                    "[💰 Balance] ⚠️ {address:#x} is running low: {} {FEE_TOKEN_TICKER} left",
+                    balance.round(4)
+                );
+            }
+            if let Some(runway) = runway.filter(|runway| *runway < rules.min_runway) {
+                tracing::warn!(
+                    "[💰 Balance] ⚠️ {address:#x} can only pay the fees for {}h at the recent usage",
+                    runway.as_secs() / 3600
+                );
+            }
+        }
+
+        if !self.accounts.can_pay_fees(rules) {
+            tracing::error!(
+                "[💰 Balance] 🚨 No liquidator account holds {} {FEE_TOKEN_TICKER}, the liquidations are paused",
+                rules.min_fee_balance
+            );
+        }
+    }
+}
+
+/// Returns how long the balance lasts if the fees keep being paid at the rate they
+/// were paid during the last `window`, or None if no fee was paid.
+fn runway(balance: &BigDecimal, fees_paid: &BigDecimal, window: Duration) -> Option<Duration> {
+    if *fees_paid <= BigDecimal::from(0) {
+        return None;
+    }
+    let remaining = balance.max(&BigDecimal::from(0)) * BigDecimal::from(window.as_secs());
+    let secs = (remaining / fees_paid).to_u64()?;
+    Some(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bigdecimal::BigDecimal;
+
+    use super::runway;
+
+    #[test]
+    fn test_runway() {
+        let day = Duration::from_secs(86400);
+        // 10 STRK paid during the last day, 5 left.
+        assert_eq!(
+            runway(&BigDecimal::from(5), &BigDecimal::from(10), day),
+            Some(Duration::from_secs(43200))
+        );
+        assert_eq!(
+            runway(&BigDecimal::from(5), &BigDecimal::from(0), day),
+            None
+        );
+        assert_eq!(
+            runway(&BigDecimal::from(-1), &BigDecimal::from(10), day),
+            Some(Duration::ZERO)
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::watch,
+    task::JoinSet,
+    time::interval,
+};
+
+use crate::{config::Config, services::oracle::LatestOraclePrices, utils::services::Service};
+
+/// Interval at which the config file modification time is checked.
+const CONFIG_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Hot-reloads the config file when it is modified or when the bot receives
+/// a SIGHUP, and pushes the new config to the running services.
+#[derive(Clone)]
+pub struct ConfigWatcherService {
+    config_path: PathBuf,
+    config_sender: Arc<watch::Sender<Config>>,
+    latest_oracle_prices: LatestOraclePrices,
+}
+
+#[async_trait::async_trait]
+impl Service for ConfigWatcherService {
+    async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("📝 Config watcher service started");
+            service.run_forever().await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl ConfigWatcherService {
+    pub fn new(
+        config_path: PathBuf,
+        config_sender: watch::Sender<Config>,
+        latest_oracle_prices: LatestOraclePrices,
+    ) -> Self {
+        Self {
+            config_path,
+            config_sender: Arc::new(config_sender),
+            latest_oracle_prices,
+        }
+    }
+
+    /// Reloads the config on SIGHUP or when the config file has been modified.
+    pub async fn run_forever(self) -> Result<()> {
+        let mut sighup = signal(SignalKind::hangup())?;
+        let mut watch_interval = interval(CONFIG_WATCH_INTERVAL);
+        let mut last_modified = self.last_modified();
+
+        loop {
+            tokio::select! {
+                _ = sighup.recv() => {
+                    tracing::info!("[📝 Config] Received SIGHUP, reloading config");
+                    self.reload();
+                }
+                _ = watch_interval.tick() => {
+                    let modified = self.last_modified();
+                    if modified != last_modified {
+                        last_modified = modified;
+                        tracing::info!("[📝 Config] Config file modified, reloading config");
+                        self.reload();
+                    }
+                }
+            }
+        }
+    }
+
+    fn last_modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.config_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Loads & validates the new config. An invalid config is rejected and the
+    /// current one is kept.
+    fn reload(&self) {
+        let current = self.config_sender.borrow().clone();
+        let new_config = Config::new(
+            &current.network,
+            current.liquidation_mode,
+            &self.config_path,
+        )
+        .and_then(|config| config.validate().map(|_| config));
+
+        match new_config {
+            Ok(new_config) => self.apply(current, new_config),
+            Err(e) => {
+                tracing::error!(
+                    "[📝 Config] Invalid config rejected, keeping the current one: {e}"
+                );
+            }
+        }
+    }
+
+    /// Pushes the new config to the running services.
+    fn apply(&self, current: Config, mut new_config: Config) {
+        // New assets are merged into the shared asset map, so the ones discovered
+        // at runtime are kept.
+        for entry in new_config.asset_map.iter() {
+            if !current.asset_map.contains_key(entry.key()) {
+                tracing::info!("[📝 Config] New asset {}", entry.value().ticker);
+                self.latest_oracle_prices
+                    .0
+                    .entry(entry.value().ticker.to_lowercase())
+                    .or_default();
+            }
+            current
+                .asset_map
+                .insert(*entry.key(), entry.value().clone());
+        }
+        new_config.asset_map = current.asset_map.clone();
+
+        let address_changes = [
+            (
+                "singleton",
+                current.singleton_address,
+                new_config.singleton_address,
+            ),
+            (
+                "extension",
+                current.extension_address,
+                new_config.extension_address,
+            ),
+            (
+                "liquidate",
+                current.liquidate_address,
+                new_config.liquidate_address,
+            ),
+            (
+                "pragma oracle",
+                current.pragma_oracle_address,
+                new_config.pragma_oracle_address,
+            ),
+        ];
+        for (name, old_address, new_address) in address_changes {
+            if old_address != new_address {
+                tracing::info!(
+                    "[📝 Config] New {name} address: {old_address:#x} => {new_address:#x}"
+                );
+            }
+        }
+        if current.singleton_address != new_config.singleton_address {
+            tracing::warn!(
+                "[📝 Config] The indexer keeps streaming the events of the previous singleton until restart"
+            );
+        }
+        if current.almost_liquidable_threshold != new_config.almost_liquidable_threshold {
+            tracing::info!(
+                "[📝 Config] New almost liquidable threshold: {}",
+                new_config.almost_liquidable_threshold
+            );
+        }
+
+        self.config_sender.send_replace(new_config);
+        tracing::info!("[📝 Config] ✅ Config reloaded");
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use bigdecimal::BigDecimal;
+use dashmap::{DashMap, DashSet};
+use starknet::{
+    core::types::Felt,
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+use tokio::sync::watch;
+
+use crate::{
+    config::{Asset, Config},
+    services::oracle::{LatestOraclePrices, OraclePrice, OracleService},
+    utils::erc20::{fetch_decimals, fetch_symbol},
+};
+
+/// Delay before retrying the discovery of an asset that failed for a reason that
+/// may not last, e.g an RPC error or no price yet.
+const DISCOVERY_RETRY_DELAY: Duration = Duration::from_secs(600);
+
+/// Largest decimals of an asset that can be discovered.
+const MAX_ASSET_DECIMALS: i64 = 18;
+
+/// Reason why an asset could not be discovered.
+enum DiscoveryError {
+    /// The asset can't be monitored, its discovery is never retried.
+    Rejected(anyhow::Error),
+    /// The discovery is retried after `DISCOVERY_RETRY_DELAY`.
+    Failed(anyhow::Error),
+}
+
+/// Discovers the assets of the Vesu positions that are not in the configuration,
+/// and adds them to the monitored assets at runtime.
+#[derive(Clone)]
+pub struct AssetDiscovery {
+    config: watch::Receiver<Config>,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    latest_oracle_prices: LatestOraclePrices,
+    /// Assets denied by the discovery rules, logged once.
+    ignored_assets: Arc<DashSet<Felt>>,
+    /// Assets that can't be monitored.
+    rejected_assets: Arc<DashSet<Felt>>,
+    /// Assets whose discovery failed, with the time of the failure.
+    failed_assets: Arc<DashMap<Felt, Instant>>,
+}
+
+impl AssetDiscovery {
+    pub fn new(
+        config: watch::Receiver<Config>,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        latest_oracle_prices: LatestOraclePrices,
+    ) -> Self {
+        Self {
+            config,
+            rpc_client,
+            latest_oracle_prices,
+            ignored_assets: Arc::new(DashSet::new()),
+            rejected_assets: Arc::new(DashSet::new()),
+            failed_assets: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Discovers the provided assets if they're unknown. Once discovered, an asset is
+    /// part of the config asset map & has its price fetched by the oracle service.
+    pub async fn discover(&self, addresses: &[Felt]) {
+        let config = self.config.borrow().clone();
+        for address in addresses {
+            if config.asset_map.contains_key(address) || self.rejected_assets.contains(address) {
+                continue;
+            }
+            if self
+                .failed_assets
+                .get(address)
+                .is_some_and(|failed_at| failed_at.elapsed() < DISCOVERY_RETRY_DELAY)
+            {
+                continue;
+            }
+            // Not cached as a rejection, the rules may change with the config.
+            if !config.discovery.allows(address) {
+                if self.ignored_assets.insert(*address) {
+                    tracing::info!("[🧭 Discovery] Ignoring unknown asset {address:#x}");
+                }
+                continue;
+            }
+
+            match self.fetch_asset(&config, *address).await {
+                Ok(asset) => {
+                    tracing::info!(
+                        "[🧭 Discovery] Discovered {} ({} decimals) at {address:#x}",
+                        asset.ticker,
+                        asset.decimals
+                    );
+                    self.latest_oracle_prices
+                        .0
+                        .insert(asset.ticker.to_lowercase(), OraclePrice::default());
+                    config.asset_map.insert(*address, asset);
+                    self.failed_assets.remove(address);
+                }
+                Err(DiscoveryError::Rejected(e)) => {
+                    tracing::warn!("[🧭 Discovery] Rejecting asset {address:#x}: {e}");
+                    self.rejected_assets.insert(*address);
+                    self.failed_assets.remove(address);
+                }
+                Err(DiscoveryError::Failed(e)) => {
+                    tracing::warn!(
+                        "[🧭 Discovery] Could not discover asset {address:#x}, retrying in {}s: {e}",
+                        DISCOVERY_RETRY_DELAY.as_secs()
+                    );
+                    self.failed_assets.insert(*address, Instant::now());
+                }
+            }
+        }
+    }
+
+    /// Fetches the ERC20 metadata of an asset & checks that it has a price source.
+    async fn fetch_asset(&self, config: &Config, address: Felt) -> Result<Asset, DiscoveryError> {
+        let symbol = fetch_symbol(&self.rpc_client, address)
+            .await
+            .map_err(DiscoveryError::Failed)?;
+        let decimals = fetch_decimals(&self.rpc_client, address)
+            .await
+            .map_err(DiscoveryError::Failed)?;
+
+        if config.has_ticker(&symbol) {
+            return Err(DiscoveryError::Rejected(anyhow!(
+                "an asset with the ticker {symbol} is already monitored"
+            )));
+        }
+        if !(0..=MAX_ASSET_DECIMALS).contains(&decimals) {
+            return Err(DiscoveryError::Rejected(anyhow!(
+                "unsupported decimals {decimals}"
+            )));
+        }
+
+        let oracle = OracleService::new(
+            config,
+            self.rpc_client.clone(),
+            self.latest_oracle_prices.clone(),
+        )
+        .map_err(DiscoveryError::Failed)?;
+        let price = oracle
+            .get_price_in_dollars(&symbol.to_lowercase())
+            .await
+            .map_err(DiscoveryError::Failed)?;
+        if price <= BigDecimal::from(0) {
+            return Err(DiscoveryError::Failed(anyhow!("no price for {symbol}/USD")));
+        }
+
+        Ok(Asset::discovered(
+            &config.network,
+            address,
+            symbol,
+            decimals,
+        ))
+    }
+}
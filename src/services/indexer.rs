@@ -11,11 +11,13 @@ use starknet::core::types::Felt;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::task::JoinSet;
 
-use crate::cli::NetworkName;
-use crate::config::{Config, MIGRATE_POSITION_EVENT, MODIFY_POSITION_EVENT};
+use crate::config::{
+    Config, LIQUIDATE_POSITION_EVENT, MIGRATE_POSITION_EVENT, MODIFY_POSITION_EVENT,
+};
+use crate::services::discovery::AssetDiscovery;
 use crate::utils::services::Service;
 use crate::{
-    types::position::Position,
+    types::{competition::LiquidationEvent, position::Position},
     utils::conversions::{apibara_field_as_felt, felt_as_apibara_field},
 };
 
@@ -28,7 +30,9 @@ pub struct IndexerService {
     apibara_api_key: String,
     stream_config: Configuration<Filter>,
     positions_sender: UnboundedSender<(u64, Position)>,
+    liquidations_sender: UnboundedSender<LiquidationEvent>,
     seen_positions: DashSet<u64>,
+    asset_discovery: AssetDiscovery,
 }
 
 #[async_trait::async_trait]
@@ -49,12 +53,11 @@ impl IndexerService {
         config: Config,
         apibara_api_key: String,
         positions_sender: UnboundedSender<(u64, Position)>,
+        liquidations_sender: UnboundedSender<LiquidationEvent>,
         from_block: u64,
-    ) -> IndexerService {
-        let uri = match config.network {
-            NetworkName::Mainnet => Uri::from_static("https://mainnet.starknet.a5a.ch"),
-            NetworkName::Sepolia => Uri::from_static("https://sepolia.starknet.a5a.ch"),
-        };
+        asset_discovery: AssetDiscovery,
+    ) -> Result<IndexerService> {
+        let uri: Uri = config.indexer_url.parse()?;
 
         let stream_config = Configuration::<Filter>::default()
             .with_starting_block(from_block)
@@ -72,20 +75,28 @@ impl IndexerService {
                             .with_from_address(felt_as_apibara_field(&config.singleton_address))
                             .with_keys(vec![felt_as_apibara_field(&MIGRATE_POSITION_EVENT)])
                     })
+                    .add_event(|event| {
+                        event
+                            .with_from_address(felt_as_apibara_field(&config.singleton_address))
+                            .with_keys(vec![felt_as_apibara_field(&LIQUIDATE_POSITION_EVENT)])
+                    })
                     .build()
             });
 
-        IndexerService {
+        Ok(IndexerService {
             config,
             uri,
             apibara_api_key,
             stream_config,
             positions_sender,
+            liquidations_sender,
             seen_positions: DashSet::default(),
-        }
+            asset_discovery,
+        })
     }
 
-    /// Retrieve all the ModifyPosition events emitted from the Vesu Singleton Contract.
+    /// Retrieve all the ModifyPosition, MigratePosition & LiquidatePosition events emitted
+    /// from the Vesu Singleton Contract.
     pub async fn run_forever(mut self) -> Result<()> {
         let (config_client, config_stream) = configuration::channel(INDEXING_STREAM_CHUNK_SIZE);
 
@@ -117,12 +128,18 @@ impl IndexerService {
                         }
                         for block in batch {
                             for event in block.events {
+                                let tx_hash = event
+                                    .transaction
+                                    .and_then(|tx| tx.meta)
+                                    .and_then(|meta| meta.hash)
+                                    .map(|hash| apibara_field_as_felt(&hash));
                                 if let Some(event) = event.event {
                                     let block_number = match block.header.clone() {
                                         Some(hdr) => hdr.block_number,
                                         None => 0,
                                     };
-                                    self.create_position_from_event(block_number, event).await?;
+                                    self.create_position_from_event(block_number, event, tx_hash)
+                                        .await?;
                                 }
                             }
                         }
@@ -151,7 +168,13 @@ impl IndexerService {
     }
 
     /// Index the provided event & creates a new position.
-    async fn create_position_from_event(&mut self, block_number: u64, event: Event) -> Result<()> {
+    /// The liquidations are also sent to the monitoring service, to follow the competition.
+    async fn create_position_from_event(
+        &mut self,
+        block_number: u64,
+        event: Event,
+        tx_hash: Option<Felt>,
+    ) -> Result<()> {
         if event.from_address.is_none() {
             return Ok(());
         }
@@ -162,6 +185,11 @@ impl IndexerService {
             return Ok(());
         }
 
+        let collateral_address = apibara_field_as_felt(&event.keys[2]);
+        self.asset_discovery
+            .discover(&[collateral_address, debt_address])
+            .await;
+
         // Create the new position & sends it to the monitoring service.
         if let Some(new_position) = Position::from_event(&self.config, &event.keys) {
             let position_key = new_position.key();
@@ -171,6 +199,20 @@ impl IndexerService {
                     block_number
                 );
             }
+            // LiquidatePosition keys: [selector, pool_id, collateral, debt, user, caller]
+            let is_liquidation = event.keys.len() >= 6
+                && apibara_field_as_felt(&event.keys[0]) == *LIQUIDATE_POSITION_EVENT;
+            if is_liquidation {
+                let liquidation = LiquidationEvent {
+                    position: new_position.clone(),
+                    caller: apibara_field_as_felt(&event.keys[5]),
+                    tx_hash,
+                    block_number,
+                };
+                if let Err(e) = self.liquidations_sender.send(liquidation) {
+                    tracing::warn!("[🔍 Indexer] Could not send liquidation: {e}");
+                }
+            }
             match self.positions_sender.send((block_number, new_position)) {
                 Ok(_) => {}
                 Err(e) => panic!("[🔍 Indexer] 😱 Could not send position: {}", e),
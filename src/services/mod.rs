@@ -1,35 +1,50 @@
+pub mod balance;
+pub mod config_watcher;
+pub mod discovery;
 pub mod indexer;
 pub mod monitoring;
 pub mod oracle;
+pub mod sweep;
 
-use std::{cmp, sync::Arc};
+use std::sync::Arc;
 
 use anyhow::Result;
 use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::{mpsc::unbounded_channel, watch};
 
 use oracle::{LatestOraclePrices, OracleService};
 
 use crate::{
     cli::RunCmd,
     config::Config,
-    services::{indexer::IndexerService, monitoring::MonitoringService},
+    services::{
+        balance::BalanceService, config_watcher::ConfigWatcherService, discovery::AssetDiscovery,
+        indexer::IndexerService, monitoring::MonitoringService, sweep::SweepService,
+    },
     storages::{Storage, json::JsonStorage},
-    types::{account::StarknetAccount, position::Position},
+    types::{
+        account::AccountPool, competition::LiquidationEvent, position::Position,
+        price_history::PriceHistory, transaction::TxTracker,
+    },
     utils::services::{Service, ServiceGroup},
 };
 
 /// Starts all the services needed by the Liquidator Bot.
 /// This include:
 /// - the indexer service, that indexes blocks & send positions,
-/// - the monitoring service, that monitors & liquidates positions.
+/// - the oracle service, that fetches the latest prices,
+/// - the monitoring service, that monitors & liquidates positions,
+/// - the balance service, that tracks the fee balance of the liquidator accounts,
+/// - the sweep service, that transfers the liquidation proceeds to the treasury,
+/// - the config watcher service, that hot-reloads the config file.
 pub async fn start_all_services(
     config: Config,
     rpc_client: Arc<JsonRpcClient<HttpTransport>>,
-    account: StarknetAccount,
+    accounts: AccountPool,
     run_cmd: RunCmd,
 ) -> Result<()> {
     let (positions_sender, position_receiver) = unbounded_channel::<(u64, Position)>();
+    let (liquidations_sender, liquidations_receiver) = unbounded_channel::<LiquidationEvent>();
 
     // TODO: Add new methods of storage (s3, postgres, sqlite) and be able to define them in CLI
     let mut storage = JsonStorage::new(
@@ -42,34 +57,70 @@ pub async fn start_all_services(
     );
     let (last_block_indexed, _) = storage.load().await?;
 
-    let starting_block = cmp::max(run_cmd.starting_block, last_block_indexed);
+    let starting_block = [
+        run_cmd.starting_block.unwrap_or_default(),
+        config.starting_block,
+        last_block_indexed,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or_default();
     println!("  🥡 Starting from block {}\n\n", starting_block);
 
+    let latest_oracle_prices = LatestOraclePrices::from_config(&config);
+    let price_history = PriceHistory::from_points(storage.get_price_history());
+    accounts.record_fees(&storage.get_liquidations());
+    let (config_sender, config_receiver) = watch::channel(config.clone());
+    let config_watcher_service = ConfigWatcherService::new(
+        run_cmd.config_path.clone().unwrap_or_default(),
+        config_sender,
+        latest_oracle_prices.clone(),
+    );
+    let asset_discovery = AssetDiscovery::new(
+        config_receiver.clone(),
+        rpc_client.clone(),
+        latest_oracle_prices.clone(),
+    );
     let indexer_service = IndexerService::new(
         config.clone(),
         run_cmd.apibara_api_key.unwrap(),
         positions_sender,
+        liquidations_sender,
         starting_block,
-    );
-    let latest_oracle_prices = LatestOraclePrices::from_config(&config);
-    let oracle_service = OracleService::new(
-        config.pragma_oracle_address,
-        rpc_client.clone(),
-        latest_oracle_prices.clone(),
-    );
+        asset_discovery,
+    )?;
+    let oracle_service =
+        OracleService::new(&config, rpc_client.clone(), latest_oracle_prices.clone())?
+            .with_config_updates(config_receiver.clone())
+            .with_price_history(price_history.clone());
+    let tx_tracker = TxTracker::new(rpc_client.clone());
+    let balance_service = BalanceService::new(config_receiver.clone(), accounts.clone());
     let monitoring_service = MonitoringService::new(
-        config,
-        rpc_client,
-        account,
+        config_receiver.clone(),
+        rpc_client.clone(),
+        accounts.clone(),
         position_receiver,
+        liquidations_receiver,
         latest_oracle_prices,
+        price_history,
         Box::new(storage),
+        tx_tracker.clone(),
+    );
+    let sweep_service = SweepService::new(
+        config_receiver,
+        rpc_client,
+        accounts,
+        monitoring_service.storage(),
+        tx_tracker,
     );
 
     ServiceGroup::default()
         .with(indexer_service)
         .with(oracle_service)
         .with(monitoring_service)
+        .with(balance_service)
+        .with(sweep_service)
+        .with(config_watcher_service)
         .start_and_drive_to_end()
         .await?;
 
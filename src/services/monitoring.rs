@@ -1,38 +1,53 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{Result, anyhow};
-use futures_util::lock::Mutex;
-use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
+use futures_util::{StreamExt, lock::Mutex, stream};
+use starknet::core::types::{Felt, InvokeTransaction, Transaction};
+use starknet::providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport};
 use tokio::task::JoinSet;
 use tokio::{
-    sync::mpsc::UnboundedReceiver,
+    sync::{mpsc::UnboundedReceiver, watch},
     time::{interval, sleep},
 };
 
 use crate::bindings::liquidate::Liquidate;
-use crate::types::StarknetSingleOwnerAccount;
 use crate::{
     config::Config,
     services::oracle::LatestOraclePrices,
     storages::Storage,
     types::{
-        account::StarknetAccount,
-        position::{Position, PositionsMap},
+        account::{AccountLease, AccountPool, StarknetAccount},
+        competition::{LiquidabilityTracker, LiquidationEvent, ObservedLiquidation},
+        fee_strategy,
+        liquidation::{LiquidationOutcome, LiquidationRecord},
+        position::{LiquidationFactors, Position, PositionsMap},
+        position_state::{PositionLifecycle, PositionState},
+        price_history::PriceHistory,
+        route::{RouteCache, RouteProviders},
+        transaction::{TxFinalStatus, TxStatus, TxTracker},
     },
-    utils::{services::Service, wait_for_tx},
+    utils::{constants::FEE_TOKEN_TICKER, services::Service},
 };
 
 #[derive(Clone)]
 pub struct MonitoringService {
-    liquidate_contract: Arc<Liquidate<StarknetSingleOwnerAccount>>,
-    config: Config,
+    config: watch::Receiver<Config>,
     rpc_client: Arc<JsonRpcClient<HttpTransport>>,
-    account: Arc<StarknetAccount>,
+    accounts: AccountPool,
     positions_receiver: Arc<Mutex<UnboundedReceiver<(u64, Position)>>>,
+    liquidations_receiver: Arc<Mutex<UnboundedReceiver<LiquidationEvent>>>,
     positions: PositionsMap,
     latest_oracle_prices: LatestOraclePrices,
+    price_history: PriceHistory,
     storage: Arc<Mutex<Box<dyn Storage>>>,
     http_client: reqwest::Client,
+    route_cache: RouteCache,
+    liquidation_factors: LiquidationFactors,
+    tx_tracker: TxTracker,
+    liquidability: LiquidabilityTracker,
 }
 
 #[async_trait::async_trait]
@@ -53,54 +68,95 @@ impl Service for MonitoringService {
 
 impl MonitoringService {
     pub fn new(
-        config: Config,
+        config: watch::Receiver<Config>,
         rpc_client: Arc<JsonRpcClient<HttpTransport>>,
-        account: StarknetAccount,
+        accounts: AccountPool,
         positions_receiver: UnboundedReceiver<(u64, Position)>,
+        liquidations_receiver: UnboundedReceiver<LiquidationEvent>,
         latest_oracle_prices: LatestOraclePrices,
+        price_history: PriceHistory,
         storage: Box<dyn Storage>,
+        tx_tracker: TxTracker,
     ) -> MonitoringService {
         MonitoringService {
-            liquidate_contract: Arc::new(Liquidate::new(
-                config.liquidate_address,
-                account.0.clone(),
-            )),
             config,
             rpc_client,
-            account: Arc::new(account),
+            accounts,
             positions_receiver: Arc::new(Mutex::new(positions_receiver)),
+            liquidations_receiver: Arc::new(Mutex::new(liquidations_receiver)),
             positions: PositionsMap::from_storage(storage.as_ref()),
             latest_oracle_prices,
+            price_history,
             storage: Arc::new(Mutex::new(storage)),
             http_client: reqwest::Client::new(),
+            route_cache: RouteCache::default(),
+            liquidation_factors: LiquidationFactors::default(),
+            tx_tracker,
+            liquidability: LiquidabilityTracker::default(),
         }
     }
 
+    /// Returns the storage, shared with the services recording into it.
+    pub fn storage(&self) -> Arc<Mutex<Box<dyn Storage>>> {
+        self.storage.clone()
+    }
+
+    /// Returns the latest version of the config, that may have been hot-reloaded.
+    fn config(&self) -> Config {
+        self.config.borrow().clone()
+    }
+
+    /// Returns the route providers of the current config.
+    fn route_providers(&self, config: &Config) -> RouteProviders {
+        RouteProviders::from_config(config, &self.http_client, &self.rpc_client)
+    }
+
     /// Starts the monitoring service.
     pub async fn run_forever(&self) -> Result<()> {
         const CHECK_POSITIONS_INTERVAL: u64 = 3500;
+        const SAVE_INTERVAL: u64 = 60;
         let mut update_interval = interval(Duration::from_millis(CHECK_POSITIONS_INTERVAL));
+        let mut save_interval = interval(Duration::from_secs(SAVE_INTERVAL));
+        // The liquidations replayed by the indexer while catching up happened before the
+        // bot could race for them, and are not recorded.
+        let start_block = self.rpc_client.block_number().await?;
 
         loop {
             let mut receiver = self.positions_receiver.lock().await;
+            let mut liquidations = self.liquidations_receiver.lock().await;
 
             tokio::select! {
                 _ = update_interval.tick() => {
                     drop(receiver);
+                    drop(liquidations);
                     self.monitor_positions_liquidability().await?;
                 }
 
+                _ = save_interval.tick() => {
+                    drop(receiver);
+                    drop(liquidations);
+                    let mut storage = self.storage.lock().await;
+                    storage.save_price_history(self.price_history.snapshot()).await?;
+                    storage.save_positions(&self.positions.0).await?;
+                }
+
                 maybe_position = receiver.recv() => {
                     drop(receiver);
+                    drop(liquidations);
                     match maybe_position {
                         Some((block_number, mut new_position)) => {
                             new_position
-                                .update(&self.rpc_client, &self.config.singleton_address)
+                                .update(&self.rpc_client, &self.config().singleton_address)
                                 .await?;
-                            if new_position.is_closed() {
+                            let key = new_position.key();
+                            let known = self.positions.0.get(&key).map(|known| known.lifecycle.clone());
+                            if new_position.is_closed() && known.is_none() {
                                 continue;
                             }
-                            self.positions.0.insert(new_position.key(), new_position);
+                            if let Some(lifecycle) = known {
+                                new_position.lifecycle = lifecycle;
+                            }
+                            self.positions.0.insert(key, new_position);
                             self.storage.lock().await.save(&self.positions.0, block_number).await?;
                         }
                         None => {
@@ -108,77 +164,549 @@ impl MonitoringService {
                         }
                     }
                 }
+
+                Some(liquidation) = liquidations.recv() => {
+                    drop(receiver);
+                    drop(liquidations);
+                    if liquidation.block_number >= start_block {
+                        self.record_observed_liquidation(liquidation).await?;
+                    }
+                }
             }
         }
     }
 
-    /// Update all monitored positions and check if it's worth to liquidate any.
+    /// Refreshes all monitored positions and check if it's worth to liquidate any.
     async fn monitor_positions_liquidability(&self) -> Result<()> {
         if self.positions.0.is_empty() {
             return Ok(());
         }
 
+        let config = self.config();
+        self.positions
+            .refresh(&self.rpc_client, &config.singleton_address)
+            .await;
+        self.route_cache.prune(&config.route_cache);
+        // Block at which the positions are checked, to measure how fast they're liquidated.
+        let block_number = self.rpc_client.block_number().await.ok();
+        let now = unix_now();
         let position_keys: Vec<u64> = self.positions.0.iter().map(|entry| *entry.key()).collect();
-        let mut positions_to_delete = vec![];
+        let mut positions_to_liquidate = vec![];
 
         for key in position_keys {
-            if let Some(mut entry) = self.positions.0.get_mut(&key) {
-                let position = entry.value_mut();
+            // The position is checked from a copy: the shard of the map is not locked
+            // across the checks, as the liquidations followed in the background update it.
+            let Some(mut position) = self.positions.0.get(&key).map(|entry| entry.clone()) else {
+                continue;
+            };
+            if position.is_closed() {
+                self.update_lifecycle(key, PositionLifecycle::close);
+                continue;
+            }
+            // In flight or cooling down after a failure.
+            if !position.lifecycle.needs_check(now) {
+                continue;
+            }
 
-                if !position.is_liquidable(&self.latest_oracle_prices).await? {
-                    continue;
+            let is_liquidable = position
+                .check_liquidability(&config, &self.rpc_client, &self.latest_oracle_prices)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!(
+                        "[🔭 Monitoring] Could not check the liquidability of #{}: {e}",
+                        position.key()
+                    );
+                    false
+                });
+            if let Some(block_number) = block_number {
+                if is_liquidable {
+                    self.liquidability.seen_liquidable(key, block_number);
+                } else {
+                    self.liquidability.seen_healthy(key, block_number);
                 }
-                tracing::info!(
-                    "[🔭 Monitoring] Liquidatable position found #{}!",
+            }
+            if !is_liquidable {
+                // Routes of the almost liquidable positions are computed ahead of time,
+                // so they're ready if the position becomes liquidable.
+                let is_almost_liquidable = position
+                    .is_almost_liquidable(
+                        &self.latest_oracle_prices,
+                        &position.almost_liquidable_threshold(&config, &self.price_history),
+                    )
+                    .await;
+                if is_almost_liquidable {
+                    self.route_cache.prefetch(
+                        &self.route_providers(&config),
+                        &position,
+                        &self.latest_oracle_prices,
+                        &config.route_cache,
+                    );
+                    self.prefetch_withdraw_route(&config, &position);
+                }
+                self.update_lifecycle(key, |lifecycle| {
+                    lifecycle.observe(false, is_almost_liquidable, now)
+                });
+                continue;
+            }
+            // Ready for the next attempts, if this one goes without withdraw swap.
+            self.prefetch_withdraw_route(&config, &position);
+            self.update_lifecycle(key, |lifecycle| {
+                lifecycle.observe(true, false, now);
+                position.lifecycle = lifecycle.clone();
+            });
+            if !position.lifecycle.should_liquidate() {
+                tracing::debug!(
+                    "[🔭 Monitoring] Not liquidating position #{}, {}",
+                    position.key(),
+                    position.lifecycle.state
+                );
+                continue;
+            }
+            tracing::info!(
+                "[🔭 Monitoring] Liquidatable position found #{}!",
+                position.key()
+            );
+            if let Err(e) = self.latest_oracle_prices.ensure_reliable(
+                &[&position.collateral.name, &position.debt.name],
+                &config.oracle,
+            ) {
+                tracing::warn!(
+                    "[🔭 Monitoring] ⚠️ Not liquidating position #{}, unreliable price: {e}",
                     position.key()
                 );
+                continue;
+            }
 
-                tracing::info!("[🔭 Monitoring] 🔫 Liquidating position...");
-                if let Err(e) = self.liquidate_position(position).await {
-                    if e.to_string().contains("not-undercollateralized") {
-                        tracing::warn!("[🔭 Monitoring] Position was not under collateralized!");
-                        positions_to_delete.push(key);
-                        continue;
-                    } else {
-                        tracing::error!(
-                            error = %e,
-                            "[🔭 Monitoring] 😨 Could not liquidate position #{:x}",
-                            position.key(),
-                        );
-                    }
-                }
+            positions_to_liquidate.push(position);
+        }
 
-                position
-                    .update(&self.rpc_client, &self.config.singleton_address)
-                    .await?;
-            }
+        if !positions_to_liquidate.is_empty() && !self.accounts.can_pay_fees(&config.accounts) {
+            tracing::warn!(
+                "[🔭 Monitoring] ⏸️ Not liquidating {} position(s), no liquidator account can pay the fees",
+                positions_to_liquidate.len()
+            );
+            return Ok(());
         }
 
-        for to_delete in positions_to_delete {
-            self.positions.0.remove(&to_delete);
+        // The liquidations are sent concurrently, at most one per liquidator account.
+        let results: Vec<(u64, Result<()>)> = stream::iter(positions_to_liquidate)
+            .map(|position| {
+                let config = &config;
+                async move {
+                    tracing::info!("[🔭 Monitoring] 🔫 Liquidating position...");
+                    let result = self.liquidate_position(config, &position).await;
+                    (position.key(), result)
+                }
+            })
+            .buffer_unordered(self.accounts.len().max(1))
+            .collect()
+            .await;
+
+        // The failures are retried after a backoff, set by the position lifecycle.
+        for (key, result) in results {
+            let Err(e) = result else {
+                continue;
+            };
+            if e.to_string().contains("not-undercollateralized") {
+                tracing::warn!("[🔭 Monitoring] Position was not under collateralized!");
+            } else {
+                tracing::error!(
+                    error = %e,
+                    "[🔭 Monitoring] 😨 Could not liquidate position #{:x}",
+                    key,
+                );
+            }
         }
 
         Ok(())
     }
 
     /// Check if a position is liquidable, computes the profitability and if it's worth it
-    /// liquidate it.
-    async fn liquidate_position(&self, position: &Position) -> Result<()> {
+    /// liquidate it, with the next available liquidator account.
+    /// The transaction is followed in the background until it's final, the account
+    /// staying busy meanwhile. Every attempt is recorded into the storage, whatever its outcome.
+    async fn liquidate_position(&self, config: &Config, position: &Position) -> Result<()> {
         let started_at = std::time::Instant::now();
-        let liquidation_tx = position
-            .get_vesu_liquidate_tx(
-                &self.liquidate_contract,
-                &self.http_client,
-                &self.account.account_address(),
-            )
-            .await?;
-        let tx_hash = self.account.execute_txs(&[liquidation_tx]).await?;
-        wait_for_tx(&self.rpc_client, tx_hash).await?;
+        let account = self
+            .accounts
+            .acquire(&config.accounts)
+            .await
+            .ok_or_else(|| anyhow!("No liquidator account available"))?;
+        self.liquidability.mark_attempted(position.key());
+        let now = unix_now();
+        self.update_lifecycle(position.key(), |lifecycle| lifecycle.start_attempt(now));
+        let tx_hash = match self.send_liquidation_tx(config, position, &account).await {
+            Ok(tx_hash) => tx_hash,
+            Err(e) => {
+                self.fail_liquidation(config, position.key(), e.to_string());
+                let record = LiquidationRecord::failed(
+                    position,
+                    None,
+                    e.to_string(),
+                    &self.latest_oracle_prices,
+                )
+                .with_account(account.account_address());
+                self.storage.lock().await.add_liquidation(record).await?;
+                return Err(e);
+            }
+        };
         tracing::info!(
-            "[🔭 Monitoring] ✅ Liquidated position #{}! (tx {tx_hash:#064x}) - ⌛ {:?}",
-            position.key(),
-            started_at.elapsed()
+            "[🔭 Monitoring] Sent the liquidation of #{} (tx {tx_hash:#064x})",
+            position.key()
         );
+
+        self.update_lifecycle(position.key(), |lifecycle| lifecycle.sent(tx_hash));
+        let service = self.clone();
+        let (config, position) = (config.clone(), position.clone());
+        tokio::spawn(async move {
+            if let Err(e) = service
+                .finalize_liquidation(&config, &position, account, tx_hash, started_at)
+                .await
+            {
+                tracing::error!(
+                    error = %e,
+                    "[🔭 Monitoring] 😨 Could not liquidate position #{:x}",
+                    position.key(),
+                );
+            }
+        });
         Ok(())
     }
+
+    /// Follows a liquidation transaction until it's final, records its outcome & fee
+    /// and refreshes the liquidated position. A dropped liquidation is sent again by
+    /// the next checks if the position is still liquidable, a failed one after a backoff.
+    async fn finalize_liquidation(
+        &self,
+        config: &Config,
+        position: &Position,
+        account: AccountLease,
+        tx_hash: Felt,
+        started_at: std::time::Instant,
+    ) -> Result<()> {
+        let mut dropped = false;
+        let record = match self.tx_tracker.follow(tx_hash, &config.transactions).await {
+            Ok(TxFinalStatus::Included(receipt)) => LiquidationRecord::from_receipt(
+                position,
+                config,
+                &receipt,
+                &self.latest_oracle_prices,
+            ),
+            Ok(TxFinalStatus::Dropped) => {
+                tracing::warn!(
+                    "[🔭 Monitoring] Liquidation of #{} (tx {tx_hash:#064x}) dropped, it will be sent again if still liquidable",
+                    position.key()
+                );
+                dropped = true;
+                LiquidationRecord::failed(
+                    position,
+                    Some(tx_hash),
+                    TxStatus::Dropped.to_string(),
+                    &self.latest_oracle_prices,
+                )
+            }
+            Err(e) => LiquidationRecord::failed(
+                position,
+                Some(tx_hash),
+                e.to_string(),
+                &self.latest_oracle_prices,
+            ),
+        };
+        let record = record.with_account(account.account_address());
+        self.accounts.record_fee(
+            account.account_address(),
+            record.timestamp,
+            record.fee_paid.clone(),
+        );
+        drop(account);
+        let outcome = record.outcome.clone();
+        match &outcome {
+            LiquidationOutcome::Succeeded => {
+                self.update_lifecycle(position.key(), PositionLifecycle::succeed)
+            }
+            LiquidationOutcome::Failed(_) if dropped => {
+                self.update_lifecycle(position.key(), PositionLifecycle::drop_attempt)
+            }
+            LiquidationOutcome::Reverted(reason) | LiquidationOutcome::Failed(reason) => {
+                self.fail_liquidation(config, position.key(), reason.clone())
+            }
+        }
+        let mut storage = self.storage.lock().await;
+        storage.add_liquidation(record).await?;
+        storage.save_positions(&self.positions.0).await?;
+        drop(storage);
+        // The liquidated positions are refreshed, so the next checks see their new state.
+        if !matches!(outcome, LiquidationOutcome::Failed(_)) {
+            self.refresh_position(config, position.key()).await?;
+        }
+
+        match outcome {
+            LiquidationOutcome::Succeeded => {
+                tracing::info!(
+                    "[🔭 Monitoring] ✅ Liquidated position #{}! (tx {tx_hash:#064x}) - ⌛ {:?}",
+                    position.key(),
+                    started_at.elapsed()
+                );
+                Ok(())
+            }
+            LiquidationOutcome::Reverted(reason) if reason.contains("not-undercollateralized") => {
+                tracing::warn!("[🔭 Monitoring] Position was not under collateralized!");
+                Ok(())
+            }
+            LiquidationOutcome::Reverted(reason) => Err(anyhow!(
+                "Transaction {tx_hash:#064x} has been rejected/reverted: {reason}"
+            )),
+            LiquidationOutcome::Failed(reason) => Err(anyhow!(reason)),
+        }
+    }
+
+    /// Records the liquidation of a monitored position, matched against the blocks
+    /// during which the bot saw it liquidable & its own liquidations.
+    async fn record_observed_liquidation(&self, liquidation: LiquidationEvent) -> Result<()> {
+        let key = liquidation.position.key();
+        let window = self.liquidability.take(key);
+        if window.is_none() && !self.positions.0.contains_key(&key) {
+            return Ok(());
+        }
+
+        let liquidator = self.liquidation_sender(&liquidation).await;
+        let mut storage = self.storage.lock().await;
+        let in_flight_tx = self
+            .positions
+            .0
+            .get(&key)
+            .and_then(|position| position.lifecycle.in_flight_tx());
+        let ours = self.accounts.addresses().contains(&liquidator)
+            || liquidation.tx_hash.is_some_and(|tx_hash| {
+                in_flight_tx == Some(tx_hash)
+                    || storage
+                        .get_liquidations()
+                        .iter()
+                        .any(|record| record.tx_hash == Some(tx_hash))
+            });
+        let record = ObservedLiquidation::new(&liquidation, liquidator, window, ours);
+        if !ours {
+            let delay = match record.block_delay {
+                Some(delay) => format!("{delay} block(s) after we saw it liquidable"),
+                None => "before we saw it liquidable".to_string(),
+            };
+            tracing::warn!(
+                "[🔭 Monitoring] 🏁 Position #{} liquidated by {:#x} {delay} (attempted: {})",
+                key,
+                record.liquidator,
+                record.attempted
+            );
+        }
+        storage.add_observed_liquidation(record).await
+    }
+
+    /// Pre-computes in the background the route converting the collateral left after
+    /// the liquidation of a position, once its liquidation factor & liquidation route
+    /// are known.
+    fn prefetch_withdraw_route(&self, config: &Config, position: &Position) {
+        let Some(liquidation_factor) = self.liquidation_factors.get(position) else {
+            let liquidation_factors = self.liquidation_factors.clone();
+            let rpc_client = self.rpc_client.clone();
+            let config = config.clone();
+            let position = position.clone();
+            tokio::spawn(async move {
+                if let Err(e) = liquidation_factors
+                    .get_or_fetch(&position, &config, rpc_client)
+                    .await
+                {
+                    tracing::debug!(
+                        "[🔭 Monitoring] Could not fetch the liquidation factor of #{}: {e}",
+                        position.key()
+                    );
+                }
+            });
+            return;
+        };
+        // The collateral left is net of the fees of the liquidation route, pre-computed
+        // along with it.
+        let Some(liquidation_route) =
+            self.route_cache
+                .get(position, &self.latest_oracle_prices, &config.route_cache)
+        else {
+            return;
+        };
+        match position.withdraw_plan(
+            config,
+            &liquidation_factor,
+            &liquidation_route,
+            &self.latest_oracle_prices,
+        ) {
+            Ok(Some(plan)) => self.route_cache.prefetch_withdraw(
+                &self.route_providers(config),
+                &plan,
+                &config.route_cache,
+            ),
+            Ok(None) => {}
+            Err(e) => tracing::debug!(
+                "[🔭 Monitoring] No withdraw route to pre-compute for #{}: {e}",
+                position.key()
+            ),
+        }
+    }
+
+    /// Returns the sender of a liquidation transaction: the caller of the singleton is
+    /// the Liquidate periphery for the liquidations routed through it. Falls back to
+    /// the caller if the transaction can't be fetched.
+    async fn liquidation_sender(&self, liquidation: &LiquidationEvent) -> Felt {
+        let Some(tx_hash) = liquidation.tx_hash else {
+            return liquidation.caller;
+        };
+        match self.rpc_client.get_transaction_by_hash(tx_hash).await {
+            Ok(Transaction::Invoke(InvokeTransaction::V1(tx))) => tx.sender_address,
+            Ok(Transaction::Invoke(InvokeTransaction::V3(tx))) => tx.sender_address,
+            Ok(_) => liquidation.caller,
+            Err(e) => {
+                tracing::warn!(
+                    "[🔭 Monitoring] Could not fetch the sender of liquidation {tx_hash:#064x}: {e}"
+                );
+                liquidation.caller
+            }
+        }
+    }
+
+    /// Updates the amounts of a monitored position from the chain, keeping its
+    /// lifecycle. A closed position is marked so by the next checks.
+    async fn refresh_position(&self, config: &Config, key: u64) -> Result<()> {
+        let Some(mut position) = self.positions.0.get(&key).map(|entry| entry.clone()) else {
+            return Ok(());
+        };
+        position
+            .update(&self.rpc_client, &config.singleton_address)
+            .await?;
+        if let Some(mut entry) = self.positions.0.get_mut(&key) {
+            entry.collateral = position.collateral;
+            entry.debt = position.debt;
+            entry.lltv = position.lltv;
+        }
+        Ok(())
+    }
+
+    /// Applies a transition to the lifecycle of a monitored position.
+    fn update_lifecycle(&self, key: u64, transition: impl FnOnce(&mut PositionLifecycle)) {
+        if let Some(mut position) = self.positions.0.get_mut(&key) {
+            transition(&mut position.lifecycle);
+        }
+    }
+
+    /// Records a failed liquidation attempt, backing off or quarantining the position.
+    fn fail_liquidation(&self, config: &Config, key: u64, error: String) {
+        let now = unix_now();
+        self.update_lifecycle(key, |lifecycle| {
+            lifecycle.fail(error, now, &config.retries);
+            match lifecycle.state {
+                PositionState::Quarantined => tracing::error!(
+                    "[🔭 Monitoring] 🚧 Position #{key} quarantined after {} failed liquidations in a row",
+                    lifecycle.consecutive_failures
+                ),
+                PositionState::Failed { until } => tracing::warn!(
+                    "[🔭 Monitoring] Liquidation of #{key} failed {} time(s) in a row, retrying in {}s",
+                    lifecycle.consecutive_failures,
+                    until.saturating_sub(now)
+                ),
+                _ => {}
+            }
+        });
+    }
+
+    /// Builds & sends the liquidation transaction of a position from the provided account.
+    async fn send_liquidation_tx(
+        &self,
+        config: &Config,
+        position: &Position,
+        account: &StarknetAccount,
+    ) -> Result<Felt> {
+        let liquidate_contract =
+            Arc::new(Liquidate::new(config.liquidate_address, account.0.clone()));
+        let route_providers = self.route_providers(config);
+        // The liquidation factor is fetched once, along with the route, for both the
+        // withdraw swap & the expected profit.
+        let (route, liquidation_factor) = tokio::join!(
+            self.route_cache.get_or_fetch(
+                &route_providers,
+                position,
+                &self.latest_oracle_prices,
+                &config.route_cache,
+            ),
+            self.liquidation_factors
+                .get_or_fetch(position, config, self.rpc_client.clone()),
+        );
+        let route = route?;
+        // The liquidation goes through even if the collateral can't be converted, and
+        // doesn't wait for a withdraw route that has not been pre-computed.
+        let withdraw_swap = match &liquidation_factor {
+            Ok(liquidation_factor) => position.withdraw_plan(
+                config,
+                liquidation_factor,
+                &route,
+                &self.latest_oracle_prices,
+            ),
+            Err(e) => Err(anyhow!("unknown liquidation factor: {e}")),
+        }
+        .and_then(|plan| {
+            plan.map(|plan| {
+                self.route_cache
+                    .get_withdraw_swap(&plan, &config.route_cache)
+                    .ok_or_else(|| anyhow!("the withdraw route is not pre-computed yet"))
+            })
+            .transpose()
+        })
+        .unwrap_or_else(|e| {
+            tracing::warn!(
+                "[🔭 Monitoring] Keeping the collateral of #{}, no withdraw swap: {e}",
+                position.key()
+            );
+            None
+        });
+        let liquidation_tx = position.get_vesu_liquidate_tx(
+            &liquidate_contract,
+            route,
+            withdraw_swap,
+            &account.account_address(),
+        );
+
+        // Without the expected profit, the fee is bid uncapped.
+        let expected_profit = match liquidation_factor.and_then(|factor| {
+            position.estimated_liquidation_profit(&factor, &self.latest_oracle_prices)
+        }) {
+            Ok(profit) => Some(profit),
+            Err(e) => {
+                tracing::warn!(
+                    "[🔭 Monitoring] Could not estimate the profit of #{}, the fee is not capped: {e}",
+                    position.key()
+                );
+                None
+            }
+        };
+        let estimate = account.estimate_gas(&[liquidation_tx.clone()]).await?;
+        let fee_bid = fee_strategy::bid(
+            &config.fees,
+            &estimate,
+            expected_profit.as_ref(),
+            self.latest_oracle_prices
+                .price_of(FEE_TOKEN_TICKER)
+                .as_ref(),
+        )?;
+        tracing::debug!(
+            "[🔭 Monitoring] Bidding up to {} {FEE_TOKEN_TICKER} (tip {}) for #{}",
+            fee_bid.max_fee_amount().round(4),
+            fee_bid.tip,
+            position.key()
+        );
+        account
+            .execute_txs_with_bid(&[liquidation_tx], &fee_bid)
+            .await
+    }
+}
+
+/// Current unix timestamp, in seconds.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
 }
@@ -1,25 +1,27 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use bigdecimal::BigDecimal;
-use dashmap::DashMap;
-use futures_util::future::join_all;
-use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
-use starknet::core::utils::{cairo_short_string_to_felt, get_selector_from_name};
+use chrono::Utc;
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{BlockId, BlockTag, Felt};
 use starknet::providers::jsonrpc::HttpTransport;
 use starknet::providers::{JsonRpcClient, Provider};
+use tokio::sync::watch;
 use tokio::task::JoinSet;
 
-use crate::config::Config;
-use crate::utils::conversions::hex_str_to_big_decimal;
+use crate::config::{Config, OracleRules};
+use crate::types::price_history::{PriceHistory, PricePoint};
+use crate::types::price_source::PriceSources;
 use crate::utils::services::Service;
 
-const LST_ASSETS: [&str; 3] = ["xstrk", "sstrk", "kstrk"];
-
 /// Aggregations possible using the Pragma Oracle contract.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AggregationMode {
+    #[default]
     Median,
     Mean,
     ConversionRate,
@@ -35,25 +37,100 @@ impl AggregationMode {
     }
 }
 
+/// Price in dollars of an asset, with the Pragma metadata of the price.
+#[derive(Debug, Default, Clone)]
+pub struct OraclePrice {
+    pub price: BigDecimal,
+    /// Unix timestamp of the last update of the price.
+    pub timestamp: u64,
+    /// Number of sources aggregated in the price.
+    pub num_sources: u32,
+    /// Largest relative deviation of a price source to the aggregated price.
+    pub deviation: BigDecimal,
+}
+
+impl OraclePrice {
+    /// Ensures that the price of an asset is recent enough & aggregated from enough
+    /// sources that agree.
+    pub fn ensure_reliable(&self, ticker: &str, rules: &OracleRules) -> Result<()> {
+        let age = (Utc::now().timestamp() as u64).saturating_sub(self.timestamp);
+        anyhow::ensure!(
+            age <= rules.max_price_age_of(ticker).as_secs(),
+            "price is stale (last updated {age}s ago)"
+        );
+        anyhow::ensure!(
+            self.num_sources >= rules.min_sources,
+            "price has {} source(s), {} needed",
+            self.num_sources,
+            rules.min_sources
+        );
+        anyhow::ensure!(
+            self.deviation <= rules.max_deviation,
+            "price sources deviate by {:.2}%",
+            &self.deviation * BigDecimal::from(100)
+        );
+        Ok(())
+    }
+}
+
 /// Map contaning the price in dollars for a list of monitored assets.
 #[derive(Default, Clone)]
-pub struct LatestOraclePrices(pub Arc<DashMap<String, BigDecimal>>);
+pub struct LatestOraclePrices(pub Arc<DashMap<String, OraclePrice>>);
 
 impl LatestOraclePrices {
     pub fn from_config(config: &Config) -> Self {
         let prices = DashMap::new();
-        for asset in config.assets.iter() {
-            prices.insert(asset.ticker.to_lowercase(), BigDecimal::default());
+        for asset in config.asset_map.iter() {
+            prices.insert(asset.ticker.to_lowercase(), OraclePrice::default());
         }
         LatestOraclePrices(Arc::new(prices))
     }
+
+    /// Returns the latest price in dollars of an asset.
+    pub fn price_of(&self, ticker: &str) -> Option<BigDecimal> {
+        self.0
+            .get(&ticker.to_lowercase())
+            .map(|oracle_price| oracle_price.price.clone())
+    }
+
+    /// Ensures that the latest prices of the provided assets can be trusted.
+    pub fn ensure_reliable(&self, tickers: &[&str], rules: &OracleRules) -> Result<()> {
+        for ticker in tickers {
+            let oracle_price = self
+                .0
+                .get(&ticker.to_lowercase())
+                .ok_or_else(|| anyhow!("no price for {ticker}"))?;
+            oracle_price
+                .ensure_reliable(ticker, rules)
+                .map_err(|e| anyhow!("{ticker} {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Fetches once the current prices of all the configured assets.
+    pub async fn fetch(
+        config: &Config,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    ) -> Result<Self> {
+        let latest_prices = Self::from_config(config);
+        OracleService::new(config, rpc_client.clone(), latest_prices.clone())?
+            .update_prices()
+            .await?;
+        Ok(latest_prices)
+    }
 }
 
 #[derive(Clone)]
 pub struct OracleService {
-    pragma_address: Felt,
     rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    http_client: reqwest::Client,
     latest_prices: LatestOraclePrices,
+    sources: PriceSources,
+    rules: OracleRules,
+    config_updates: Option<watch::Receiver<Config>>,
+    /// Assets whose price sources currently deviate too much.
+    deviating_assets: Arc<DashSet<String>>,
+    price_history: PriceHistory,
 }
 
 #[async_trait::async_trait]
@@ -71,29 +148,65 @@ impl Service for OracleService {
 
 impl OracleService {
     pub fn new(
-        pragma_address: Felt,
+        config: &Config,
         rpc_client: Arc<JsonRpcClient<HttpTransport>>,
         latest_prices: LatestOraclePrices,
-    ) -> Self {
-        Self {
-            pragma_address,
+    ) -> Result<Self> {
+        let http_client = reqwest::Client::new();
+        Ok(Self {
+            sources: PriceSources::from_config(config, &http_client, &rpc_client, &latest_prices)?,
+            rules: config.oracle.clone(),
             rpc_client,
+            http_client,
             latest_prices,
-        }
+            config_updates: None,
+            deviating_assets: Arc::new(DashSet::new()),
+            price_history: PriceHistory::default(),
+        })
+    }
+
+    /// Records the fetched prices into the provided history.
+    pub fn with_price_history(mut self, price_history: PriceHistory) -> Self {
+        self.price_history = price_history;
+        self
+    }
+
+    /// Follows the hot-reloads of the config to update the price sources.
+    pub fn with_config_updates(mut self, config_updates: watch::Receiver<Config>) -> Self {
+        self.config_updates = Some(config_updates);
+        self
     }
 
     /// Starts the oracle service that will fetch the latest oracle prices every
     /// PRICES_UPDATE_INTERVAL seconds.
-    pub async fn run_forever(self) -> Result<()> {
+    pub async fn run_forever(mut self) -> Result<()> {
         const PRICES_UPDATE_INTERVAL: u64 = 3;
         let sleep_duration = Duration::from_secs(PRICES_UPDATE_INTERVAL);
         loop {
+            if let Some(config_updates) = &self.config_updates {
+                let config = config_updates.borrow().clone();
+                match PriceSources::from_config(
+                    &config,
+                    &self.http_client,
+                    &self.rpc_client,
+                    &self.latest_prices,
+                ) {
+                    Ok(sources) => {
+                        self.sources = sources;
+                        self.rules = config.oracle;
+                    }
+                    Err(e) => tracing::error!(
+                        "[🔮 Oracle] Invalid price sources, keeping the current ones: {e}"
+                    ),
+                }
+            }
             self.update_prices().await?;
             tokio::time::sleep(sleep_duration).await;
         }
     }
 
-    /// Update all the monitored assets with their latest USD price asynchronously.
+    /// Update all the monitored assets with their latest USD price. The on-chain
+    /// prices are all read in batches on the same block.
     async fn update_prices(&self) -> Result<()> {
         let assets: Vec<String> = self
             .latest_prices
@@ -102,51 +215,149 @@ impl OracleService {
             .map(|entry| entry.key().clone())
             .collect();
 
-        let fetch_tasks = assets.into_iter().map(|asset| async move {
-            let price = self.get_price_in_dollars(&asset).await;
-            (asset, price)
-        });
-
-        let results = join_all(fetch_tasks).await;
+        let block_id = match self.rpc_client.block_number().await {
+            Ok(block_number) => BlockId::Number(block_number),
+            Err(e) => {
+                tracing::warn!("[🔮 Oracle] Could not fetch the latest block number: {e}");
+                BlockId::Tag(BlockTag::PreConfirmed)
+            }
+        };
+        let results = self.sources.get_prices(&assets, block_id).await;
 
-        for (asset, price_result) in results {
-            if let Ok(price) = price_result {
-                self.latest_prices.0.insert(asset, price);
+        for (asset, price_result) in assets.into_iter().zip(results) {
+            match price_result {
+                Ok(price) => {
+                    self.check_deviation(&asset, &price).await;
+                    if let BlockId::Number(block_number) = block_id {
+                        let point = PricePoint {
+                            price: price.price.clone(),
+                            timestamp: Utc::now().timestamp() as u64,
+                            block_number,
+                        };
+                        self.price_history
+                            .record(&asset, point, self.rules.history_size);
+                    }
+                    self.latest_prices.0.insert(asset, price);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "[🔮 Oracle] Could not fetch the price of {asset}, keeping the previous one: {e}"
+                    );
+                }
             }
         }
 
         Ok(())
     }
 
-    async fn get_price_in_dollars(&self, base_asset: &str) -> Result<BigDecimal> {
-        let pair = format!("{}/USD", base_asset.to_ascii_uppercase());
+    pub async fn get_price_in_dollars(&self, base_asset: &str) -> Result<BigDecimal> {
+        Ok(self.get_price(base_asset).await?.price)
+    }
 
-        let aggregation_mode = if LST_ASSETS.contains(&base_asset) {
-            AggregationMode::ConversionRate
-        } else {
-            AggregationMode::Median
-        };
+    /// Fetches the price of an asset from all the sources, aggregated.
+    pub async fn get_price(&self, base_asset: &str) -> Result<OraclePrice> {
+        self.sources.get_price(base_asset).await
+    }
 
-        let price_request = FunctionCall {
-            contract_address: self.pragma_address,
-            entry_point_selector: get_selector_from_name("get_data")?,
-            calldata: vec![
-                Felt::ZERO,
-                cairo_short_string_to_felt(&pair)?,
-                aggregation_mode.to_felt(),
-            ],
+    /// Alerts when the price sources of an asset start deviating too much, which
+    /// pauses its liquidations, and logs when they agree again.
+    async fn check_deviation(&self, asset: &str, price: &OraclePrice) {
+        if price.deviation > self.rules.max_deviation {
+            if self.deviating_assets.insert(asset.to_string()) {
+                let message = format!(
+                    "Price sources of {asset} deviate by {:.2}%, its liquidations are paused",
+                    &price.deviation * BigDecimal::from(100)
+                );
+                tracing::error!("[🔮 Oracle] 🚨 {message}");
+                self.send_alert(&message).await;
+            }
+        } else if self.deviating_assets.remove(asset).is_some() {
+            tracing::info!(
+                "[🔮 Oracle] Price sources of {asset} agree again, its liquidations are resumed"
+            );
+        }
+    }
+
+    /// Posts an alert to the webhook of the config, if any.
+    async fn send_alert(&self, message: &str) {
+        let Some(webhook_url) = &self.rules.alert_webhook_url else {
+            return;
         };
+        let result = self
+            .http_client
+            .post(webhook_url)
+            .timeout(Duration::from_secs(5))
+            .json(&serde_json::json!({ "text": message }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            tracing::warn!("[🔮 Oracle] Could not send the alert: {e}");
+        }
+    }
+}
 
-        let call_result = self
-            .rpc_client
-            .call(price_request, BlockId::Tag(BlockTag::PreConfirmed))
-            .await?;
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr, time::Duration};
+
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+
+    use crate::config::OracleRules;
+
+    use super::{LatestOraclePrices, OraclePrice};
+
+    fn rules() -> OracleRules {
+        OracleRules {
+            max_price_age: Duration::from_secs(60),
+            max_price_ages: HashMap::from([("xstrk".to_string(), Duration::from_secs(600))]),
+            min_sources: 2,
+            sources: vec![],
+            max_deviation: BigDecimal::from_str("0.02").unwrap(),
+            alert_webhook_url: None,
+            history_size: 2,
+        }
+    }
+
+    fn price(age_secs: u64, num_sources: u32, deviation: &str) -> OraclePrice {
+        OraclePrice {
+            price: BigDecimal::from(2000),
+            timestamp: Utc::now().timestamp() as u64 - age_secs,
+            num_sources,
+            deviation: BigDecimal::from_str(deviation).unwrap(),
+        }
+    }
 
-        let asset_price = hex_str_to_big_decimal(
-            &call_result[0].to_hex_string(),
-            call_result[1].to_bigint().try_into()?,
+    #[test]
+    fn test_price_reliability() {
+        let rules = rules();
+        assert!(price(10, 2, "0.02").ensure_reliable("eth", &rules).is_ok());
+        // Stale, unless the asset allows older prices.
+        assert!(price(120, 2, "0").ensure_reliable("eth", &rules).is_err());
+        assert!(price(120, 2, "0").ensure_reliable("XSTRK", &rules).is_ok());
+        // Not enough sources.
+        assert!(price(10, 1, "0").ensure_reliable("eth", &rules).is_err());
+        // Sources that deviate too much.
+        assert!(
+            price(10, 2, "0.021")
+                .ensure_reliable("eth", &rules)
+                .is_err()
         );
+    }
+
+    #[test]
+    fn test_latest_prices_reliability() {
+        let rules = rules();
+        let prices = LatestOraclePrices::default();
+        prices.0.insert("eth".to_string(), price(10, 3, "0"));
+        prices.0.insert("usdc".to_string(), price(10, 1, "0"));
 
-        Ok(asset_price)
+        assert!(prices.ensure_reliable(&["ETH"], &rules).is_ok());
+        let error = prices
+            .ensure_reliable(&["ETH", "USDC"], &rules)
+            .unwrap_err();
+        assert!(error.to_string().starts_with("USDC"));
+        assert!(prices.ensure_reliable(&["STRK"], &rules).is_err());
     }
 }
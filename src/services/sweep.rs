@@ -0,0 +1,301 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::Utc;
+use futures_util::lock::Mutex;
+use starknet::{
+    core::types::{BlockId, BlockTag, Call, ExecutionResult, Felt, FunctionCall},
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+use tokio::{sync::watch, task::JoinSet};
+
+use crate::{
+    config::{Config, RetainedToken, SweepRules},
+    storages::Storage,
+    types::{
+        account::AccountPool,
+        fee_strategy::FeeBid,
+        sweep::{SweepOutcome, SweepRecord, SweptToken},
+        transaction::{TxFinalStatus, TxStatus, TxTracker},
+    },
+    utils::{
+        batch::batch_calls,
+        constants::FEE_TOKEN_DECIMALS,
+        erc20::{balance_of_request, parse_balance, transfer_call},
+        services::Service,
+    },
+};
+
+/// Periodically transfers the balances of the liquidator accounts above the retained
+/// amounts to the treasury, so that the proceeds don't pile up in the hot accounts.
+#[derive(Clone)]
+pub struct SweepService {
+    config: watch::Receiver<Config>,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    accounts: AccountPool,
+    storage: Arc<Mutex<Box<dyn Storage>>>,
+    tx_tracker: TxTracker,
+}
+
+#[async_trait::async_trait]
+impl Service for SweepService {
+    async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("🧹 Sweep service started");
+            service.run_forever().await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl SweepService {
+    pub fn new(
+        config: watch::Receiver<Config>,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        accounts: AccountPool,
+        storage: Arc<Mutex<Box<dyn Storage>>>,
+        tx_tracker: TxTracker,
+    ) -> Self {
+        Self {
+            config,
+            rpc_client,
+            accounts,
+            storage,
+            tx_tracker,
+        }
+    }
+
+    /// Sweeps the accounts every `interval`.
+    pub async fn run_forever(self) -> Result<()> {
+        loop {
+            let rules = self.config.borrow().sweep.clone();
+            if let Some(treasury) = rules.treasury {
+                for address in self.accounts.addresses() {
+                    if let Err(e) = self.sweep_account(address, treasury, &rules).await {
+                        tracing::warn!("[🧹 Sweep] Could not sweep {address:#x}: {e}");
+                    }
+                }
+            }
+            tokio::time::sleep(rules.interval).await;
+        }
+    }
+
+    /// Transfers the excess balances of an account to the treasury, in one multicall.
+    async fn sweep_account(&self, address: Felt, treasury: Felt, rules: &SweepRules) -> Result<()> {
+        // The account is skipped while it is liquidating, to not compete for its nonce.
+        let Some(account) = self.accounts.lease(&address) else {
+            tracing::debug!("[🧹 Sweep] {address:#x} is busy, sweeping it next time");
+            return Ok(());
+        };
+
+        let requests: Vec<FunctionCall> = rules
+            .retained
+            .iter()
+            .map(|token| balance_of_request(token.address, address))
+            .collect();
+        let balances = batch_calls(
+            &self.rpc_client,
+            &requests,
+            BlockId::Tag(BlockTag::PreConfirmed),
+        )
+        .await;
+
+        let balances: Vec<(&RetainedToken, BigDecimal)> = rules
+            .retained
+            .iter()
+            .zip(balances)
+            .filter_map(|(token, balance)| {
+                match balance.and_then(|response| parse_balance(&response, token.decimals)) {
+                    Ok(balance) => Some((token, balance)),
+                    Err(e) => {
+                        tracing::warn!(
+                            "[🧹 Sweep] Could not fetch the {} balance of {address:#x}: {e}",
+                            token.ticker
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        // The fee of the sweep is paid from the fee token balance, which must stay
+        // above its retained amount: the max fee is kept on the account.
+        let (fee_token, fee_rules) = {
+            let config = self.config.borrow();
+            (config.accounts.fee_token, config.fees.clone())
+        };
+        let (_, calls) = sweep_calls(&balances, treasury, fee_token, &BigDecimal::from(0));
+        if calls.is_empty() {
+            return Ok(());
+        }
+        let estimate = account.estimate_gas(&calls).await?;
+        let bid = FeeBid::from_estimate(
+            &estimate,
+            fee_rules.gas_multiplier,
+            fee_rules.gas_price_multiplier,
+            0,
+        );
+        let (tokens, calls) = sweep_calls(&balances, treasury, fee_token, &bid.max_fee_amount());
+        if calls.is_empty() {
+            return Ok(());
+        }
+
+        let tx_rules = self.config.borrow().transactions.clone();
+        let mut record = SweepRecord {
+            account: address,
+            treasury,
+            tx_hash: None,
+            block_number: None,
+            timestamp: Utc::now().timestamp() as u64,
+            outcome: SweepOutcome::Succeeded,
+            tokens,
+        };
+        let submitted = account.execute_txs_with_bid(&calls, &bid).await;
+        match submitted {
+            Ok(tx_hash) => {
+                record.tx_hash = Some(tx_hash);
+                // The account can liquidate again once the sweep is in the mempool.
+                let release = move || drop(account);
+                match self
+                    .tx_tracker
+                    .follow_with(tx_hash, &tx_rules, release)
+                    .await
+                {
+                    Ok(TxFinalStatus::Included(receipt)) => {
+                        record.block_number = Some(receipt.block.block_number());
+                        if let ExecutionResult::Reverted { reason } =
+                            receipt.receipt.execution_result()
+                        {
+                            record.outcome = SweepOutcome::Reverted(reason.clone());
+                        }
+                        self.accounts.record_fee(
+                            address,
+                            record.timestamp,
+                            BigDecimal::new(
+                                receipt.receipt.actual_fee().amount.to_bigint(),
+                                FEE_TOKEN_DECIMALS,
+                            ),
+                        );
+                    }
+                    Ok(TxFinalStatus::Dropped) => {
+                        record.outcome = SweepOutcome::Failed(TxStatus::Dropped.to_string())
+                    }
+                    Err(e) => record.outcome = SweepOutcome::Failed(e.to_string()),
+                }
+            }
+            Err(e) => record.outcome = SweepOutcome::Failed(e.to_string()),
+        }
+
+        let swept: Vec<String> = record
+            .tokens
+            .iter()
+            .map(|token| format!("{} {}", token.amount, token.ticker))
+            .collect();
+        match &record.outcome {
+            SweepOutcome::Succeeded => tracing::info!(
+                "[🧹 Sweep] Swept {} from {address:#x} to the treasury",
+                swept.join(", ")
+            ),
+            outcome => tracing::warn!(
+                "[🧹 Sweep] Sweep of {} from {address:#x} {outcome}",
+                swept.join(", ")
+            ),
+        }
+        self.storage.lock().await.add_sweep(record).await
+    }
+}
+
+/// Returns the transfers of the balances above the retained amounts to the treasury,
+/// `max_fee` being kept on top of the retained amount of the fee token.
+fn sweep_calls(
+    balances: &[(&RetainedToken, BigDecimal)],
+    treasury: Felt,
+    fee_token: Option<Felt>,
+    max_fee: &BigDecimal,
+) -> (Vec<SweptToken>, Vec<Call>) {
+    balances
+        .iter()
+        .filter_map(|(token, balance)| {
+            let balance = if Some(token.address) == fee_token {
+                balance - max_fee
+            } else {
+                balance.clone()
+            };
+            let amount = excess(&balance, token)?;
+            let call = transfer_call(token.address, treasury, &amount, token.decimals);
+            let swept = SweptToken {
+                ticker: token.ticker.clone(),
+                address: token.address,
+                amount,
+            };
+            Some((swept, call))
+        })
+        .unzip()
+}
+
+/// Returns the balance above the retained amount of a token, rounded down to its
+/// decimals, or None if there is nothing to sweep.
+fn excess(balance: &BigDecimal, token: &RetainedToken) -> Option<BigDecimal> {
+    let excess = (balance - &token.amount).with_scale_round(token.decimals, RoundingMode::Down);
+    (excess > BigDecimal::from(0)).then_some(excess)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use crate::config::RetainedToken;
+
+    use super::{excess, sweep_calls};
+
+    fn token(ticker: &str, address: Felt, decimals: i64, amount: &str) -> RetainedToken {
+        RetainedToken {
+            ticker: ticker.to_string(),
+            address,
+            decimals,
+            amount: BigDecimal::from_str(amount).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_excess() {
+        let token = token("USDC", Felt::ONE, 6, "100.1");
+        assert_eq!(
+            excess(&BigDecimal::from_str("150.3456789").unwrap(), &token),
+            Some(BigDecimal::from_str("50.245678").unwrap())
+        );
+        assert_eq!(excess(&BigDecimal::from(100), &token), None);
+        assert_eq!(
+            excess(&BigDecimal::from_str("100.1").unwrap(), &token),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sweep_keeps_the_max_fee() {
+        let strk = token("STRK", Felt::ONE, 18, "50");
+        let usdc = token("USDC", Felt::TWO, 6, "0");
+        let balances = vec![
+            (&strk, BigDecimal::from(60)),
+            (&usdc, BigDecimal::from(100)),
+        ];
+        let max_fee = BigDecimal::from_str("0.5").unwrap();
+
+        let (tokens, calls) = sweep_calls(&balances, Felt::THREE, Some(Felt::ONE), &max_fee);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(tokens[0].amount, BigDecimal::from_str("9.5").unwrap());
+        assert_eq!(tokens[1].amount, BigDecimal::from(100));
+
+        // Nothing left to sweep once the fee is kept.
+        let max_fee = BigDecimal::from(10);
+        let (tokens, _) = sweep_calls(&balances, Felt::THREE, Some(Felt::ONE), &max_fee);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].ticker, "USDC");
+    }
+}
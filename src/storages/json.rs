@@ -1,36 +1,107 @@
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
 use dashmap::DashMap;
+use serde::Serialize;
 use serde_json::Value;
 use std::collections::HashMap;
 
-use crate::types::position::{self, Position};
+use crate::types::{
+    competition::ObservedLiquidation,
+    liquidation::LiquidationRecord,
+    position::{self, Position},
+    price_history::PricePoint,
+    sweep::SweepRecord,
+};
 
 use super::{Storage, StoredData};
 
 pub struct JsonStorage {
     file_path: PathBuf,
+    /// File of the price history, next to the main file.
+    price_history_path: PathBuf,
     data: StoredData,
 }
 
 impl JsonStorage {
     pub fn new(path: &str) -> Self {
+        let file_path = PathBuf::from(path);
         JsonStorage {
-            file_path: PathBuf::from(path),
+            price_history_path: file_path.with_extension("prices.json"),
+            file_path,
             data: StoredData::default(),
         }
     }
+
+    /// Writes the current data into the json file.
+    fn persist(&self) -> Result<()> {
+        write_json(&self.file_path, &self.data)
+    }
+
+    /// Reads the price history from its file, or from the main file where it was
+    /// stored before.
+    fn load_price_history(&self, json_value: &Value) -> HashMap<String, Vec<PricePoint>> {
+        if self.price_history_path.exists() {
+            return File::open(&self.price_history_path)
+                .ok()
+                .and_then(|file| serde_json::from_reader(file).ok())
+                .unwrap_or_default();
+        }
+        match json_value.get("price_history") {
+            Some(value) => serde_json::from_value(value.clone()).unwrap_or_default(),
+            None => HashMap::new(),
+        }
+    }
+}
+
+/// Writes the value into a temporary file renamed over the json file, so that the
+/// file is never left half written.
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    let tmp_path = path.with_extension("json.tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(json.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
 }
 
 #[async_trait::async_trait]
 impl Storage for JsonStorage {
     async fn load(&mut self) -> Result<(u64, HashMap<u64, Position>)> {
         if !self.file_path.exists() {
-            self.data = StoredData::new(0, HashMap::new());
+            self.data = StoredData::default();
+            self.data.price_history = self.load_price_history(&Value::Null);
             return Ok(self.data.as_tuple());
         }
         let json_value: Value = serde_json::from_reader(File::open(self.file_path.clone())?)?;
+        let liquidations: Vec<LiquidationRecord> = match json_value.get("liquidations") {
+            Some(Value::Array(records)) => records
+                .iter()
+                .filter_map(|value| serde_json::from_value(value.clone()).ok())
+                .collect(),
+            _ => vec![],
+        };
+        let sweeps: Vec<SweepRecord> = match json_value.get("sweeps") {
+            Some(Value::Array(records)) => records
+                .iter()
+                .filter_map(|value| serde_json::from_value(value.clone()).ok())
+                .collect(),
+            _ => vec![],
+        };
+        let observed_liquidations: Vec<ObservedLiquidation> =
+            match json_value.get("observed_liquidations") {
+                Some(Value::Array(records)) => records
+                    .iter()
+                    .filter_map(|value| serde_json::from_value(value.clone()).ok())
+                    .collect(),
+                _ => vec![],
+            };
+        let price_history = self.load_price_history(&json_value);
         let last_block_indexed: u64 = match json_value.get("last_block_indexed") {
             Some(Value::Number(lbi)) => {
                 if lbi.is_u64() {
@@ -43,7 +114,10 @@ impl Storage for JsonStorage {
         };
         // no need to go further if last block indexed is genesis
         if last_block_indexed == 0 {
-            self.data = StoredData::new(0, HashMap::new());
+            self.data = StoredData::new(0, HashMap::new(), liquidations);
+            self.data.price_history = price_history;
+            self.data.sweeps = sweeps;
+            self.data.observed_liquidations = observed_liquidations;
             return Ok(self.data.as_tuple());
         }
         let positions: HashMap<u64, Position> = match json_value.get("positions") {
@@ -57,7 +131,10 @@ impl Storage for JsonStorage {
                 .collect(),
             _ => HashMap::new(),
         };
-        self.data = StoredData::new(last_block_indexed, positions);
+        self.data = StoredData::new(last_block_indexed, positions, liquidations);
+        self.data.price_history = price_history;
+        self.data.sweeps = sweeps;
+        self.data.observed_liquidations = observed_liquidations;
         Ok(self.data.as_tuple())
     }
 
@@ -66,25 +143,110 @@ impl Storage for JsonStorage {
         positions: &DashMap<u64, position::Position>,
         last_block_indexed: u64,
     ) -> Result<()> {
-        let file_path = self.file_path.clone();
         // Convert DashMap to HashMap for serialization
-        let positions_map: HashMap<u64, position::Position> = positions
+        self.data.positions = positions
             .iter()
             .map(|entry| (*entry.key(), entry.value().clone()))
             .collect();
+        self.data.last_block_indexed = last_block_indexed;
+        self.persist()
+    }
 
-        let map = StoredData {
-            last_block_indexed,
-            positions: positions_map,
-        };
+    fn get_positions(&self) -> HashMap<u64, Position> {
+        self.data.positions.clone()
+    }
+
+    async fn save_positions(&mut self, positions: &DashMap<u64, position::Position>) -> Result<()> {
+        self.data.positions = positions
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect();
+        self.persist()
+    }
+
+    async fn add_liquidation(&mut self, record: LiquidationRecord) -> Result<()> {
+        self.data.liquidations.push(record);
+        self.persist()
+    }
 
-        let json = serde_json::to_string_pretty(&map)?;
-        let mut file = File::create(file_path)?;
-        file.write_all(json.as_bytes())?;
+    fn get_liquidations(&self) -> Vec<LiquidationRecord> {
+        self.data.liquidations.clone()
+    }
+
+    async fn save_price_history(
+        &mut self,
+        history: HashMap<String, Vec<PricePoint>>,
+    ) -> Result<()> {
+        write_json(&self.price_history_path, &history)?;
+        self.data.price_history = history;
         Ok(())
     }
 
-    fn get_positions(&self) -> HashMap<u64, Position> {
-        self.data.positions.clone()
+    fn get_price_history(&self) -> HashMap<String, Vec<PricePoint>> {
+        self.data.price_history.clone()
+    }
+
+    async fn add_sweep(&mut self, record: SweepRecord) -> Result<()> {
+        self.data.sweeps.push(record);
+        self.persist()
+    }
+
+    fn get_sweeps(&self) -> Vec<SweepRecord> {
+        self.data.sweeps.clone()
+    }
+
+    async fn add_observed_liquidation(&mut self, record: ObservedLiquidation) -> Result<()> {
+        self.data.observed_liquidations.push(record);
+        self.persist()
+    }
+
+    fn get_observed_liquidations(&self) -> Vec<ObservedLiquidation> {
+        self.data.observed_liquidations.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use dashmap::DashMap;
+
+    use bigdecimal::BigDecimal;
+
+    use crate::{storages::Storage, types::price_history::PricePoint};
+
+    use super::JsonStorage;
+
+    #[tokio::test]
+    async fn test_price_history_file() {
+        let dir = std::env::temp_dir().join(format!("vesu-storage-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("vesu.json");
+        let path = path.to_str().unwrap();
+
+        let points = HashMap::from([(
+            "eth".to_string(),
+            vec![PricePoint {
+                price: BigDecimal::from(2000),
+                timestamp: 1_700_000_000,
+                block_number: 1,
+            }],
+        )]);
+
+        let mut storage = JsonStorage::new(path);
+        storage.load().await.unwrap();
+        storage.save_price_history(points.clone()).await.unwrap();
+        storage.save(&DashMap::new(), 42).await.unwrap();
+
+        let main_file = fs::read_to_string(path).unwrap();
+        assert!(!main_file.contains("price_history"));
+        assert!(dir.join("vesu.prices.json").exists());
+        assert!(!dir.join("vesu.json.tmp").exists());
+
+        let mut storage = JsonStorage::new(path);
+        let (last_block_indexed, _) = storage.load().await.unwrap();
+        assert_eq!(last_block_indexed, 42);
+        assert_eq!(storage.get_price_history(), points);
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -5,19 +5,39 @@ use std::collections::HashMap;
 use anyhow::Result;
 use dashmap::DashMap;
 
-use crate::types::position::{self, Position};
+use crate::types::{
+    competition::ObservedLiquidation,
+    liquidation::LiquidationRecord,
+    position::{self, Position},
+    price_history::PricePoint,
+    sweep::SweepRecord,
+};
 
 #[derive(serde::Serialize, Default)]
 struct StoredData {
     last_block_indexed: u64,
     positions: HashMap<u64, Position>,
+    liquidations: Vec<LiquidationRecord>,
+    /// Saved into its own file, being larger & saved less often than the rest.
+    #[serde(skip)]
+    price_history: HashMap<String, Vec<PricePoint>>,
+    sweeps: Vec<SweepRecord>,
+    observed_liquidations: Vec<ObservedLiquidation>,
 }
 
 impl StoredData {
-    pub fn new(last_block_indexed: u64, positions: HashMap<u64, Position>) -> Self {
+    pub fn new(
+        last_block_indexed: u64,
+        positions: HashMap<u64, Position>,
+        liquidations: Vec<LiquidationRecord>,
+    ) -> Self {
         StoredData {
             last_block_indexed,
             positions,
+            liquidations,
+            price_history: HashMap::new(),
+            sweeps: vec![],
+            observed_liquidations: vec![],
         }
     }
     pub fn as_tuple(&self) -> (u64, HashMap<u64, Position>) {
@@ -34,4 +54,24 @@ pub trait Storage: Send + Sync {
         last_block_indexed: u64,
     ) -> Result<()>;
     fn get_positions(&self) -> HashMap<u64, Position>;
+    /// Stores the positions along with their lifecycle, keeping the last block indexed.
+    async fn save_positions(&mut self, positions: &DashMap<u64, position::Position>) -> Result<()>;
+    /// Stores the record of a liquidation attempt.
+    async fn add_liquidation(&mut self, record: LiquidationRecord) -> Result<()>;
+    /// Returns all the recorded liquidation attempts, oldest first.
+    fn get_liquidations(&self) -> Vec<LiquidationRecord>;
+    /// Stores the price history of the monitored assets, by ticker, apart from the
+    /// rest of the data.
+    async fn save_price_history(&mut self, history: HashMap<String, Vec<PricePoint>>)
+    -> Result<()>;
+    /// Returns the stored price history of the monitored assets, by ticker.
+    fn get_price_history(&self) -> HashMap<String, Vec<PricePoint>>;
+    /// Stores the record of a sweep to the treasury.
+    async fn add_sweep(&mut self, record: SweepRecord) -> Result<()>;
+    /// Returns all the recorded sweeps, oldest first.
+    fn get_sweeps(&self) -> Vec<SweepRecord>;
+    /// Stores the record of a liquidation of a monitored position, by the bot or not.
+    async fn add_observed_liquidation(&mut self, record: ObservedLiquidation) -> Result<()>;
+    /// Returns all the recorded liquidations of the monitored positions, oldest first.
+    fn get_observed_liquidations(&self) -> Vec<ObservedLiquidation>;
 }
@@ -1,74 +1,129 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::VecDeque,
+    ops::Deref,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use bigdecimal::BigDecimal;
-use bigdecimal::num_bigint::ToBigInt;
+use chrono::Utc;
+use dashmap::{DashMap, DashSet};
 use starknet::{
-    accounts::{Account, ExecutionEncoding, SingleOwnerAccount},
-    core::{
-        chain_id,
-        types::{BlockId, BlockTag, Call, Felt},
-    },
-    providers::{JsonRpcClient, jsonrpc::HttpTransport},
-    signers::{LocalWallet, SigningKey},
+    accounts::{Account, ConnectedAccount, ExecutionEncoding, SingleOwnerAccount},
+    core::types::{BlockId, BlockTag, Call, Felt},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+    signers::{LocalWallet, Signer, SigningKey},
 };
 
 use crate::{
-    cli::{NetworkName, RunCmd},
-    utils::constants::VESU_RESPONSE_DECIMALS,
+    cli::{
+        RunCmd,
+        account::{AccountKey, AccountParams},
+    },
+    config::AccountsRules,
+    types::{
+        fee_strategy::{FeeBid, GasEstimate},
+        liquidation::LiquidationRecord,
+        signer::{LiquidatorSigner, RemoteSigner},
+    },
+    utils::{
+        constants::FEE_TOKEN_DECIMALS,
+        erc20::{balance_of_request, parse_balance},
+    },
 };
 
-pub struct StarknetAccount(
-    pub Arc<SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, LocalWallet>>,
+/// Starknet account, generic over the signer of its transactions. The liquidator
+/// accounts use either a local key or a remote signing service.
+pub struct StarknetAccount<S = LiquidatorSigner>(
+    pub Arc<SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, S>>,
 );
 
 impl StarknetAccount {
-    /// Creates a StarknetAccount from the CLI args
-    pub fn from_cli(
+    /// Creates a StarknetAccount from the first account of the account CLI params
+    pub fn from_params(
         rpc_client: Arc<JsonRpcClient<HttpTransport>>,
-        run_cmd: RunCmd,
+        chain_id: Felt,
+        account_params: AccountParams,
     ) -> Result<StarknetAccount> {
-        let mut builder = StarknetAccountBuilder::default();
-
-        builder = match run_cmd.network {
-            NetworkName::Mainnet => builder.on_mainnet(),
-            NetworkName::Sepolia => builder.on_sepolia(),
-        };
-
-        builder = builder
-            .as_account(run_cmd.account_params.account_address)
-            .with_provider(rpc_client);
+        Self::all_from_params(rpc_client, chain_id, account_params)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No liquidator account provided"))
+    }
 
-        if let Some(private_key) = run_cmd.account_params.private_key {
-            builder.from_secret(private_key)
-        } else {
-            builder.from_keystore(
-                run_cmd.account_params.keystore_path.unwrap(),
-                &run_cmd.account_params.keystore_password.unwrap(),
-            )
-        }
+    /// Creates a StarknetAccount for every account of the account CLI params
+    pub fn all_from_params(
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        chain_id: Felt,
+        account_params: AccountParams,
+    ) -> Result<Vec<StarknetAccount>> {
+        account_params
+            .accounts()
+            .into_iter()
+            .map(|(account_address, key)| {
+                let builder = StarknetAccountBuilder::default()
+                    .with_chain_id(chain_id)
+                    .as_account(account_address)
+                    .with_provider(rpc_client.clone());
+                match key {
+                    AccountKey::PrivateKey(private_key) => builder.from_secret(private_key),
+                    AccountKey::Keystore { path, password } => {
+                        builder.from_keystore(path, &password)
+                    }
+                    AccountKey::Remote { url, auth_token } => builder
+                        .from_remote_signer(RemoteSigner::new(url, auth_token, account_address)),
+                }
+            })
+            .collect()
     }
+}
 
+impl<S> StarknetAccount<S>
+where
+    S: Signer + Send + Sync,
+{
     /// Returns the account_address of the Account.
     pub fn account_address(&self) -> Felt {
         self.0.address()
     }
 
-    /// Simulate a set of TXs and return the estimation of the fee necessary
-    /// to execute them.
-    pub async fn estimate_fees_cost(&self, txs: &[Call]) -> Result<BigDecimal> {
+    /// Returns the balance of the account in the token used to pay the fees.
+    pub async fn fee_balance(&self, fee_token: Felt) -> Result<BigDecimal> {
+        let balance = self
+            .0
+            .provider()
+            .call(
+                balance_of_request(fee_token, self.account_address()),
+                BlockId::Tag(BlockTag::PreConfirmed),
+            )
+            .await?;
+        parse_balance(&balance, FEE_TOKEN_DECIMALS)
+    }
+
+    /// Simulate a set of TXs and return the gas they consume & the gas prices.
+    pub async fn estimate_gas(&self, txs: &[Call]) -> Result<GasEstimate> {
         let estimation = self.0.execute_v3(txs.to_vec()).estimate_fee().await?;
-        Ok(BigDecimal::new(
-            estimation.overall_fee.to_bigint().unwrap(),
-            VESU_RESPONSE_DECIMALS,
-        ))
+        Ok(GasEstimate::from(&estimation))
     }
 
-    /// Executes a set of transactions and returns the transaction hash.
-    pub async fn execute_txs(&self, txs: &[Call]) -> Result<Felt> {
+    /// Executes a set of transactions with the provided resource bounds & tip and
+    /// returns the transaction hash.
+    pub async fn execute_txs_with_bid(&self, txs: &[Call], bid: &FeeBid) -> Result<Felt> {
         let res = self
             .0
             .execute_v3(txs.to_vec())
+            .l1_gas(bid.l1_gas)
+            .l1_gas_price(bid.l1_gas_price)
+            .l2_gas(bid.l2_gas)
+            .l2_gas_price(bid.l2_gas_price)
+            .l1_data_gas(bid.l1_data_gas)
+            .l1_data_gas_price(bid.l1_data_gas_price)
+            .tip(bid.tip)
             .send()
             .await
             .map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
@@ -76,6 +131,200 @@ impl StarknetAccount {
     }
 }
 
+/// Liquidator accounts the liquidations are spread across. Each account has its own
+/// nonce, so several liquidations can be sent at once, one per account.
+#[derive(Clone)]
+pub struct AccountPool {
+    accounts: Arc<Vec<Arc<StarknetAccount>>>,
+    /// Addresses of the accounts currently sending a liquidation.
+    busy: Arc<DashSet<Felt>>,
+    /// Index of the account tried first by the next liquidation.
+    next: Arc<AtomicUsize>,
+    /// Latest known fee token balance of the accounts.
+    fee_balances: Arc<DashMap<Felt, BigDecimal>>,
+    /// Fees paid by the accounts, as (unix timestamp, fee), oldest first.
+    fees_paid: Arc<DashMap<Felt, VecDeque<(u64, BigDecimal)>>>,
+}
+
+impl AccountPool {
+    pub fn new(accounts: Vec<StarknetAccount>) -> Self {
+        Self {
+            accounts: Arc::new(accounts.into_iter().map(Arc::new).collect()),
+            busy: Arc::new(DashSet::new()),
+            next: Arc::new(AtomicUsize::new(0)),
+            fee_balances: Arc::new(DashMap::new()),
+            fees_paid: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Creates the pool of all the accounts of the CLI args
+    pub fn from_cli(
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        chain_id: Felt,
+        run_cmd: RunCmd,
+    ) -> Result<AccountPool> {
+        let accounts =
+            StarknetAccount::all_from_params(rpc_client, chain_id, run_cmd.account_params)?;
+        Ok(Self::new(accounts))
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Returns the accounts of the pool.
+    pub fn iter(&self) -> impl Iterator<Item = &StarknetAccount> {
+        self.accounts.iter().map(Arc::as_ref)
+    }
+
+    /// Returns the addresses of the accounts of the pool.
+    pub fn addresses(&self) -> Vec<Felt> {
+        self.accounts
+            .iter()
+            .map(|account| account.account_address())
+            .collect()
+    }
+
+    /// Returns the latest known fee token balance of an account.
+    pub fn fee_balance(&self, address: &Felt) -> Option<BigDecimal> {
+        self.fee_balances
+            .get(address)
+            .map(|balance| balance.value().clone())
+    }
+
+    pub fn set_fee_balance(&self, address: Felt, balance: BigDecimal) {
+        self.fee_balances.insert(address, balance);
+    }
+
+    /// Records a fee paid by an account, deducted from its known balance.
+    pub fn record_fee(&self, address: Felt, timestamp: u64, fee: BigDecimal) {
+        if let Some(mut balance) = self.fee_balances.get_mut(&address) {
+            *balance -= &fee;
+        }
+        let mut fees = self.fees_paid.entry(address).or_default();
+        let position = fees.partition_point(|(paid_at, _)| *paid_at <= timestamp);
+        fees.insert(position, (timestamp, fee));
+    }
+
+    /// Records the fees paid by the liquidations of the accounts of the pool.
+    pub fn record_fees(&self, records: &[LiquidationRecord]) {
+        for record in records {
+            if let Some(account) = record.account.filter(|account| {
+                self.accounts
+                    .iter()
+                    .any(|known| known.account_address() == *account)
+            }) {
+                self.fees_paid
+                    .entry(account)
+                    .or_default()
+                    .push_back((record.timestamp, record.fee_paid.clone()));
+            }
+        }
+        for mut fees in self.fees_paid.iter_mut() {
+            fees.make_contiguous().sort_by_key(|(paid_at, _)| *paid_at);
+        }
+    }
+
+    /// Returns the fees paid by an account during the last `window`, forgetting the
+    /// older ones.
+    pub fn fees_paid_since(&self, address: &Felt, window: Duration) -> BigDecimal {
+        let since = (Utc::now().timestamp() as u64).saturating_sub(window.as_secs());
+        let Some(mut fees) = self.fees_paid.get_mut(address) else {
+            return BigDecimal::from(0);
+        };
+        while fees.front().is_some_and(|(paid_at, _)| *paid_at < since) {
+            fees.pop_front();
+        }
+        fees.iter().map(|(_, fee)| fee).sum()
+    }
+
+    /// Whether at least one account can pay the fees of a liquidation, i.e its known
+    /// balance isn't below the minimum fee balance.
+    pub fn can_pay_fees(&self, rules: &AccountsRules) -> bool {
+        rules.fee_token.is_none()
+            || self.iter().any(|account| {
+                self.fee_balance(&account.account_address())
+                    .is_none_or(|balance| balance >= rules.min_fee_balance)
+            })
+    }
+
+    /// Takes a given account of the pool, if it is idle.
+    pub fn lease(&self, address: &Felt) -> Option<AccountLease> {
+        let account = self
+            .accounts
+            .iter()
+            .find(|account| account.account_address() == *address)?;
+        self.busy.insert(*address).then(|| AccountLease {
+            account: account.clone(),
+            busy: self.busy.clone(),
+        })
+    }
+
+    /// Takes the next idle account, in turn, holding at least the minimum fee balance.
+    /// The account is given back to the pool when the returned lease is dropped.
+    /// Returns None if all the accounts are busy or can't pay the fees.
+    pub async fn acquire(&self, rules: &AccountsRules) -> Option<AccountLease> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for i in 0..self.accounts.len() {
+            let account = &self.accounts[(start + i) % self.accounts.len()];
+            let address = account.account_address();
+            if !self.busy.insert(address) {
+                continue;
+            }
+            let lease = AccountLease {
+                account: account.clone(),
+                busy: self.busy.clone(),
+            };
+            let Some(fee_token) = rules.fee_token else {
+                return Some(lease);
+            };
+            let balance = match self.fee_balance(&address) {
+                Some(balance) => Ok(balance),
+                None => account.fee_balance(fee_token).await,
+            };
+            match balance {
+                Ok(balance) if balance < rules.min_fee_balance => {
+                    tracing::warn!(
+                        "[🔭 Monitoring] Skipping account {address:#x}, its fee balance is too low ({balance})"
+                    );
+                }
+                Ok(_) => return Some(lease),
+                Err(e) => {
+                    tracing::warn!(
+                        "[🔭 Monitoring] Could not fetch the fee balance of {address:#x}, using it anyway: {e}"
+                    );
+                    return Some(lease);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Account taken from an [`AccountPool`], given back to the pool when dropped.
+pub struct AccountLease {
+    account: Arc<StarknetAccount>,
+    busy: Arc<DashSet<Felt>>,
+}
+
+impl Deref for AccountLease {
+    type Target = StarknetAccount;
+
+    fn deref(&self) -> &Self::Target {
+        &self.account
+    }
+}
+
+impl Drop for AccountLease {
+    fn drop(&mut self) {
+        self.busy.remove(&self.account.account_address());
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct StarknetAccountBuilder {
     account_address: Option<Felt>,
@@ -88,15 +337,11 @@ impl StarknetAccountBuilder {
         StarknetAccountBuilder::default()
     }
 
-    pub fn on_mainnet(mut self) -> Self {
-        self.chain_id = Some(chain_id::MAINNET);
+    pub fn with_chain_id(mut self, chain_id: Felt) -> Self {
+        self.chain_id = Some(chain_id);
         self
     }
 
-    pub fn on_sepolia(mut self) -> Self {
-        self.chain_id = Some(chain_id::SEPOLIA);
-        self
-    }
     pub fn as_account(mut self, account_address: Felt) -> Self {
         self.account_address = Some(account_address);
         self
@@ -110,7 +355,7 @@ impl StarknetAccountBuilder {
     pub fn from_secret(self, private_key: Felt) -> Result<StarknetAccount> {
         let signing_key = SigningKey::from_secret_scalar(private_key);
         let signer = LocalWallet::from(signing_key);
-        self.build(signer)
+        self.build(LiquidatorSigner::Local(signer))
     }
 
     pub fn from_keystore(
@@ -120,10 +365,18 @@ impl StarknetAccountBuilder {
     ) -> Result<StarknetAccount> {
         let signing_key = SigningKey::from_keystore(keystore_path, keystore_password)?;
         let signer = LocalWallet::from(signing_key);
-        self.build(signer)
+        self.build(LiquidatorSigner::Local(signer))
+    }
+
+    /// Delegates the signature of the transactions to a remote signing service.
+    pub fn from_remote_signer(self, signer: RemoteSigner) -> Result<StarknetAccount> {
+        self.build(LiquidatorSigner::Remote(signer))
     }
 
-    fn build(self, signer: LocalWallet) -> Result<StarknetAccount> {
+    pub fn build<S>(self, signer: S) -> Result<StarknetAccount<S>>
+    where
+        S: Signer + Send + Sync,
+    {
         let mut account = SingleOwnerAccount::new(
             self.rpc_client.unwrap(),
             signer,
@@ -137,3 +390,55 @@ impl StarknetAccountBuilder {
         Ok(StarknetAccount(Arc::new(account)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use bigdecimal::BigDecimal;
+    use starknet::{
+        core::types::Felt,
+        providers::{JsonRpcClient, jsonrpc::HttpTransport},
+    };
+    use url::Url;
+
+    use crate::config::AccountsRules;
+
+    use super::{AccountPool, StarknetAccount, StarknetAccountBuilder};
+
+    fn account(address: u64) -> StarknetAccount {
+        let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+            Url::parse("http://localhost:5050").unwrap(),
+        )));
+        StarknetAccountBuilder::new()
+            .with_chain_id(Felt::ONE)
+            .as_account(Felt::from(address))
+            .with_provider(rpc_client)
+            .from_secret(Felt::from(address))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_accounts_rotation() {
+        let pool = AccountPool::new(vec![account(1), account(2)]);
+        let rules = AccountsRules {
+            fee_token: None,
+            min_fee_balance: BigDecimal::from(1),
+            low_fee_balance: BigDecimal::from(10),
+            min_runway: Duration::from_secs(86400),
+            fee_usage_window: Duration::from_secs(86400),
+            balance_check_interval: Duration::from_secs(60),
+        };
+
+        let first = pool.acquire(&rules).await.unwrap();
+        let second = pool.acquire(&rules).await.unwrap();
+        assert_ne!(first.account_address(), second.account_address());
+        // Both accounts are busy.
+        assert!(pool.acquire(&rules).await.is_none());
+
+        let released = first.account_address();
+        drop(first);
+        let third = pool.acquire(&rules).await.unwrap();
+        assert_eq!(third.account_address(), released);
+    }
+}
@@ -40,14 +40,14 @@ mod tests {
     use bigdecimal::BigDecimal;
     use starknet::core::types::Felt;
 
-    use crate::{cli::NetworkName, config::Config};
+    use crate::config::Config;
 
     use super::Asset;
 
     #[test]
     fn test_asset_from_address() {
         let config = Config::new(
-            NetworkName::Mainnet,
+            &"mainnet".parse().unwrap(),
             crate::config::LiquidationMode::Full,
             &PathBuf::from("./config.yaml"),
         )
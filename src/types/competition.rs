@@ -0,0 +1,262 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use super::position::Position;
+
+/// Number of blocks a position is remembered as liquidable after it was last seen so,
+/// for its liquidation event, indexed after the position refresh, to still match it.
+const LIQUIDABLE_MEMORY_BLOCKS: u64 = 20;
+
+/// Blocks during which a monitored position has been seen liquidable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidableWindow {
+    pub first_block: u64,
+    pub last_block: u64,
+    /// Whether the bot tried to liquidate the position meanwhile.
+    pub attempted: bool,
+}
+
+/// Thread-safe windows of liquidability of the monitored positions, by position key.
+#[derive(Clone, Default)]
+pub struct LiquidabilityTracker(Arc<DashMap<u64, LiquidableWindow>>);
+
+impl LiquidabilityTracker {
+    pub fn seen_liquidable(&self, key: u64, block_number: u64) {
+        self.0
+            .entry(key)
+            .and_modify(|window| window.last_block = window.last_block.max(block_number))
+            .or_insert(LiquidableWindow {
+                first_block: block_number,
+                last_block: block_number,
+                attempted: false,
+            });
+    }
+
+    /// Forgets the window of a position no longer liquidable, once it has not been
+    /// seen liquidable for a while.
+    pub fn seen_healthy(&self, key: u64, block_number: u64) {
+        self.0.remove_if(&key, |_, window| {
+            window.last_block + LIQUIDABLE_MEMORY_BLOCKS < block_number
+        });
+    }
+
+    pub fn mark_attempted(&self, key: u64) {
+        if let Some(mut window) = self.0.get_mut(&key) {
+            window.attempted = true;
+        }
+    }
+
+    /// Removes & returns the window of a position, once it has been liquidated.
+    pub fn take(&self, key: u64) -> Option<LiquidableWindow> {
+        self.0.remove(&key).map(|(_, window)| window)
+    }
+}
+
+/// `LiquidatePosition` event emitted by the singleton, sent by the indexer.
+#[derive(Debug, Clone)]
+pub struct LiquidationEvent {
+    pub position: Position,
+    /// Address that called the singleton: the Liquidate periphery for the liquidations
+    /// routed through it, not the liquidator.
+    pub caller: Felt,
+    pub tx_hash: Option<Felt>,
+    pub block_number: u64,
+}
+
+/// Record of the liquidation of a monitored position, by the bot or a competitor.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObservedLiquidation {
+    pub position_key: u64,
+    pub pool_id: Felt,
+    pub user_address: Felt,
+    pub collateral: String,
+    pub debt: String,
+    /// Sender of the liquidation transaction, or the caller of the singleton if it
+    /// could not be resolved.
+    pub liquidator: Felt,
+    /// Address that called the singleton.
+    #[serde(default)]
+    pub caller: Felt,
+    pub tx_hash: Option<Felt>,
+    pub block_number: u64,
+    /// Unix timestamp (in seconds) at which the liquidation was indexed.
+    pub timestamp: u64,
+    /// Block at which the bot first saw the position liquidable, if it did.
+    pub first_seen_liquidable: Option<u64>,
+    /// Blocks between the bot first seeing the position liquidable & its liquidation.
+    pub block_delay: Option<u64>,
+    /// Whether the bot tried to liquidate the position.
+    pub attempted: bool,
+    /// Whether the liquidation was sent by the bot.
+    pub ours: bool,
+}
+
+impl ObservedLiquidation {
+    pub fn new(
+        event: &LiquidationEvent,
+        liquidator: Felt,
+        window: Option<LiquidableWindow>,
+        ours: bool,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            position_key: event.position.key(),
+            pool_id: event.position.pool_id,
+            user_address: event.position.user_address,
+            collateral: event.position.collateral.name.clone(),
+            debt: event.position.debt.name.clone(),
+            liquidator,
+            caller: event.caller,
+            tx_hash: event.tx_hash,
+            block_number: event.block_number,
+            timestamp,
+            first_seen_liquidable: window.map(|window| window.first_block),
+            block_delay: window.map(|window| event.block_number.saturating_sub(window.first_block)),
+            attempted: ours || window.is_some_and(|window| window.attempted),
+            ours,
+        }
+    }
+}
+
+/// Aggregated outcome of the races for the liquidations of the monitored positions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompetitionSummary {
+    pub liquidations: usize,
+    /// Liquidations sent by the bot.
+    pub won: usize,
+    /// Liquidations sent by a competitor.
+    pub lost: usize,
+    /// Lost liquidations the bot tried to send.
+    pub lost_attempted: usize,
+    /// Lost liquidations of positions the bot never saw liquidable.
+    pub lost_unseen: usize,
+    /// Block delays of the lost liquidations seen liquidable, sorted.
+    pub lost_delays: Vec<u64>,
+}
+
+impl CompetitionSummary {
+    fn add(&mut self, record: &ObservedLiquidation) {
+        self.liquidations += 1;
+        if record.ours {
+            self.won += 1;
+            return;
+        }
+        self.lost += 1;
+        if record.attempted {
+            self.lost_attempted += 1;
+        }
+        match record.block_delay {
+            Some(delay) => {
+                let index = self.lost_delays.partition_point(|other| *other <= delay);
+                self.lost_delays.insert(index, delay);
+            }
+            None => self.lost_unseen += 1,
+        }
+    }
+
+    /// Races over all the records.
+    pub fn total(records: &[ObservedLiquidation]) -> CompetitionSummary {
+        let mut summary = CompetitionSummary::default();
+        records.iter().for_each(|record| summary.add(record));
+        summary
+    }
+
+    /// Races grouped by the address that liquidated.
+    pub fn by_liquidator(records: &[ObservedLiquidation]) -> BTreeMap<String, CompetitionSummary> {
+        let mut groups: BTreeMap<String, CompetitionSummary> = BTreeMap::new();
+        for record in records {
+            groups
+                .entry(format!("{:#x}", record.liquidator))
+                .or_default()
+                .add(record);
+        }
+        groups
+    }
+
+    /// Percentile of the block delays of the lost liquidations, nearest-rank.
+    pub fn lost_delay_percentile(&self, percentile: u64) -> Option<u64> {
+        if self.lost_delays.is_empty() {
+            return None;
+        }
+        let rank = (percentile.min(100) as usize * self.lost_delays.len()).div_ceil(100);
+        Some(self.lost_delays[rank.saturating_sub(1)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use super::{CompetitionSummary, LiquidabilityTracker, ObservedLiquidation};
+
+    fn record(ours: bool, attempted: bool, block_delay: Option<u64>) -> ObservedLiquidation {
+        ObservedLiquidation {
+            position_key: 0,
+            pool_id: Felt::ZERO,
+            user_address: Felt::ZERO,
+            collateral: "ETH".to_string(),
+            debt: "USDC".to_string(),
+            liquidator: if ours { Felt::ONE } else { Felt::TWO },
+            caller: Felt::THREE,
+            tx_hash: None,
+            block_number: 100,
+            timestamp: 0,
+            first_seen_liquidable: block_delay.map(|delay| 100 - delay),
+            block_delay,
+            attempted,
+            ours,
+        }
+    }
+
+    #[test]
+    fn test_liquidability_tracker() {
+        let tracker = LiquidabilityTracker::default();
+        tracker.seen_liquidable(1, 10);
+        tracker.seen_liquidable(1, 12);
+        tracker.mark_attempted(1);
+        // Still remembered right after it stops being liquidable.
+        tracker.seen_healthy(1, 13);
+        let window = tracker.take(1).unwrap();
+        assert_eq!((window.first_block, window.last_block), (10, 12));
+        assert!(window.attempted);
+        assert!(tracker.take(1).is_none());
+
+        tracker.seen_liquidable(2, 10);
+        tracker.seen_healthy(2, 100);
+        assert!(tracker.take(2).is_none());
+    }
+
+    #[test]
+    fn test_competition_summary() {
+        let records = [
+            record(true, true, Some(1)),
+            record(false, true, Some(4)),
+            record(false, false, Some(2)),
+            record(false, false, Some(10)),
+            record(false, false, None),
+        ];
+        let summary = CompetitionSummary::total(&records);
+        assert_eq!(summary.liquidations, 5);
+        assert_eq!(summary.won, 1);
+        assert_eq!(summary.lost, 4);
+        assert_eq!(summary.lost_attempted, 1);
+        assert_eq!(summary.lost_unseen, 1);
+        assert_eq!(summary.lost_delays, vec![2, 4, 10]);
+        assert_eq!(summary.lost_delay_percentile(50), Some(4));
+        assert_eq!(summary.lost_delay_percentile(90), Some(10));
+
+        let by_liquidator = CompetitionSummary::by_liquidator(&records);
+        assert_eq!(by_liquidator["0x1"].won, 1);
+        assert_eq!(by_liquidator["0x2"].lost, 4);
+    }
+}
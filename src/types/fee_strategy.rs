@@ -0,0 +1,220 @@
+use anyhow::{Result, bail};
+use bigdecimal::{BigDecimal, num_bigint::BigInt};
+use starknet::core::types::FeeEstimate;
+
+use crate::{
+    config::FeeRules,
+    utils::constants::{FEE_TOKEN_DECIMALS, FEE_TOKEN_TICKER},
+};
+
+/// Gas consumed by a transaction & gas prices, as estimated by the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasEstimate {
+    pub l1_gas: u64,
+    pub l1_gas_price: u128,
+    pub l2_gas: u64,
+    pub l2_gas_price: u128,
+    pub l1_data_gas: u64,
+    pub l1_data_gas_price: u128,
+}
+
+impl From<&FeeEstimate> for GasEstimate {
+    fn from(estimate: &FeeEstimate) -> Self {
+        Self {
+            l1_gas: estimate.l1_gas_consumed,
+            l1_gas_price: estimate.l1_gas_price,
+            l2_gas: estimate.l2_gas_consumed,
+            l2_gas_price: estimate.l2_gas_price,
+            l1_data_gas: estimate.l1_data_gas_consumed,
+            l1_data_gas_price: estimate.l1_data_gas_price,
+        }
+    }
+}
+
+/// Resource bounds & tip a V3 transaction is sent with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeBid {
+    pub l1_gas: u64,
+    pub l1_gas_price: u128,
+    pub l2_gas: u64,
+    pub l2_gas_price: u128,
+    pub l1_data_gas: u64,
+    pub l1_data_gas_price: u128,
+    /// Tip per L2 gas, in fri.
+    pub tip: u64,
+}
+
+impl FeeBid {
+    /// Bounds the estimate scaled by the multipliers, rounded up.
+    pub fn from_estimate(
+        estimate: &GasEstimate,
+        gas_multiplier: f64,
+        gas_price_multiplier: f64,
+        tip: u64,
+    ) -> Self {
+        let gas = |amount: u64| (amount as f64 * gas_multiplier).ceil() as u64;
+        let price = |price: u128| (price as f64 * gas_price_multiplier).ceil() as u128;
+        Self {
+            l1_gas: gas(estimate.l1_gas),
+            l1_gas_price: price(estimate.l1_gas_price),
+            l2_gas: gas(estimate.l2_gas),
+            l2_gas_price: price(estimate.l2_gas_price),
+            l1_data_gas: gas(estimate.l1_data_gas),
+            l1_data_gas_price: price(estimate.l1_data_gas_price),
+            tip,
+        }
+    }
+
+    /// Maximum fee the transaction can be charged, in fri.
+    pub fn max_fee(&self) -> u128 {
+        self.l1_gas as u128 * self.l1_gas_price
+            + self.l2_gas as u128 * (self.l2_gas_price + self.tip as u128)
+            + self.l1_data_gas as u128 * self.l1_data_gas_price
+    }
+
+    /// Maximum fee the transaction can be charged, in STRK.
+    pub fn max_fee_amount(&self) -> BigDecimal {
+        BigDecimal::new(BigInt::from(self.max_fee()), FEE_TOKEN_DECIMALS)
+    }
+}
+
+/// Picks the resource bounds & tip of a liquidation from its gas estimate.
+///
+/// The estimate is scaled by the multipliers of the rules, and the tip is raised for
+/// the opportunities expected to yield at least the high value profit. When the profit
+/// & the fee token price are known, the max fee is capped to a share of the profit:
+/// the tip is dropped first, and the liquidation is skipped if it still doesn't fit.
+/// The multipliers are always kept, as a bid without margin fails on any gas price move.
+pub fn bid(
+    rules: &FeeRules,
+    estimate: &GasEstimate,
+    expected_profit: Option<&BigDecimal>,
+    fee_token_price: Option<&BigDecimal>,
+) -> Result<FeeBid> {
+    let high_value = expected_profit.is_some_and(|profit| *profit >= rules.high_value_profit);
+    let tip = if high_value {
+        rules.high_value_tip
+    } else {
+        rules.tip
+    };
+    let bid = FeeBid::from_estimate(
+        estimate,
+        rules.gas_multiplier,
+        rules.gas_price_multiplier,
+        tip,
+    );
+
+    let (Some(profit), Some(fee_token_price)) = (expected_profit, fee_token_price) else {
+        return Ok(bid);
+    };
+    if *fee_token_price <= BigDecimal::from(0) {
+        return Ok(bid);
+    }
+    let max_fee_value = profit * &rules.max_profit_share;
+    let candidates = [bid, FeeBid { tip: 0, ..bid }];
+    for candidate in candidates {
+        if candidate.max_fee_amount() * fee_token_price <= max_fee_value {
+            return Ok(candidate);
+        }
+    }
+    bail!(
+        "The max fee ({} {FEE_TOKEN_TICKER}) exceeds {}% of the expected profit ({} USD)",
+        candidates[1].max_fee_amount().round(4),
+        (&rules.max_profit_share * BigDecimal::from(100)).round(2),
+        profit.round(2)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+
+    use crate::config::FeeRules;
+
+    use super::{FeeBid, GasEstimate, bid};
+
+    fn rules() -> FeeRules {
+        FeeRules {
+            gas_multiplier: 1.5,
+            gas_price_multiplier: 2.0,
+            tip: 10,
+            high_value_profit: BigDecimal::from(500),
+            high_value_tip: 1_000,
+            max_profit_share: "0.5".parse().unwrap(),
+        }
+    }
+
+    // 1 STRK at the estimate: 1e18 fri = 1e8 L2 gas at 1e10 fri.
+    fn estimate() -> GasEstimate {
+        GasEstimate {
+            l1_gas: 0,
+            l1_gas_price: 0,
+            l2_gas: 100_000_000,
+            l2_gas_price: 10_000_000_000,
+            l1_data_gas: 0,
+            l1_data_gas_price: 0,
+        }
+    }
+
+    #[test]
+    fn test_bid_scales_the_estimate() {
+        let fee_bid = bid(&rules(), &estimate(), None, None).unwrap();
+        assert_eq!(fee_bid.l2_gas, 150_000_000);
+        assert_eq!(fee_bid.l2_gas_price, 20_000_000_000);
+        assert_eq!(fee_bid.tip, 10);
+        assert_eq!(
+            fee_bid.max_fee(),
+            150_000_000 * (20_000_000_000 + 10) as u128
+        );
+
+        let strk_price = BigDecimal::from(1);
+        let high_value = bid(
+            &rules(),
+            &estimate(),
+            Some(&BigDecimal::from(1000)),
+            Some(&strk_price),
+        )
+        .unwrap();
+        assert_eq!(high_value.tip, 1_000);
+    }
+
+    #[test]
+    fn test_bid_is_capped_by_the_profit() {
+        let strk_price = BigDecimal::from(1);
+        // 3 STRK max fee with the multipliers, allowed up to 5 USD.
+        let fee_bid = bid(
+            &rules(),
+            &estimate(),
+            Some(&BigDecimal::from(10)),
+            Some(&strk_price),
+        )
+        .unwrap();
+        assert_eq!(fee_bid.tip, 10);
+
+        // Only the bid without tip fits in 3 USD.
+        let fee_bid = bid(
+            &rules(),
+            &estimate(),
+            Some(&BigDecimal::from(6)),
+            Some(&strk_price),
+        )
+        .unwrap();
+        assert_eq!(fee_bid, FeeBid::from_estimate(&estimate(), 1.5, 2.0, 0));
+
+        // Without price, the profit can't be compared to the fee.
+        assert!(bid(&rules(), &estimate(), Some(&BigDecimal::from(1)), None).is_ok());
+    }
+
+    #[test]
+    fn test_bid_without_margin_is_never_sent() {
+        let strk_price = BigDecimal::from(1);
+        // The bare estimate (1 STRK) would fit in 1.5 USD, but not the padded bid.
+        let result = bid(
+            &rules(),
+            &estimate(),
+            Some(&BigDecimal::from(3)),
+            Some(&strk_price),
+        );
+        assert!(result.is_err());
+    }
+}
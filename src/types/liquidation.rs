@@ -0,0 +1,498 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bigdecimal::BigDecimal;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{
+    Event, ExecutionResult, Felt, TransactionReceipt, TransactionReceiptWithBlockInfo,
+};
+
+use crate::config::{Config, ERC20_TRANSFER_EVENT, LIQUIDATE_POSITION_EVENT};
+use crate::services::oracle::LatestOraclePrices;
+use crate::utils::constants::{FEE_TOKEN_DECIMALS, FEE_TOKEN_TICKER};
+use crate::utils::conversions::u256_felts_to_big_decimal;
+
+use super::asset::Asset;
+use super::position::Position;
+
+/// Final outcome of a liquidation attempt.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum LiquidationOutcome {
+    /// The transaction landed & succeeded.
+    Succeeded,
+    /// The transaction landed but has been reverted.
+    Reverted(String),
+    /// The liquidation failed before (or while) being submitted.
+    Failed(String),
+}
+
+impl LiquidationOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, LiquidationOutcome::Succeeded)
+    }
+}
+
+impl fmt::Display for LiquidationOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LiquidationOutcome::Succeeded => write!(f, "succeeded"),
+            LiquidationOutcome::Reverted(reason) => write!(f, "reverted ({reason})"),
+            LiquidationOutcome::Failed(reason) => write!(f, "failed ({reason})"),
+        }
+    }
+}
+
+/// Record of a liquidation attempt, stored for PnL reporting.
+///
+/// `collateral.amount` is the collateral seized & `debt.amount` the debt repaid.
+/// USD values are computed with the oracle prices at the moment of the attempt.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LiquidationRecord {
+    pub position_key: u64,
+    pub pool_id: Felt,
+    pub user_address: Felt,
+    pub block_number: Option<u64>,
+    pub tx_hash: Option<Felt>,
+    /// Liquidator account that sent the transaction.
+    #[serde(default)]
+    pub account: Option<Felt>,
+    /// Unix timestamp (in seconds) of the attempt.
+    pub timestamp: u64,
+    pub outcome: LiquidationOutcome,
+    pub collateral: Asset,
+    pub debt: Asset,
+    /// Collateral left to the liquidator once the flash loan has been repaid,
+    /// in the residual token.
+    pub residual: BigDecimal,
+    /// Token the residual is received in: the target of the withdraw swap, or the
+    /// collateral without swap. Missing from the records made before it was stored.
+    #[serde(default)]
+    pub residual_token: Option<Asset>,
+    /// Fee paid for the transaction, in STRK.
+    pub fee_paid: BigDecimal,
+    pub collateral_received_usd: BigDecimal,
+    pub debt_repaid_usd: BigDecimal,
+    pub residual_usd: BigDecimal,
+    pub fee_paid_usd: BigDecimal,
+}
+
+impl LiquidationRecord {
+    /// Creates a record for an attempt that never produced a transaction receipt.
+    pub fn failed(
+        position: &Position,
+        tx_hash: Option<Felt>,
+        reason: String,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Self {
+        let mut record = Self::empty(position, LiquidationOutcome::Failed(reason));
+        record.tx_hash = tx_hash;
+        record.with_usd_values(oracle_prices)
+    }
+
+    /// Creates a record from the receipt of a liquidation transaction.
+    /// The liquidated amounts are read from the `LiquidatePosition` event emitted
+    /// by the Liquidate contract, and the residual token from its transfer to the
+    /// recipient.
+    pub fn from_receipt(
+        position: &Position,
+        config: &Config,
+        receipt: &TransactionReceiptWithBlockInfo,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Self {
+        let outcome = match receipt.receipt.execution_result() {
+            ExecutionResult::Succeeded => LiquidationOutcome::Succeeded,
+            ExecutionResult::Reverted { reason } => LiquidationOutcome::Reverted(reason.clone()),
+        };
+        let mut record = Self::empty(position, outcome);
+        record.tx_hash = Some(*receipt.receipt.transaction_hash());
+        record.block_number = Some(receipt.block.block_number());
+        record.fee_paid = BigDecimal::new(
+            receipt.receipt.actual_fee().amount.to_bigint(),
+            FEE_TOKEN_DECIMALS,
+        );
+
+        let events = match &receipt.receipt {
+            TransactionReceipt::Invoke(invoke) => invoke.events.as_slice(),
+            _ => &[],
+        };
+        let liquidate_event = events.iter().find(|event| {
+            event.from_address == config.liquidate_address
+                && event.keys.first() == Some(&*LIQUIDATE_POSITION_EVENT)
+                && event.data.len() >= 6
+        });
+        if let Some(event) = liquidate_event {
+            let mut residual_token = record.collateral.clone();
+            if let Some(token) = residual_token_address(
+                events,
+                &config.liquidate_address,
+                (&event.data[0], &event.data[1]),
+            ) {
+                match Asset::from_address(config, token) {
+                    Some(asset) => residual_token = asset,
+                    None => tracing::warn!(
+                        "Unknown residual token {token:#x} of #{}, valued as the collateral",
+                        position.key()
+                    ),
+                }
+            }
+            record.residual =
+                u256_felts_to_big_decimal(&event.data[0], &event.data[1], residual_token.decimals);
+            residual_token.amount = record.residual.clone();
+            record.residual_token = Some(residual_token);
+            record.collateral.amount = u256_felts_to_big_decimal(
+                &event.data[2],
+                &event.data[3],
+                position.collateral.decimals,
+            );
+            record.debt.amount =
+                u256_felts_to_big_decimal(&event.data[4], &event.data[5], position.debt.decimals);
+        }
+
+        record.with_usd_values(oracle_prices)
+    }
+
+    fn empty(position: &Position, outcome: LiquidationOutcome) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            position_key: position.key(),
+            pool_id: position.pool_id,
+            user_address: position.user_address,
+            block_number: None,
+            tx_hash: None,
+            account: None,
+            timestamp,
+            outcome,
+            collateral: Asset::new(
+                position.collateral.name.clone(),
+                position.collateral.address,
+                position.collateral.decimals,
+            ),
+            debt: Asset::new(
+                position.debt.name.clone(),
+                position.debt.address,
+                position.debt.decimals,
+            ),
+            residual: BigDecimal::from(0),
+            residual_token: None,
+            fee_paid: BigDecimal::from(0),
+            collateral_received_usd: BigDecimal::from(0),
+            debt_repaid_usd: BigDecimal::from(0),
+            residual_usd: BigDecimal::from(0),
+            fee_paid_usd: BigDecimal::from(0),
+        }
+    }
+
+    fn with_usd_values(mut self, oracle_prices: &LatestOraclePrices) -> Self {
+        let price_of =
+            |ticker: &str| -> BigDecimal { oracle_prices.price_of(ticker).unwrap_or_default() };
+        let collateral_price = price_of(&self.collateral.name);
+        let debt_price = price_of(&self.debt.name);
+
+        self.collateral_received_usd = &self.collateral.amount * collateral_price;
+        self.debt_repaid_usd = &self.debt.amount * debt_price;
+        self.residual_usd = &self.residual * price_of(self.residual_asset());
+        self.fee_paid_usd = &self.fee_paid * price_of(FEE_TOKEN_TICKER);
+        self
+    }
+
+    /// Sets the liquidator account that sent the transaction.
+    pub fn with_account(mut self, account: Felt) -> Self {
+        self.account = Some(account);
+        self
+    }
+
+    /// Name of the token the residual is received in.
+    pub fn residual_asset(&self) -> &str {
+        self.residual_token
+            .as_ref()
+            .map_or(&self.collateral.name, |token| &token.name)
+    }
+
+    /// Realized PnL of the attempt, in USD.
+    pub fn pnl_usd(&self) -> BigDecimal {
+        &self.residual_usd - &self.fee_paid_usd
+    }
+
+    /// UTC day of the attempt, formatted as `YYYY-MM-DD`.
+    pub fn day(&self) -> String {
+        DateTime::from_timestamp(self.timestamp as i64, 0)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the token transferred by the Liquidate contract for the given residual
+/// amount, from the ERC20 `Transfer` events of the receipt.
+fn residual_token_address(
+    events: &[Event],
+    liquidate_address: &Felt,
+    (residual_low, residual_high): (&Felt, &Felt),
+) -> Option<Felt> {
+    events
+        .iter()
+        .find(|event| {
+            if event.keys.first() != Some(&*ERC20_TRANSFER_EVENT) {
+                return false;
+            }
+            // `from` & `to` are keys of the recent ERC20s, and data of the legacy ones.
+            let (from, amount) = match (event.keys.len(), event.data.len()) {
+                (3, 2) => (&event.keys[1], &event.data[..]),
+                (1, 4) => (&event.data[0], &event.data[2..]),
+                _ => return false,
+            };
+            from == liquidate_address && amount[0] == *residual_low && amount[1] == *residual_high
+        })
+        .map(|event| event.from_address)
+}
+
+/// Aggregated PnL over a set of liquidation attempts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PnlSummary {
+    pub attempts: usize,
+    pub liquidations: usize,
+    pub residual_usd: BigDecimal,
+    pub fees_usd: BigDecimal,
+    pub pnl_usd: BigDecimal,
+}
+
+impl PnlSummary {
+    fn add(&mut self, record: &LiquidationRecord) {
+        self.attempts += 1;
+        if record.outcome.is_success() {
+            self.liquidations += 1;
+        }
+        self.residual_usd += &record.residual_usd;
+        self.fees_usd += &record.fee_paid_usd;
+        self.pnl_usd += record.pnl_usd();
+    }
+
+    /// Realized PnL grouped by the asset the residual is received in.
+    pub fn by_asset(records: &[LiquidationRecord]) -> BTreeMap<String, PnlSummary> {
+        Self::group_by(records, |record| record.residual_asset().to_string())
+    }
+
+    /// Realized PnL grouped by UTC day.
+    pub fn by_day(records: &[LiquidationRecord]) -> BTreeMap<String, PnlSummary> {
+        Self::group_by(records, LiquidationRecord::day)
+    }
+
+    /// Realized PnL grouped by liquidator account.
+    pub fn by_account(records: &[LiquidationRecord]) -> BTreeMap<String, PnlSummary> {
+        Self::group_by(records, |record| match record.account {
+            Some(account) => format!("{account:#x}"),
+            None => "unknown".to_string(),
+        })
+    }
+
+    /// Realized PnL over all the records.
+    pub fn total(records: &[LiquidationRecord]) -> PnlSummary {
+        let mut summary = PnlSummary::default();
+        records.iter().for_each(|record| summary.add(record));
+        summary
+    }
+
+    fn group_by(
+        records: &[LiquidationRecord],
+        key: impl Fn(&LiquidationRecord) -> String,
+    ) -> BTreeMap<String, PnlSummary> {
+        let mut groups: BTreeMap<String, PnlSummary> = BTreeMap::new();
+        for record in records {
+            groups.entry(key(record)).or_default().add(record);
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use bigdecimal::BigDecimal;
+    use serde_json::json;
+    use starknet::core::types::{Felt, TransactionReceiptWithBlockInfo};
+
+    use crate::{
+        config::{Config, ERC20_TRANSFER_EVENT, LIQUIDATE_POSITION_EVENT, LiquidationMode},
+        services::oracle::{LatestOraclePrices, OraclePrice},
+        types::{asset::Asset, position::Position},
+    };
+
+    use super::{LiquidationOutcome, LiquidationRecord, PnlSummary};
+
+    const ETH: &str = "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7";
+    const USDC: &str = "0x53c91253bc9682c04929ca02ed00b3e423f6710d2ee7e0d5ebb06f3ecf368a8";
+    const ACCOUNT: &str = "0x123";
+
+    fn config() -> Config {
+        Config::new(
+            &"mainnet".parse().unwrap(),
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap()
+    }
+
+    fn oracle_prices() -> LatestOraclePrices {
+        let prices = LatestOraclePrices::default();
+        for (ticker, price) in [("eth", 2500), ("usdc", 1)] {
+            let price = OraclePrice {
+                price: BigDecimal::from(price),
+                ..Default::default()
+            };
+            prices.0.insert(ticker.to_string(), price);
+        }
+        prices.0.insert(
+            "strk".to_string(),
+            OraclePrice {
+                price: "0.5".parse().unwrap(),
+                ..Default::default()
+            },
+        );
+        prices
+    }
+
+    /// Receipt of the liquidation of 1 ETH for 2000 USDC, paying 1 STRK, with the
+    /// residual transferred in the given token.
+    fn receipt(
+        config: &Config,
+        residual: &str,
+        residual_token: &str,
+    ) -> TransactionReceiptWithBlockInfo {
+        let liquidate = format!("{:#x}", config.liquidate_address);
+        let liquidate_event = json!({
+            "from_address": liquidate,
+            "keys": [format!("{:#x}", *LIQUIDATE_POSITION_EVENT), "0x1", ETH, USDC, "0x2"],
+            "data": [residual, "0x0", "0xde0b6b3a7640000", "0x0", "0x77359400", "0x0"],
+        });
+        let transfer_event = json!({
+            "from_address": residual_token,
+            "keys": [format!("{:#x}", *ERC20_TRANSFER_EVENT), liquidate, ACCOUNT],
+            "data": [residual, "0x0"],
+        });
+        serde_json::from_value(json!({
+            "type": "INVOKE",
+            "transaction_hash": "0xabc",
+            "actual_fee": { "amount": "0xde0b6b3a7640000", "unit": "FRI" },
+            "execution_status": "SUCCEEDED",
+            "finality_status": "ACCEPTED_ON_L2",
+            "block_hash": "0x1",
+            "block_number": 100,
+            "messages_sent": [],
+            "events": [liquidate_event, transfer_event],
+            "execution_resources": { "l1_gas": 0, "l1_data_gas": 0, "l2_gas": 0 },
+        }))
+        .unwrap()
+    }
+
+    fn position(config: &Config) -> Position {
+        Position {
+            user_address: Felt::TWO,
+            pool_id: Felt::ONE,
+            collateral: Asset::from_address(config, Felt::from_hex(ETH).unwrap()).unwrap(),
+            debt: Asset::from_address(config, Felt::from_hex(USDC).unwrap()).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_receipt_with_withdraw_swap() {
+        let config = config();
+        // 50 USDC left once swapped.
+        let receipt = receipt(&config, "0x2faf080", USDC);
+        let record = LiquidationRecord::from_receipt(
+            &position(&config),
+            &config,
+            &receipt,
+            &oracle_prices(),
+        );
+
+        assert_eq!(record.outcome, LiquidationOutcome::Succeeded);
+        assert_eq!(record.block_number, Some(100));
+        assert_eq!(record.collateral.amount, BigDecimal::from(1));
+        assert_eq!(record.debt.amount, BigDecimal::from(2000));
+        assert_eq!(record.residual, BigDecimal::from(50));
+        assert_eq!(record.residual_asset(), "USDC");
+        assert_eq!(record.residual_usd, BigDecimal::from(50));
+        assert_eq!(record.fee_paid, BigDecimal::from(1));
+        assert_eq!(record.pnl_usd(), "49.5".parse::<BigDecimal>().unwrap());
+        assert!(PnlSummary::by_asset(&[record]).contains_key("USDC"));
+    }
+
+    #[test]
+    fn test_from_receipt_without_withdraw_swap() {
+        let config = config();
+        // 0.02 ETH left as collateral.
+        let receipt = receipt(&config, "0x470de4df820000", ETH);
+        let record = LiquidationRecord::from_receipt(
+            &position(&config),
+            &config,
+            &receipt,
+            &oracle_prices(),
+        );
+
+        assert_eq!(record.residual, "0.02".parse::<BigDecimal>().unwrap());
+        assert_eq!(record.residual_asset(), "ETH");
+        assert_eq!(record.residual_usd, BigDecimal::from(50));
+        assert_eq!(record.pnl_usd(), "49.5".parse::<BigDecimal>().unwrap());
+    }
+
+    fn record(ticker: &str, timestamp: u64, residual_usd: i64, fee_usd: i64) -> LiquidationRecord {
+        LiquidationRecord {
+            position_key: 0,
+            pool_id: Felt::ZERO,
+            user_address: Felt::ZERO,
+            block_number: Some(1),
+            tx_hash: Some(Felt::ONE),
+            account: Some(Felt::from(timestamp % 2)),
+            timestamp,
+            outcome: if residual_usd > 0 {
+                LiquidationOutcome::Succeeded
+            } else {
+                LiquidationOutcome::Reverted("not-undercollateralized".to_string())
+            },
+            collateral: Asset::new(ticker.to_string(), Felt::ONE, 18),
+            debt: Asset::new("USDC".to_string(), Felt::TWO, 6),
+            residual: BigDecimal::from(0),
+            residual_token: None,
+            fee_paid: BigDecimal::from(0),
+            collateral_received_usd: BigDecimal::from(0),
+            debt_repaid_usd: BigDecimal::from(0),
+            residual_usd: BigDecimal::from(residual_usd),
+            fee_paid_usd: BigDecimal::from(fee_usd),
+        }
+    }
+
+    #[test]
+    fn test_pnl_by_asset_and_day() {
+        let records = vec![
+            record("ETH", 1_700_000_000, 100, 1),
+            record("ETH", 1_700_000_000 + 86_400, 50, 2),
+            record("STRK", 1_700_000_000, 0, 3),
+        ];
+
+        let by_asset = PnlSummary::by_asset(&records);
+        assert_eq!(by_asset["ETH"].attempts, 2);
+        assert_eq!(by_asset["ETH"].liquidations, 2);
+        assert_eq!(by_asset["ETH"].pnl_usd, BigDecimal::from(147));
+        assert_eq!(by_asset["STRK"].liquidations, 0);
+        assert_eq!(by_asset["STRK"].pnl_usd, BigDecimal::from(-3));
+
+        let by_day = PnlSummary::by_day(&records);
+        assert_eq!(by_day["2023-11-14"].attempts, 2);
+        assert_eq!(by_day["2023-11-14"].pnl_usd, BigDecimal::from(96));
+        assert_eq!(by_day["2023-11-15"].pnl_usd, BigDecimal::from(48));
+
+        let by_account = PnlSummary::by_account(&records);
+        assert_eq!(by_account["0x0"].attempts, 2);
+        assert_eq!(by_account["0x0"].pnl_usd, BigDecimal::from(96));
+        assert_eq!(by_account["0x1"].pnl_usd, BigDecimal::from(48));
+
+        assert_eq!(PnlSummary::total(&records).pnl_usd, BigDecimal::from(144));
+    }
+}
@@ -4,11 +4,21 @@ use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
 
 pub mod account;
 pub mod asset;
+pub mod competition;
+pub mod fee_strategy;
+pub mod liquidation;
 pub mod position;
+pub mod position_state;
+pub mod price_history;
+pub mod price_source;
+pub mod route;
+pub mod signer;
+pub mod sweep;
+pub mod transaction;
 
 pub type StarknetSingleOwnerAccount = Arc<
     starknet::accounts::SingleOwnerAccount<
         Arc<JsonRpcClient<HttpTransport>>,
-        starknet::signers::LocalWallet,
+        signer::LiquidatorSigner,
     >,
 >;
@@ -1,6 +1,6 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use apibara_core::starknet::v1alpha2::FieldElement;
-use bigdecimal::{BigDecimal, FromPrimitive};
+use bigdecimal::BigDecimal;
 use colored::Colorize;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
@@ -11,23 +11,72 @@ use starknet::providers::{JsonRpcClient, Provider};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::bindings::liquidate::{Liquidate, LiquidateParams};
 
 use crate::config::{
-    Config, LIQUIDATION_CONFIG_SELECTOR, VESU_LTV_CONFIG_SELECTOR, VESU_POSITION_UNSAFE_SELECTOR,
+    Config, LIQUIDATION_CONFIG_SELECTOR, LiquidabilityCheck, VESU_CHECK_COLLATERALIZATION_SELECTOR,
+    VESU_LTV_CONFIG_SELECTOR, VESU_POSITION_UNSAFE_SELECTOR, VESU_PRICE_SELECTOR,
 };
 use crate::services::oracle::LatestOraclePrices;
 use crate::storages::Storage;
 use crate::utils::constants::{U256_ZERO, VESU_RESPONSE_DECIMALS};
-use crate::utils::ekubo::get_ekubo_route;
-use crate::{types::asset::Asset, utils::conversions::apibara_field_as_felt};
+use crate::{
+    types::{
+        asset::Asset,
+        position_state::PositionLifecycle,
+        price_history::PriceHistory,
+        route::{Route, RouteProvider, WithdrawPlan, WithdrawSwap, route_fee},
+    },
+    utils::{
+        batch::batch_calls,
+        conversions::{apibara_field_as_felt, u256_felts_to_big_decimal},
+    },
+};
 
 use super::StarknetSingleOwnerAccount;
 
-/// Threshold for which we consider a position almost liquidable.
-const ALMOST_LIQUIDABLE_THRESHOLD: f64 = 0.01;
+/// Maximum number of positions refreshed by a single batch request.
+pub const POSITIONS_BATCH_SIZE: usize = 50;
+/// Time the liquidation factor of a pair is cached for.
+const LIQUIDATION_FACTOR_TTL: Duration = Duration::from_secs(3600);
+
+/// Thread-safe cache of the liquidation factors, by (pool, collateral, debt): they
+/// only change with the liquidation config of the pools.
+#[derive(Clone, Default)]
+pub struct LiquidationFactors(Arc<DashMap<(Felt, Felt, Felt), (BigDecimal, Instant)>>);
+
+impl LiquidationFactors {
+    /// Returns the cached liquidation factor of the pair of a position, if any.
+    pub fn get(&self, position: &Position) -> Option<BigDecimal> {
+        self.0
+            .get(&position.pair_key())
+            .filter(|entry| entry.1.elapsed() < LIQUIDATION_FACTOR_TTL)
+            .map(|entry| entry.0.clone())
+    }
+
+    /// Returns the liquidation factor of the pair of a position, from the cache or
+    /// from the extension contract on a miss.
+    pub async fn get_or_fetch(
+        &self,
+        position: &Position,
+        config: &Config,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    ) -> Result<BigDecimal> {
+        if let Some(liquidation_factor) = self.get(position) {
+            return Ok(liquidation_factor);
+        }
+        let liquidation_factor = position
+            .fetch_liquidation_factors(config, rpc_client)
+            .await?;
+        self.0.insert(
+            position.pair_key(),
+            (liquidation_factor.clone(), Instant::now()),
+        );
+        Ok(liquidation_factor)
+    }
+}
 
 /// Thread-safe wrapper around the positions.
 /// PositionsMap is a map between position position_key <=> position.
@@ -42,7 +91,9 @@ impl PositionsMap {
     pub fn from_storage(storage: &dyn Storage) -> Self {
         let positions = storage.get_positions();
         let dash_map = DashMap::new();
-        for (key, value) in positions {
+        for (key, mut value) in positions {
+            // The transactions in flight before a restart are no longer followed.
+            value.lifecycle.abandon_in_flight();
             dash_map.insert(key, value);
         }
         Self(Arc::new(dash_map))
@@ -59,6 +110,37 @@ impl PositionsMap {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// Refreshes the amounts & LLTV of all the positions, by batches of
+    /// `POSITIONS_BATCH_SIZE` positions.
+    pub async fn refresh(
+        &self,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+        singleton_address: &Felt,
+    ) {
+        let mut positions: Vec<Position> =
+            self.0.iter().map(|entry| entry.value().clone()).collect();
+        for batch in positions.chunks_mut(POSITIONS_BATCH_SIZE) {
+            let results = Position::update_batch(batch, rpc_client, singleton_address).await;
+            for (position, result) in batch.iter().zip(results) {
+                match result {
+                    Ok(()) => {
+                        // Positions removed in the meantime are not inserted back, and
+                        // the lifecycle may have moved meanwhile.
+                        if let Some(mut entry) = self.0.get_mut(&position.key()) {
+                            entry.collateral = position.collateral.clone();
+                            entry.debt = position.debt.clone();
+                            entry.lltv = position.lltv.clone();
+                        }
+                    }
+                    Err(e) => tracing::warn!(
+                        "[🔭 Monitoring] Could not refresh position #{}: {e}",
+                        position.key()
+                    ),
+                }
+            }
+        }
+    }
 }
 
 impl Default for PositionsMap {
@@ -74,6 +156,9 @@ pub struct Position {
     pub collateral: Asset,
     pub debt: Asset,
     pub lltv: BigDecimal,
+    /// Liquidation state & attempts of the position, kept across the refreshes.
+    #[serde(default)]
+    pub lifecycle: PositionLifecycle,
 }
 
 impl Position {
@@ -95,38 +180,71 @@ impl Position {
             debt: debt.unwrap(),
             user_address: event_keys[4],
             lltv: BigDecimal::default(),
+            lifecycle: PositionLifecycle::default(),
         };
         Some(position)
     }
 
     /// Computes & returns the LTV Ratio for a position.
     pub async fn ltv(&self, oracle_prices: &LatestOraclePrices) -> Result<BigDecimal> {
+        let (collateral_price, debt_price) = self.prices(oracle_prices)?;
+        anyhow::ensure!(
+            (self.collateral.amount > BigDecimal::from(0)),
+            "Colateral amount is zero. Can't compute LTV."
+        );
+
+        let ltv = (&self.debt.amount * debt_price) / (&self.collateral.amount * collateral_price);
+        Ok(ltv)
+    }
+
+    /// Estimates the profit, in USD and before fees, of fully liquidating the position.
+    /// The collateral received is the debt value scaled by the liquidation factor,
+    /// capped by the collateral of the position.
+    pub fn estimated_liquidation_profit(
+        &self,
+        liquidation_factor: &BigDecimal,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Result<BigDecimal> {
+        let (collateral_price, debt_price) = self.prices(oracle_prices)?;
+        let liquidation_factor = if *liquidation_factor == BigDecimal::from(0) {
+            BigDecimal::from(1)
+        } else {
+            liquidation_factor.clone()
+        };
+
+        let debt_value = &self.debt.amount * debt_price;
+        let collateral_value = &self.collateral.amount * collateral_price;
+        let collateral_to_receive = (&debt_value / liquidation_factor).min(collateral_value);
+        Ok(collateral_to_receive - debt_value)
+    }
+
+    /// Returns the price of the collateral, denominated in debt.
+    pub fn collateral_price_in_debt(
+        &self,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Result<BigDecimal> {
+        let (collateral_price, debt_price) = self.prices(oracle_prices)?;
+        Ok(collateral_price / debt_price)
+    }
+
+    /// Returns the (collateral, debt) prices of the position.
+    fn prices(&self, oracle_prices: &LatestOraclePrices) -> Result<(BigDecimal, BigDecimal)> {
         let collateral_name = self.collateral.name.to_lowercase();
         let debt_name = self.debt.name.to_lowercase();
 
         let collateral_price = oracle_prices
-            .0
-            .get(&collateral_name)
-            .ok_or_else(|| anyhow!("Price not found for collateral: {}", collateral_name))?
-            .clone();
+            .price_of(&collateral_name)
+            .ok_or_else(|| anyhow!("Price not found for collateral: {}", collateral_name))?;
 
         let debt_price = oracle_prices
-            .0
-            .get(&debt_name)
-            .ok_or_else(|| anyhow!("Price not found for debt: {}", debt_name))?
-            .clone();
+            .price_of(&debt_name)
+            .ok_or_else(|| anyhow!("Price not found for debt: {}", debt_name))?;
 
         anyhow::ensure!(
             (collateral_price > BigDecimal::from(0)) && (debt_price > BigDecimal::from(0)),
             "Oracle prices are zero. Can't compute LTV."
         );
-        anyhow::ensure!(
-            (self.collateral.amount > BigDecimal::from(0)),
-            "Colateral amount is zero. Can't compute LTV."
-        );
-
-        let ltv = (&self.debt.amount * debt_price) / (&self.collateral.amount * collateral_price);
-        Ok(ltv)
+        Ok((collateral_price, debt_price))
     }
 
     /// Check if a position is closed.
@@ -135,7 +253,12 @@ impl Position {
     }
 
     /// Returns if the position is liquidable or not.
-    pub async fn is_liquidable(&self, oracle_prices: &LatestOraclePrices) -> anyhow::Result<bool> {
+    /// Positions within `almost_liquidable_threshold` of their LLTV are logged.
+    pub async fn is_liquidable(
+        &self,
+        oracle_prices: &LatestOraclePrices,
+        almost_liquidable_threshold: &BigDecimal,
+    ) -> anyhow::Result<bool> {
         if self.lltv == BigDecimal::default() {
             return Ok(false);
         }
@@ -146,17 +269,215 @@ impl Position {
         };
 
         let is_liquidable = ltv_ratio >= self.lltv.clone();
-        let almost_liquidable_threshold =
-            self.lltv.clone() - BigDecimal::from_f64(ALMOST_LIQUIDABLE_THRESHOLD).unwrap();
-        let is_almost_liquidable = ltv_ratio > almost_liquidable_threshold;
+        self.logs_if_almost_liquidable(is_liquidable, ltv_ratio, almost_liquidable_threshold);
+        Ok(is_liquidable)
+    }
 
-        if is_liquidable || is_almost_liquidable {
-            self.logs_liquidation_state(is_liquidable, ltv_ratio);
+    /// Returns if the position is liquidable, according to the check of the config.
+    /// Disagreements between the Pragma prices & Vesu are logged when comparing them.
+    pub async fn check_liquidability(
+        &self,
+        config: &Config,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Result<bool> {
+        if self.lltv == BigDecimal::default() {
+            return Ok(false);
         }
+        let threshold = &config.almost_liquidable_threshold;
+
+        let is_liquidable = match config.liquidability_check {
+            LiquidabilityCheck::Pragma => self.is_liquidable(oracle_prices, threshold).await?,
+            LiquidabilityCheck::VesuPrices => {
+                let ltv_ratio = self.vesu_ltv(config, rpc_client).await?;
+                let is_liquidable = ltv_ratio >= self.lltv;
+                self.logs_if_almost_liquidable(is_liquidable, ltv_ratio, threshold);
+                is_liquidable
+            }
+            LiquidabilityCheck::VesuCollateralization => {
+                let (is_collateralized, ltv_ratio) =
+                    self.vesu_collateralization(config, rpc_client).await?;
+                self.logs_if_almost_liquidable(!is_collateralized, ltv_ratio, threshold);
+                !is_collateralized
+            }
+        };
 
+        if config.compare_liquidability_checks {
+            self.compare_liquidability_checks(config, rpc_client, oracle_prices)
+                .await;
+        }
         Ok(is_liquidable)
     }
 
+    /// Logs when the Pragma prices & the Vesu collateralization check disagree.
+    /// Returns if they agree, or None if they could not be compared.
+    async fn compare_liquidability_checks(
+        &self,
+        config: &Config,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Option<bool> {
+        let (pragma_ltv, vesu) = tokio::join!(
+            self.ltv(oracle_prices),
+            self.vesu_collateralization(config, rpc_client)
+        );
+        let (pragma_ltv, (is_collateralized, vesu_ltv)) = match (pragma_ltv, vesu) {
+            (Ok(pragma_ltv), Ok(vesu)) => (pragma_ltv, vesu),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::warn!("Could not compare the liquidability of {self}: {e}");
+                return None;
+            }
+        };
+        let pragma_is_liquidable = pragma_ltv >= self.lltv;
+        let agree = pragma_is_liquidable != is_collateralized;
+        if !agree {
+            tracing::warn!(
+                "{self} is {} according to Pragma (LTV {:.2}%) but {} according to Vesu (LTV {:.2}%)",
+                if pragma_is_liquidable {
+                    "liquidable"
+                } else {
+                    "safe"
+                },
+                pragma_ltv * BigDecimal::from(100),
+                if is_collateralized {
+                    "safe"
+                } else {
+                    "liquidable"
+                },
+                vesu_ltv * BigDecimal::from(100),
+            );
+        }
+        Some(agree)
+    }
+
+    /// Computes the LTV of the position from the prices of the Vesu extension.
+    pub async fn vesu_ltv(
+        &self,
+        config: &Config,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    ) -> Result<BigDecimal> {
+        anyhow::ensure!(
+            (self.collateral.amount > BigDecimal::from(0)),
+            "Colateral amount is zero. Can't compute LTV."
+        );
+        let (collateral_price, debt_price) = tokio::try_join!(
+            self.fetch_vesu_price(config, rpc_client, &self.collateral),
+            self.fetch_vesu_price(config, rpc_client, &self.debt)
+        )?;
+        Ok((&self.debt.amount * debt_price) / (&self.collateral.amount * collateral_price))
+    }
+
+    /// Fetches the price of an asset of the position from the Vesu extension.
+    async fn fetch_vesu_price(
+        &self,
+        config: &Config,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+        asset: &Asset,
+    ) -> Result<BigDecimal> {
+        let price_request = &FunctionCall {
+            contract_address: config.extension_address,
+            entry_point_selector: *VESU_PRICE_SELECTOR,
+            calldata: vec![self.pool_id, asset.address],
+        };
+        let result = rpc_client
+            .call(price_request, BlockId::Tag(BlockTag::PreConfirmed))
+            .await?;
+        anyhow::ensure!(result.len() >= 3, "Unexpected Vesu price response");
+        anyhow::ensure!(
+            result[2] != Felt::ZERO,
+            "Vesu price of {} is not valid",
+            asset.name
+        );
+        let price = u256_felts_to_big_decimal(&result[0], &result[1], VESU_RESPONSE_DECIMALS);
+        anyhow::ensure!(
+            price > BigDecimal::from(0),
+            "Vesu price of {} is zero",
+            asset.name
+        );
+        Ok(price)
+    }
+
+    /// Runs the collateralization check of the Vesu singleton, the one deciding if the
+    /// liquidation goes through. Returns if the position is collateralized & its LTV.
+    pub async fn vesu_collateralization(
+        &self,
+        config: &Config,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    ) -> Result<(bool, BigDecimal)> {
+        let check_request = &FunctionCall {
+            contract_address: config.singleton_address,
+            entry_point_selector: *VESU_CHECK_COLLATERALIZATION_SELECTOR,
+            calldata: self.as_update_calldata(),
+        };
+        let result = rpc_client
+            .call(check_request, BlockId::Tag(BlockTag::PreConfirmed))
+            .await?;
+        anyhow::ensure!(
+            result.len() >= 5,
+            "Unexpected Vesu collateralization response"
+        );
+
+        let is_collateralized = result[0] != Felt::ZERO;
+        let collateral_value =
+            u256_felts_to_big_decimal(&result[1], &result[2], VESU_RESPONSE_DECIMALS);
+        let debt_value = u256_felts_to_big_decimal(&result[3], &result[4], VESU_RESPONSE_DECIMALS);
+        let ltv_ratio = if collateral_value > BigDecimal::from(0) {
+            debt_value / collateral_value
+        } else {
+            BigDecimal::default()
+        };
+        Ok((is_collateralized, ltv_ratio))
+    }
+
+    /// Returns true if the position is within `almost_liquidable_threshold` of its
+    /// LLTV, or already liquidable.
+    pub async fn is_almost_liquidable(
+        &self,
+        oracle_prices: &LatestOraclePrices,
+        almost_liquidable_threshold: &BigDecimal,
+    ) -> bool {
+        if self.lltv == BigDecimal::default() {
+            return false;
+        }
+        match self.ltv(oracle_prices).await {
+            Ok(ltv_ratio) => ltv_ratio > &self.lltv - almost_liquidable_threshold,
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the almost liquidable threshold of the position, widened by the LTV move
+    /// expected from the recent volatility & velocity of its assets prices.
+    pub fn almost_liquidable_threshold(
+        &self,
+        config: &Config,
+        price_history: &PriceHistory,
+    ) -> BigDecimal {
+        let rules = &config.volatility;
+        if rules.horizon.is_zero() {
+            return config.almost_liquidable_threshold.clone();
+        }
+        let expected_move = price_history.expected_ltv_move(
+            &self.collateral.name,
+            &self.debt.name,
+            rules.window,
+            rules.horizon,
+        );
+        &config.almost_liquidable_threshold + &self.lltv * expected_move
+    }
+
+    /// Logs the position if it is liquidable or within `almost_liquidable_threshold`
+    /// of its LLTV.
+    fn logs_if_almost_liquidable(
+        &self,
+        is_liquidable: bool,
+        ltv_ratio: BigDecimal,
+        almost_liquidable_threshold: &BigDecimal,
+    ) {
+        if is_liquidable || ltv_ratio > &self.lltv - almost_liquidable_threshold {
+            self.logs_liquidation_state(is_liquidable, ltv_ratio);
+        }
+    }
+
     fn logs_liquidation_state(&self, is_liquidable: bool, ltv_ratio: BigDecimal) {
         tracing::info!(
             "{} is at ratio {:.2}%/{:.2}% => {}",
@@ -171,13 +492,12 @@ impl Position {
         );
     }
 
-    // TODO : put that in cache in a map with poolid/collateral/debt as key
     /// Fetches the liquidation factor from the extension contract
     pub async fn fetch_liquidation_factors(
         &self,
         config: &Config,
         rpc_client: Arc<JsonRpcClient<HttpTransport>>,
-    ) -> BigDecimal {
+    ) -> Result<BigDecimal> {
         let calldata = vec![self.pool_id, self.collateral.address, self.debt.address];
 
         let liquidation_config_request = &FunctionCall {
@@ -191,9 +511,11 @@ impl Position {
                 liquidation_config_request,
                 BlockId::Tag(BlockTag::PreConfirmed),
             )
-            .await
-            .expect("failed to retrieve");
-        BigDecimal::new(ltv_config[0].to_bigint(), VESU_RESPONSE_DECIMALS)
+            .await?;
+        Ok(BigDecimal::new(
+            ltv_config[0].to_bigint(),
+            VESU_RESPONSE_DECIMALS,
+        ))
     }
 
     pub async fn update(
@@ -226,51 +548,82 @@ impl Position {
         rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
         singleton_address: &Felt,
     ) -> anyhow::Result<()> {
-        self.update_amounts(rpc_client, singleton_address).await?;
-        self.update_lltv(rpc_client, singleton_address).await?;
-        Ok(())
+        Self::update_batch(std::slice::from_mut(self), rpc_client, singleton_address)
+            .await
+            .remove(0)
     }
 
-    async fn update_amounts(
-        &mut self,
+    /// Refreshes the amounts & LLTV of several positions through a single batch
+    /// request, returning the result of each refresh.
+    pub async fn update_batch(
+        positions: &mut [Position],
         rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
         singleton_address: &Felt,
-    ) -> anyhow::Result<()> {
-        let get_position_request = &FunctionCall {
+    ) -> Vec<anyhow::Result<()>> {
+        let calls: Vec<FunctionCall> = positions
+            .iter()
+            .flat_map(|position| {
+                [
+                    position.position_request(singleton_address),
+                    position.ltv_config_request(singleton_address),
+                ]
+            })
+            .collect();
+        let mut results = batch_calls(rpc_client, &calls, BlockId::Tag(BlockTag::PreConfirmed))
+            .await
+            .into_iter();
+
+        positions
+            .iter_mut()
+            .map(|position| {
+                let (Some(amounts), Some(ltv_config)) = (results.next(), results.next()) else {
+                    bail!("Missing position response in the batch");
+                };
+                position.apply_amounts(&amounts?)?;
+                position.apply_ltv_config(&ltv_config?)?;
+                Ok(())
+            })
+            .collect()
+    }
+
+    fn position_request(&self, singleton_address: &Felt) -> FunctionCall {
+        FunctionCall {
             contract_address: *singleton_address,
             entry_point_selector: *VESU_POSITION_UNSAFE_SELECTOR,
             calldata: self.as_update_calldata(),
-        };
-        let result = rpc_client
-            .call(get_position_request, BlockId::Tag(BlockTag::PreConfirmed))
-            .await?;
-
-        let new_collateral = BigDecimal::new(result[4].to_bigint(), self.collateral.decimals);
-        let new_debt = BigDecimal::new(result[6].to_bigint(), self.debt.decimals);
-        self.collateral.amount = new_collateral;
-        self.debt.amount = new_debt;
-        Ok(())
+        }
     }
 
-    async fn update_lltv(
-        &mut self,
-        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
-        singleton_address: &Felt,
-    ) -> anyhow::Result<()> {
-        let ltv_config_request = &FunctionCall {
+    fn ltv_config_request(&self, singleton_address: &Felt) -> FunctionCall {
+        FunctionCall {
             contract_address: *singleton_address,
             entry_point_selector: *VESU_LTV_CONFIG_SELECTOR,
             calldata: self.as_ltv_calldata(),
-        };
+        }
+    }
 
-        let ltv_config = rpc_client
-            .call(ltv_config_request, BlockId::Tag(BlockTag::PreConfirmed))
-            .await?;
+    /// Updates the amounts from the `position_unsafe` response of the singleton.
+    fn apply_amounts(&mut self, result: &[Felt]) -> anyhow::Result<()> {
+        anyhow::ensure!(result.len() >= 7, "Invalid position_unsafe response");
+        self.collateral.amount = BigDecimal::new(result[4].to_bigint(), self.collateral.decimals);
+        self.debt.amount = BigDecimal::new(result[6].to_bigint(), self.debt.decimals);
+        Ok(())
+    }
 
-        self.lltv = BigDecimal::new(ltv_config[0].to_bigint(), VESU_RESPONSE_DECIMALS);
+    /// Updates the LLTV from the `ltv_config` response of the singleton.
+    fn apply_ltv_config(&mut self, result: &[Felt]) -> anyhow::Result<()> {
+        let max_ltv = result
+            .first()
+            .ok_or_else(|| anyhow!("Empty ltv_config response"))?;
+        self.lltv = BigDecimal::new(max_ltv.to_bigint(), VESU_RESPONSE_DECIMALS);
         Ok(())
     }
 
+    /// Returns the (pool, collateral, debt) of the position.
+    fn pair_key(&self) -> (Felt, Felt, Felt) {
+        (self.pool_id, self.collateral.address, self.debt.address)
+    }
+
     /// Returns a unique identifier for the position by hashing the update calldata.
     pub fn key(&self) -> u64 {
         let mut hasher = std::hash::DefaultHasher::new();
@@ -278,21 +631,110 @@ impl Position {
         hasher.finish()
     }
 
+    /// Returns the route used to swap the collateral into the debt to repay.
+    pub async fn get_liquidation_route(&self, route_provider: &dyn RouteProvider) -> Result<Route> {
+        route_provider
+            .get_route(
+                self.debt.address,
+                self.collateral.address,
+                &self.debt.amount,
+            )
+            .await
+    }
+
+    /// Returns the swap converting the collateral left after the liquidation into
+    /// the withdraw target asset of the config, if any, with a route fetched from the
+    /// providers.
+    pub async fn get_withdraw_swap(
+        &self,
+        config: &Config,
+        route_provider: &dyn RouteProvider,
+        liquidation_factor: &BigDecimal,
+        liquidation_route: &Route,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Result<Option<WithdrawSwap>> {
+        let Some(plan) =
+            self.withdraw_plan(config, liquidation_factor, liquidation_route, oracle_prices)?
+        else {
+            return Ok(None);
+        };
+        let route = route_provider
+            .get_exact_input_route(plan.collateral, plan.target.address, &plan.residual)
+            .await?;
+        Ok(Some(WithdrawSwap {
+            route,
+            min_amount_out: plan.min_amount_out,
+        }))
+    }
+
+    /// Returns how the collateral left after the liquidation is converted into the
+    /// withdraw target asset of the config, if any.
+    /// That collateral is the oracle estimate net of the pool fees of the liquidation
+    /// route, and the minimum output is its oracle value minus the max slippage.
+    pub fn withdraw_plan(
+        &self,
+        config: &Config,
+        liquidation_factor: &BigDecimal,
+        liquidation_route: &Route,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Result<Option<WithdrawPlan>> {
+        let Some(target_address) = config.withdraw.target else {
+            return Ok(None);
+        };
+        if target_address == self.collateral.address {
+            return Ok(None);
+        }
+        let target = Asset::from_address(config, target_address)
+            .ok_or_else(|| anyhow!("Unknown withdraw target asset {target_address:#x}"))?;
+        let target_price = oracle_prices
+            .price_of(&target.name)
+            .filter(|price| *price > BigDecimal::from(0))
+            .ok_or_else(|| anyhow!("Price not found for withdraw target: {}", target.name))?;
+
+        let (collateral_price, debt_price) = self.prices(oracle_prices)?;
+        // The collateral sold to repay the debt pays the pool fees on top of its value.
+        let liquidation_fees = &self.debt.amount * debt_price * route_fee(liquidation_route);
+        let residual_value = self
+            .estimated_liquidation_profit(liquidation_factor, oracle_prices)?
+            - liquidation_fees;
+        anyhow::ensure!(
+            residual_value > BigDecimal::from(0),
+            "No collateral left to convert after the liquidation"
+        );
+        let residual = (&residual_value / &collateral_price).with_scale(self.collateral.decimals);
+
+        let min_amount_out = (residual_value
+            * (BigDecimal::from(1) - &config.withdraw.max_slippage)
+            / &target_price)
+            .with_scale(target.decimals);
+        let (min_amount_out, _) = min_amount_out.as_bigint_and_exponent();
+        Ok(Some(WithdrawPlan {
+            collateral: self.collateral.address,
+            target,
+            residual,
+            price: collateral_price / target_price,
+            min_amount_out: min_amount_out.try_into()?,
+        }))
+    }
+
     /// Returns the TX necessary to liquidate this position using the Vesu Liquidate
     /// contract.
-    pub async fn get_vesu_liquidate_tx(
+    pub fn get_vesu_liquidate_tx(
         &self,
         liquidate_contract: &Arc<Liquidate<StarknetSingleOwnerAccount>>,
-        http_client: &reqwest::Client,
+        route: Route,
+        withdraw_swap: Option<WithdrawSwap>,
         liquidator_address: &Felt,
-    ) -> Result<Call> {
-        let (liquidate_swap, liquidate_swap_weights) = get_ekubo_route(
-            http_client,
-            self.debt.address,
-            self.collateral.address,
-            &self.debt.amount,
-        )
-        .await?;
+    ) -> Call {
+        let (liquidate_swap, liquidate_swap_weights) = route;
+        let (withdraw_swap, withdraw_swap_weights, withdraw_swap_limit_amount) = match withdraw_swap
+        {
+            Some(WithdrawSwap {
+                route: (swaps, weights),
+                min_amount_out,
+            }) => (swaps, weights, min_amount_out),
+            None => (vec![], vec![], 0),
+        };
 
         let liquidate_params = LiquidateParams {
             pool_id: self.pool_id,
@@ -305,11 +747,11 @@ impl Position {
             liquidate_swap,
             liquidate_swap_weights,
             liquidate_swap_limit_amount: u128::MAX,
-            withdraw_swap: vec![],
-            withdraw_swap_limit_amount: 0,
-            withdraw_swap_weights: vec![],
+            withdraw_swap,
+            withdraw_swap_limit_amount,
+            withdraw_swap_weights,
         };
-        Ok(liquidate_contract.liquidate_getcall(&liquidate_params))
+        liquidate_contract.liquidate_getcall(&liquidate_params)
     }
 
     /// Returns the position as a calldata for the LTV config RPC call.
@@ -341,3 +783,143 @@ impl fmt::Display for Position {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, str::FromStr};
+
+    use bigdecimal::BigDecimal;
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use crate::{
+        config::{
+            Config, LiquidabilityCheck, LiquidationMode, VESU_CHECK_COLLATERALIZATION_SELECTOR,
+            VESU_LTV_CONFIG_SELECTOR, VESU_POSITION_UNSAFE_SELECTOR,
+        },
+        services::oracle::{LatestOraclePrices, OraclePrice},
+        types::{asset::Asset, position_state::PositionLifecycle},
+        utils::test_utils::serve_json_rpc,
+    };
+
+    use super::Position;
+
+    fn position(user_address: u64) -> Position {
+        Position {
+            user_address: Felt::from(user_address),
+            pool_id: Felt::ONE,
+            collateral: Asset::new("ETH".to_string(), Felt::from(0xe), 18),
+            debt: Asset::new("USDC".to_string(), Felt::from(0xd), 6),
+            lltv: BigDecimal::default(),
+            lifecycle: PositionLifecycle::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_batch() {
+        // The singleton fails to read the position of the user 0xbad.
+        let rpc_client = serve_json_rpc(|_, params| {
+            let request = &params["request"];
+            let selector =
+                Felt::from_hex(request["entry_point_selector"].as_str().unwrap()).unwrap();
+            if selector == *VESU_POSITION_UNSAFE_SELECTOR {
+                if request["calldata"][3] == json!("0xbad") {
+                    return Err(json!({ "code": 40, "message": "Contract error" }));
+                }
+                // Collateral of 2 ETH & debt of 1500 USDC.
+                Ok(json!([
+                    "0x0",
+                    "0x0",
+                    "0x0",
+                    "0x0",
+                    "0x1bc16d674ec80000",
+                    "0x0",
+                    "0x59682f00"
+                ]))
+            } else if selector == *VESU_LTV_CONFIG_SELECTOR {
+                // LLTV of 80%.
+                Ok(json!(["0xb1a2bc2ec500000"]))
+            } else {
+                Err(json!({ "code": 21, "message": "Invalid message selector" }))
+            }
+        })
+        .await;
+
+        let mut positions = vec![position(1), position(0xbad), position(2)];
+        let results = Position::update_batch(&mut positions, &rpc_client, &Felt::TWO).await;
+
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+        for position in [&positions[0], &positions[2]] {
+            assert_eq!(position.collateral.amount, BigDecimal::from(2));
+            assert_eq!(position.debt.amount, BigDecimal::from(1500));
+            assert_eq!(position.lltv, BigDecimal::from_str("0.8").unwrap());
+        }
+        // The failing position is left as is.
+        assert_eq!(positions[1].collateral.amount, BigDecimal::default());
+    }
+
+    /// Compares the Pragma prices with the Vesu collateralization check of a position
+    /// at a LTV of 60% & a LLTV of 80%, Vesu answering with `is_collateralized`.
+    /// Returns if the position is liquidable & if both checks agree.
+    async fn compare_checks(is_collateralized: bool) -> (bool, Option<bool>) {
+        let rpc_client = serve_json_rpc(move |_, params| {
+            let selector =
+                Felt::from_hex(params["request"]["entry_point_selector"].as_str().unwrap())
+                    .unwrap();
+            if selector != *VESU_CHECK_COLLATERALIZATION_SELECTOR {
+                return Err(json!({ "code": 21, "message": "Invalid message selector" }));
+            }
+            // Collateral value of 5000 & debt value of 3000, as u256 with 18 decimals.
+            Ok(json!([
+                if is_collateralized { "0x1" } else { "0x0" },
+                "0x10f0cf064dd59200000",
+                "0x0",
+                "0xa2a15d09519be00000",
+                "0x0"
+            ]))
+        })
+        .await;
+
+        let mut config = Config::new(
+            &"mainnet".parse().unwrap(),
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        config.liquidability_check = LiquidabilityCheck::VesuCollateralization;
+        let oracle_prices = LatestOraclePrices::default();
+        for (ticker, price) in [("eth", 2500), ("usdc", 1)] {
+            let price = OraclePrice {
+                price: BigDecimal::from(price),
+                ..Default::default()
+            };
+            oracle_prices.0.insert(ticker.to_string(), price);
+        }
+        let mut position = position(1);
+        position.collateral.amount = BigDecimal::from(2);
+        position.debt.amount = BigDecimal::from(3000);
+        position.lltv = BigDecimal::from_str("0.8").unwrap();
+
+        let is_liquidable = position
+            .check_liquidability(&config, &rpc_client, &oracle_prices)
+            .await
+            .unwrap();
+        let agree = position
+            .compare_liquidability_checks(&config, &rpc_client, &oracle_prices)
+            .await;
+        (is_liquidable, agree)
+    }
+
+    #[tokio::test]
+    async fn test_vesu_collateralization_agrees() {
+        assert_eq!(compare_checks(true).await, (false, Some(true)));
+    }
+
+    #[tokio::test]
+    async fn test_vesu_collateralization_disagrees() {
+        // Vesu decides, the position being safe according to Pragma.
+        assert_eq!(compare_checks(false).await, (true, Some(false)));
+    }
+}
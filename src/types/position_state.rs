@@ -0,0 +1,244 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::config::RetryRules;
+
+/// Stage of a monitored position in its liquidation lifecycle.
+#[derive(Default, Clone, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum PositionState {
+    #[default]
+    Healthy,
+    /// Within the almost liquidable threshold of its LLTV.
+    NearLiquidation,
+    Liquidatable,
+    /// A liquidation transaction has been sent & is not final yet.
+    InFlight {
+        tx_hash: Felt,
+    },
+    /// The last liquidation failed, it is retried after `until` (unix timestamp).
+    Failed {
+        until: u64,
+    },
+    /// Too many liquidations failed in a row, it is not liquidated until it's healthy.
+    Quarantined,
+    /// Liquidated by the bot.
+    Liquidated,
+    /// No collateral nor debt left.
+    Closed,
+}
+
+impl PositionState {
+    /// Name of the state, e.g `in_flight`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PositionState::Healthy => "healthy",
+            PositionState::NearLiquidation => "near_liquidation",
+            PositionState::Liquidatable => "liquidatable",
+            PositionState::InFlight { .. } => "in_flight",
+            PositionState::Failed { .. } => "failed",
+            PositionState::Quarantined => "quarantined",
+            PositionState::Liquidated => "liquidated",
+            PositionState::Closed => "closed",
+        }
+    }
+}
+
+impl fmt::Display for PositionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Liquidation state of a position along with its attempt counters.
+#[derive(Default, Clone, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PositionLifecycle {
+    #[serde(flatten)]
+    pub state: PositionState,
+    /// Liquidations attempted, whether their transaction was sent or not.
+    pub attempts: u32,
+    /// Liquidations failed since the last success or the last time it was healthy.
+    pub consecutive_failures: u32,
+    /// Unix timestamp of the last liquidation attempt.
+    pub last_attempt: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl PositionLifecycle {
+    /// Returns false while a liquidation is in flight or cooling down after a failure.
+    pub fn needs_check(&self, now: u64) -> bool {
+        match self.state {
+            PositionState::InFlight { .. } => false,
+            PositionState::Failed { until } => now >= until,
+            _ => true,
+        }
+    }
+
+    pub fn should_liquidate(&self) -> bool {
+        self.state == PositionState::Liquidatable
+    }
+
+    /// Returns the hash of the liquidation transaction in flight, if any.
+    pub fn in_flight_tx(&self) -> Option<Felt> {
+        match self.state {
+            PositionState::InFlight { tx_hash } => Some(tx_hash),
+            _ => None,
+        }
+    }
+
+    /// Moves the position according to its last liquidability check. A quarantined
+    /// position stays so while it's liquidable.
+    pub fn observe(&mut self, is_liquidable: bool, is_almost_liquidable: bool, now: u64) {
+        if !self.needs_check(now) || (self.state == PositionState::Quarantined && is_liquidable) {
+            return;
+        }
+        self.state = if is_liquidable {
+            PositionState::Liquidatable
+        } else {
+            self.consecutive_failures = 0;
+            if is_almost_liquidable {
+                PositionState::NearLiquidation
+            } else {
+                PositionState::Healthy
+            }
+        };
+    }
+
+    pub fn close(&mut self) {
+        if self.in_flight_tx().is_none() {
+            self.state = PositionState::Closed;
+        }
+    }
+
+    pub fn start_attempt(&mut self, now: u64) {
+        self.attempts += 1;
+        self.last_attempt = Some(now);
+    }
+
+    pub fn sent(&mut self, tx_hash: Felt) {
+        self.state = PositionState::InFlight { tx_hash };
+    }
+
+    pub fn succeed(&mut self) {
+        self.state = PositionState::Liquidated;
+        self.consecutive_failures = 0;
+        self.last_error = None;
+    }
+
+    /// Forgets the liquidation in flight, checking the position again right away.
+    pub fn abandon_in_flight(&mut self) {
+        if self.in_flight_tx().is_some() {
+            self.state = PositionState::Liquidatable;
+        }
+    }
+
+    /// The transaction never landed: the position can be liquidated again right away.
+    pub fn drop_attempt(&mut self) {
+        self.state = PositionState::Liquidatable;
+    }
+
+    /// Records a failed liquidation: the position cools down for a backoff doubling
+    /// with each consecutive failure, and is quarantined after `max_failures` of them.
+    pub fn fail(&mut self, error: String, now: u64, rules: &RetryRules) {
+        self.consecutive_failures += 1;
+        self.last_error = Some(error);
+        self.state = if self.consecutive_failures >= rules.max_failures {
+            PositionState::Quarantined
+        } else {
+            let backoff = backoff(self.consecutive_failures, rules);
+            PositionState::Failed {
+                until: now + backoff.as_secs(),
+            }
+        };
+    }
+}
+
+/// Cooldown after the given number of consecutive failures.
+fn backoff(consecutive_failures: u32, rules: &RetryRules) -> Duration {
+    let factor = 2_u32.saturating_pow(consecutive_failures.saturating_sub(1));
+    rules
+        .backoff
+        .checked_mul(factor)
+        .unwrap_or(rules.max_backoff)
+        .min(rules.max_backoff)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use crate::config::RetryRules;
+
+    use super::{PositionLifecycle, PositionState, backoff};
+
+    fn rules() -> RetryRules {
+        RetryRules {
+            backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(100),
+            max_failures: 3,
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1, &rules()), Duration::from_secs(30));
+        assert_eq!(backoff(2, &rules()), Duration::from_secs(60));
+        assert_eq!(backoff(3, &rules()), Duration::from_secs(100));
+        assert_eq!(backoff(64, &rules()), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut lifecycle = PositionLifecycle::default();
+        lifecycle.observe(false, true, 0);
+        assert_eq!(lifecycle.state, PositionState::NearLiquidation);
+        lifecycle.observe(true, false, 0);
+        assert!(lifecycle.should_liquidate());
+
+        lifecycle.start_attempt(10);
+        lifecycle.sent(Felt::ONE);
+        assert_eq!(lifecycle.in_flight_tx(), Some(Felt::ONE));
+        assert!(!lifecycle.needs_check(10));
+        lifecycle.fail("reverted".to_string(), 20, &rules());
+        assert_eq!(lifecycle.state, PositionState::Failed { until: 50 });
+        // Cooling down.
+        lifecycle.observe(true, false, 30);
+        assert!(!lifecycle.should_liquidate());
+        lifecycle.observe(true, false, 50);
+        assert!(lifecycle.should_liquidate());
+
+        lifecycle.start_attempt(50);
+        lifecycle.sent(Felt::TWO);
+        lifecycle.drop_attempt();
+        assert!(lifecycle.should_liquidate());
+        assert_eq!(lifecycle.attempts, 2);
+        assert_eq!(lifecycle.consecutive_failures, 1);
+
+        lifecycle.start_attempt(60);
+        lifecycle.sent(Felt::THREE);
+        lifecycle.succeed();
+        assert_eq!(lifecycle.state, PositionState::Liquidated);
+        assert_eq!(lifecycle.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_quarantine() {
+        let mut lifecycle = PositionLifecycle::default();
+        for _ in 0..3 {
+            lifecycle.start_attempt(0);
+            lifecycle.fail("reverted".to_string(), 0, &rules());
+        }
+        assert_eq!(lifecycle.state, PositionState::Quarantined);
+        lifecycle.observe(true, false, 1000);
+        assert_eq!(lifecycle.state, PositionState::Quarantined);
+
+        // Released once healthy.
+        lifecycle.observe(false, false, 1000);
+        assert_eq!(lifecycle.state, PositionState::Healthy);
+        assert_eq!(lifecycle.consecutive_failures, 0);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use bigdecimal::BigDecimal;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+/// Price of an asset read by the oracle service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub price: BigDecimal,
+    /// Unix timestamp at which the price was read.
+    pub timestamp: u64,
+    /// Block on which the price was read.
+    pub block_number: u64,
+}
+
+/// Thread-safe bounded history of the prices of the monitored assets, by
+/// lowercase ticker, oldest first.
+#[derive(Clone, Default)]
+pub struct PriceHistory(pub Arc<DashMap<String, VecDeque<PricePoint>>>);
+
+impl PriceHistory {
+    pub fn from_points(points: HashMap<String, Vec<PricePoint>>) -> Self {
+        let history = DashMap::new();
+        for (ticker, points) in points {
+            history.insert(ticker.to_lowercase(), VecDeque::from(points));
+        }
+        Self(Arc::new(history))
+    }
+
+    /// Appends a price to the history of an asset, dropping the oldest ones beyond
+    /// `capacity`. Prices read on an already recorded block are ignored.
+    pub fn record(&self, ticker: &str, point: PricePoint, capacity: usize) {
+        let mut points = self.0.entry(ticker.to_lowercase()).or_default();
+        if points
+            .back()
+            .is_some_and(|last| last.block_number >= point.block_number)
+        {
+            return;
+        }
+        points.push_back(point);
+        while points.len() > capacity {
+            points.pop_front();
+        }
+    }
+
+    /// Returns the recorded prices of an asset, oldest first.
+    pub fn points(&self, ticker: &str) -> Vec<PricePoint> {
+        self.0
+            .get(&ticker.to_lowercase())
+            .map(|points| points.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the whole history, by lowercase ticker.
+    pub fn snapshot(&self) -> HashMap<String, Vec<PricePoint>> {
+        self.0
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().iter().cloned().collect()))
+            .collect()
+    }
+
+    /// Returns the prices of an asset recorded during the last `window`.
+    pub fn recent_points(&self, ticker: &str, window: Duration) -> Vec<PricePoint> {
+        recent_points(&self.points(ticker), window).to_vec()
+    }
+
+    /// Realized volatility of an asset over the last `window`, per square root of second.
+    pub fn volatility(&self, ticker: &str, window: Duration) -> Option<BigDecimal> {
+        volatility(&self.recent_points(ticker, window))
+    }
+
+    /// Relative price change of an asset per second over the last `window`.
+    pub fn velocity(&self, ticker: &str, window: Duration) -> Option<BigDecimal> {
+        velocity(&self.recent_points(ticker, window))
+    }
+
+    /// Relative move of the LTV of a collateral/debt position expected over `horizon`,
+    /// from the recent price history: its drift toward the LLTV & its volatility.
+    pub fn expected_ltv_move(
+        &self,
+        collateral: &str,
+        debt: &str,
+        window: Duration,
+        horizon: Duration,
+    ) -> BigDecimal {
+        let horizon = BigDecimal::from(horizon.as_secs());
+        // The LTV moves as the debt price over the collateral price.
+        let velocity = self.velocity(debt, window).unwrap_or_default()
+            - self.velocity(collateral, window).unwrap_or_default();
+        let drift = (velocity * &horizon).max(BigDecimal::from(0));
+
+        let variance = [collateral, debt]
+            .iter()
+            .filter_map(|ticker| self.volatility(ticker, window))
+            .map(|volatility| &volatility * &volatility)
+            .sum::<BigDecimal>();
+        let volatility = (variance * horizon).sqrt().unwrap_or_default();
+
+        drift + volatility
+    }
+}
+
+/// Returns the points recorded during the `window` preceding the last point.
+fn recent_points(points: &[PricePoint], window: Duration) -> &[PricePoint] {
+    let Some(last) = points.last() else {
+        return points;
+    };
+    let since = last.timestamp.saturating_sub(window.as_secs());
+    let start = points.partition_point(|point| point.timestamp < since);
+    &points[start..]
+}
+
+/// Realized volatility of the prices, per square root of second: the square root of
+/// the sum of the squared relative returns over the elapsed time.
+fn volatility(points: &[PricePoint]) -> Option<BigDecimal> {
+    let elapsed = points
+        .last()?
+        .timestamp
+        .checked_sub(points.first()?.timestamp)?;
+    if elapsed == 0 {
+        return None;
+    }
+    let squared_returns = points
+        .windows(2)
+        .filter(|pair| pair[0].price > BigDecimal::from(0))
+        .map(|pair| {
+            let price_return = (&pair[1].price - &pair[0].price) / &pair[0].price;
+            &price_return * &price_return
+        })
+        .sum::<BigDecimal>();
+    (squared_returns / BigDecimal::from(elapsed)).sqrt()
+}
+
+/// Relative price change per second between the first & the last prices.
+fn velocity(points: &[PricePoint]) -> Option<BigDecimal> {
+    let (first, last) = (points.first()?, points.last()?);
+    let elapsed = last.timestamp.checked_sub(first.timestamp)?;
+    if elapsed == 0 || first.price <= BigDecimal::from(0) {
+        return None;
+    }
+    Some((&last.price - &first.price) / &first.price / BigDecimal::from(elapsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, time::Duration};
+
+    use bigdecimal::BigDecimal;
+
+    use super::{PriceHistory, PricePoint, recent_points, velocity, volatility};
+
+    fn point(price: &str, timestamp: u64) -> PricePoint {
+        PricePoint {
+            price: BigDecimal::from_str(price).unwrap(),
+            timestamp,
+            block_number: timestamp,
+        }
+    }
+
+    #[test]
+    fn test_record_is_bounded() {
+        let history = PriceHistory::default();
+        for timestamp in 0..5 {
+            history.record("ETH", point("2000", timestamp), 3);
+        }
+        // Already recorded block.
+        history.record("eth", point("2100", 4), 3);
+
+        let points = history.points("eth");
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].timestamp, 2);
+        assert_eq!(points[2].price, BigDecimal::from(2000));
+    }
+
+    #[test]
+    fn test_recent_points() {
+        let points = [
+            point("1", 0),
+            point("1", 10),
+            point("1", 20),
+            point("1", 30),
+        ];
+        assert_eq!(recent_points(&points, Duration::from_secs(15)).len(), 2);
+        assert_eq!(recent_points(&points, Duration::from_secs(60)).len(), 4);
+        assert!(recent_points(&[], Duration::from_secs(60)).is_empty());
+    }
+
+    #[test]
+    fn test_velocity_and_volatility() {
+        let points = [point("100", 0), point("101", 10), point("102", 20)];
+        assert_eq!(
+            velocity(&points).unwrap(),
+            BigDecimal::from_str("0.001").unwrap()
+        );
+        assert!(volatility(&points).unwrap() > BigDecimal::from(0));
+
+        let flat = [point("100", 0), point("100", 10)];
+        assert_eq!(velocity(&flat).unwrap(), BigDecimal::from(0));
+        assert_eq!(volatility(&flat).unwrap(), BigDecimal::from(0));
+        assert!(velocity(&flat[..1]).is_none());
+    }
+
+    #[test]
+    fn test_expected_ltv_move() {
+        let history = PriceHistory::default();
+        for (timestamp, price) in [(0, "100"), (10, "99"), (20, "98")] {
+            history.record("strk", point(price, timestamp), 10);
+            history.record("usdc", point("1", timestamp), 10);
+        }
+        let window = Duration::from_secs(60);
+
+        // The collateral price going down pushes the LTV up.
+        let toward_lltv =
+            history.expected_ltv_move("strk", "usdc", window, Duration::from_secs(60));
+        assert!(toward_lltv > BigDecimal::from_str("0.06").unwrap());
+        // Only the volatility is expected when the LTV goes down.
+        let away_from_lltv =
+            history.expected_ltv_move("usdc", "strk", window, Duration::from_secs(60));
+        assert!(away_from_lltv < toward_lltv);
+        assert!(away_from_lltv > BigDecimal::from(0));
+    }
+}
@@ -0,0 +1,660 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, anyhow, bail};
+use bigdecimal::{BigDecimal, num_bigint::BigInt};
+use chrono::Utc;
+use dashmap::{DashMap, DashSet};
+use futures_util::future::join_all;
+use serde_json::Value;
+use starknet::{
+    core::{
+        types::{BlockId, BlockTag, Felt, FunctionCall},
+        utils::{cairo_short_string_to_felt, get_selector_from_name},
+    },
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+
+use crate::{
+    config::{Asset, AssetOracleConfig, Config, PriceSourceKind},
+    services::oracle::{AggregationMode, LatestOraclePrices, OraclePrice},
+    utils::{batch::batch_calls, conversions::hex_str_to_big_decimal, ekubo::EkuboPools},
+};
+
+/// Timeout of the requests to the HTTP price APIs.
+const HTTP_PRICE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Source of the price in dollars of the monitored assets.
+#[async_trait::async_trait]
+pub trait PriceSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Returns the price in dollars of the asset with the provided (lowercase) ticker.
+    async fn get_price(&self, ticker: &str) -> Result<OraclePrice>;
+
+    /// Returns the prices of several assets, read on the provided block by the
+    /// on-chain sources. Sends one request per asset, unless batched by the source.
+    async fn get_prices(&self, tickers: &[String], _block_id: BlockId) -> Vec<Result<OraclePrice>> {
+        join_all(tickers.iter().map(|ticker| self.get_price(ticker))).await
+    }
+}
+
+/// Returns the Pragma feed of the monitored asset with the provided ticker.
+fn asset_feed(asset_map: &DashMap<Felt, Asset>, ticker: &str) -> AssetOracleConfig {
+    asset_map
+        .iter()
+        .find(|entry| entry.value().ticker.eq_ignore_ascii_case(ticker))
+        .map(|entry| entry.value().oracle.clone())
+        .unwrap_or_default()
+}
+
+/// Converts a price quoted in the quote asset of a feed into dollars.
+fn feed_price_in_dollars(
+    price: BigDecimal,
+    feed: &AssetOracleConfig,
+    latest_prices: &LatestOraclePrices,
+) -> Result<BigDecimal> {
+    let Some(quote_asset) = &feed.quote_asset else {
+        return Ok(price);
+    };
+    let quote_price = latest_prices
+        .price_of(quote_asset)
+        .filter(|price| *price > BigDecimal::from(0))
+        .with_context(|| format!("No price for the quote asset {quote_asset}"))?;
+    Ok(price * quote_price)
+}
+
+/// Reads the Pragma prices of several assets in a single batch request, on the
+/// provided block. `build_call` builds the call of a pair from its id & its feed,
+/// `parse_price` decodes the price of a pair, in its quote asset, from the call result.
+async fn batch_feed_prices(
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    asset_map: &DashMap<Felt, Asset>,
+    latest_prices: &LatestOraclePrices,
+    tickers: &[String],
+    block_id: BlockId,
+    build_call: impl Fn(&str, &AssetOracleConfig) -> Result<FunctionCall>,
+    parse_price: impl Fn(&str, &[Felt]) -> Result<OraclePrice>,
+) -> Vec<Result<OraclePrice>> {
+    let feeds: Vec<(String, AssetOracleConfig)> = tickers
+        .iter()
+        .map(|ticker| {
+            let feed = asset_feed(asset_map, ticker);
+            (feed.pair_id(ticker), feed)
+        })
+        .collect();
+    let requests: Vec<Result<FunctionCall>> = feeds
+        .iter()
+        .map(|(pair_id, feed)| build_call(pair_id, feed))
+        .collect();
+
+    let valid_requests: Vec<FunctionCall> = requests
+        .iter()
+        .filter_map(|r| r.as_ref().ok())
+        .cloned()
+        .collect();
+    let mut results = batch_calls(rpc_client, &valid_requests, block_id)
+        .await
+        .into_iter();
+
+    feeds
+        .iter()
+        .zip(requests)
+        .map(|((pair_id, feed), request)| {
+            request?;
+            let call_result = results
+                .next()
+                .context("Missing Pragma response in the batch")??;
+            let mut oracle_price = parse_price(pair_id, &call_result)?;
+            oracle_price.price = feed_price_in_dollars(oracle_price.price, feed, latest_prices)?;
+            Ok(oracle_price)
+        })
+        .collect()
+}
+
+/// Price of the Pragma oracle, aggregated as configured for each asset.
+pub struct PragmaOracle {
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    pragma_address: Felt,
+    /// Monitored assets, with their Pragma feed.
+    asset_map: Arc<DashMap<Felt, Asset>>,
+    latest_prices: LatestOraclePrices,
+}
+
+impl PragmaOracle {
+    pub fn new(rpc_client: Arc<JsonRpcClient<HttpTransport>>, pragma_address: Felt) -> Self {
+        Self {
+            rpc_client,
+            pragma_address,
+            asset_map: Arc::default(),
+            latest_prices: LatestOraclePrices::default(),
+        }
+    }
+
+    /// Uses the Pragma feeds of the monitored assets, the prices of the quote assets
+    /// being read from the latest prices.
+    pub fn with_assets(
+        mut self,
+        asset_map: Arc<DashMap<Felt, Asset>>,
+        latest_prices: LatestOraclePrices,
+    ) -> Self {
+        self.asset_map = asset_map;
+        self.latest_prices = latest_prices;
+        self
+    }
+
+    /// Fetches the price of a Pragma pair, in its quote asset.
+    pub async fn get_pair_price(
+        &self,
+        pair_id: &str,
+        aggregation_mode: AggregationMode,
+    ) -> Result<OraclePrice> {
+        let request = self.pair_price_request(pair_id, aggregation_mode)?;
+        let mut results = batch_calls(
+            &self.rpc_client,
+            &[request],
+            BlockId::Tag(BlockTag::PreConfirmed),
+        )
+        .await;
+        parse_pair_price(pair_id, &results.remove(0)?)
+    }
+
+    fn pair_price_request(
+        &self,
+        pair_id: &str,
+        aggregation_mode: AggregationMode,
+    ) -> Result<FunctionCall> {
+        Ok(FunctionCall {
+            contract_address: self.pragma_address,
+            entry_point_selector: get_selector_from_name("get_data")?,
+            calldata: vec![
+                Felt::ZERO,
+                cairo_short_string_to_felt(pair_id)?,
+                aggregation_mode.to_felt(),
+            ],
+        })
+    }
+}
+
+/// Parses the `get_data` response of the Pragma oracle.
+fn parse_pair_price(pair_id: &str, call_result: &[Felt]) -> Result<OraclePrice> {
+    anyhow::ensure!(
+        call_result.len() >= 4,
+        "Invalid Pragma response for {pair_id}"
+    );
+    let asset_price = hex_str_to_big_decimal(
+        &call_result[0].to_hex_string(),
+        call_result[1].to_bigint().try_into()?,
+    );
+
+    Ok(OraclePrice {
+        price: asset_price,
+        timestamp: call_result[2].to_bigint().try_into()?,
+        num_sources: call_result[3].to_bigint().try_into()?,
+        ..Default::default()
+    })
+}
+
+#[async_trait::async_trait]
+impl PriceSource for PragmaOracle {
+    fn name(&self) -> &'static str {
+        "pragma median"
+    }
+
+    async fn get_price(&self, ticker: &str) -> Result<OraclePrice> {
+        let tickers = [ticker.to_string()];
+        self.get_prices(&tickers, BlockId::Tag(BlockTag::PreConfirmed))
+            .await
+            .remove(0)
+    }
+
+    /// Reads the prices of all the assets in a single batch request.
+    async fn get_prices(&self, tickers: &[String], block_id: BlockId) -> Vec<Result<OraclePrice>> {
+        batch_feed_prices(
+            &self.rpc_client,
+            &self.asset_map,
+            &self.latest_prices,
+            tickers,
+            block_id,
+            |pair_id, feed| self.pair_price_request(pair_id, feed.aggregation),
+            parse_pair_price,
+        )
+        .await
+    }
+}
+
+/// Time-weighted average price of the Pragma summary stats contract.
+pub struct PragmaTwap {
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    summary_stats_address: Felt,
+    window: Duration,
+    asset_map: Arc<DashMap<Felt, Asset>>,
+    latest_prices: LatestOraclePrices,
+}
+
+impl PragmaTwap {
+    fn twap_request(
+        &self,
+        pair_id: &str,
+        feed: &AssetOracleConfig,
+        now: u64,
+    ) -> Result<FunctionCall> {
+        let window = self.window.as_secs();
+        Ok(FunctionCall {
+            contract_address: self.summary_stats_address,
+            entry_point_selector: get_selector_from_name("calculate_twap")?,
+            calldata: vec![
+                // DataType::SpotEntry(pair)
+                Felt::ZERO,
+                cairo_short_string_to_felt(pair_id)?,
+                feed.aggregation.to_felt(),
+                Felt::from(window),
+                Felt::from(now.saturating_sub(window)),
+            ],
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for PragmaTwap {
+    fn name(&self) -> &'static str {
+        "pragma twap"
+    }
+
+    async fn get_price(&self, ticker: &str) -> Result<OraclePrice> {
+        let tickers = [ticker.to_string()];
+        self.get_prices(&tickers, BlockId::Tag(BlockTag::PreConfirmed))
+            .await
+            .remove(0)
+    }
+
+    /// Reads the TWAPs of all the assets in a single batch request.
+    async fn get_prices(&self, tickers: &[String], block_id: BlockId) -> Vec<Result<OraclePrice>> {
+        let now = Utc::now().timestamp() as u64;
+        batch_feed_prices(
+            &self.rpc_client,
+            &self.asset_map,
+            &self.latest_prices,
+            tickers,
+            block_id,
+            |pair_id, feed| self.twap_request(pair_id, feed, now),
+            |pair_id, call_result| parse_twap(pair_id, call_result, now),
+        )
+        .await
+    }
+}
+
+/// Parses the `calculate_twap` response of the Pragma summary stats contract.
+fn parse_twap(pair_id: &str, call_result: &[Felt], now: u64) -> Result<OraclePrice> {
+    anyhow::ensure!(
+        call_result.len() >= 2,
+        "Invalid Pragma TWAP response for {pair_id}"
+    );
+    Ok(OraclePrice {
+        price: hex_str_to_big_decimal(
+            &call_result[0].to_hex_string(),
+            call_result[1].to_bigint().try_into()?,
+        ),
+        timestamp: now,
+        num_sources: 1,
+        ..Default::default()
+    })
+}
+
+/// Spot price of the Ekubo pool between an asset & a quote asset, converted in
+/// dollars with the latest price of the quote asset.
+pub struct EkuboPoolPrice {
+    pools: EkuboPools,
+    config: Config,
+    quote_asset: String,
+    latest_prices: LatestOraclePrices,
+}
+
+impl EkuboPoolPrice {
+    /// Reads the spot price of the asset on the provided block.
+    async fn price_at(&self, ticker: &str, block_id: BlockId) -> Result<OraclePrice> {
+        if ticker.eq_ignore_ascii_case(&self.quote_asset) {
+            bail!("{ticker} is the quote asset");
+        }
+        let (address, asset) = self
+            .config
+            .get_asset_by_ticker(ticker)
+            .with_context(|| format!("Unknown asset {ticker}"))?;
+        let (quote_address, quote) = self
+            .config
+            .get_asset_by_ticker(&self.quote_asset)
+            .with_context(|| format!("Unknown quote asset {}", self.quote_asset))?;
+        let quote_price = self
+            .latest_prices
+            .price_of(&quote.ticker)
+            .filter(|price| *price > BigDecimal::from(0))
+            .with_context(|| format!("No price for the quote asset {}", quote.ticker))?;
+
+        let spot_price = self
+            .pools
+            .spot_price(address, quote_address, block_id)
+            .await?;
+        // Converts the price between the smallest units into a price between whole tokens.
+        let decimals_factor = BigDecimal::new(BigInt::from(1), quote.decimals - asset.decimals);
+
+        Ok(OraclePrice {
+            price: spot_price * decimals_factor * quote_price,
+            timestamp: Utc::now().timestamp() as u64,
+            num_sources: 1,
+            ..Default::default()
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for EkuboPoolPrice {
+    fn name(&self) -> &'static str {
+        "ekubo pool"
+    }
+
+    async fn get_price(&self, ticker: &str) -> Result<OraclePrice> {
+        self.price_at(ticker, BlockId::Tag(BlockTag::PreConfirmed))
+            .await
+    }
+
+    /// Reads the spot prices of all the assets on the provided block.
+    async fn get_prices(&self, tickers: &[String], block_id: BlockId) -> Vec<Result<OraclePrice>> {
+        join_all(tickers.iter().map(|ticker| self.price_at(ticker, block_id))).await
+    }
+}
+
+/// Price API returning JSON.
+pub struct HttpPriceApi {
+    http_client: reqwest::Client,
+    /// Url of the API, with `{ticker}` being replaced by the ticker of the asset.
+    url: String,
+    /// JSON pointer of the price in the response.
+    price_pointer: String,
+}
+
+#[async_trait::async_trait]
+impl PriceSource for HttpPriceApi {
+    fn name(&self) -> &'static str {
+        "http api"
+    }
+
+    async fn get_price(&self, ticker: &str) -> Result<OraclePrice> {
+        let url = self.url.replace("{ticker}", &ticker.to_ascii_uppercase());
+        let response: Value = self
+            .http_client
+            .get(&url)
+            .timeout(HTTP_PRICE_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let price = match response.pointer(&self.price_pointer) {
+            Some(Value::Number(price)) => price.to_string(),
+            Some(Value::String(price)) => price.clone(),
+            _ => bail!("No price at {} in the response", self.price_pointer),
+        };
+        Ok(OraclePrice {
+            price: price.parse()?,
+            timestamp: Utc::now().timestamp() as u64,
+            num_sources: 1,
+            ..Default::default()
+        })
+    }
+}
+
+/// Weighted price sources, aggregated into a single price.
+#[derive(Clone)]
+pub struct PriceSources {
+    sources: Vec<(Arc<dyn PriceSource>, BigDecimal)>,
+    /// Indexes of the sources without a price for a ticker on the last fetch, so
+    /// that a failure is only reported when it starts & ends.
+    failing: Arc<DashSet<(usize, String)>>,
+}
+
+impl PriceSources {
+    pub fn from_config(
+        config: &Config,
+        http_client: &reqwest::Client,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+        latest_prices: &LatestOraclePrices,
+    ) -> Result<Self> {
+        let sources = config
+            .oracle
+            .sources
+            .iter()
+            .map(|source| {
+                let price_source: Arc<dyn PriceSource> = match &source.kind {
+                    PriceSourceKind::PragmaMedian => Arc::new(
+                        PragmaOracle::new(rpc_client.clone(), config.pragma_oracle_address)
+                            .with_assets(config.asset_map.clone(), latest_prices.clone()),
+                    ),
+                    PriceSourceKind::PragmaTwap { window_secs } => Arc::new(PragmaTwap {
+                        rpc_client: rpc_client.clone(),
+                        summary_stats_address: config
+                            .pragma_summary_stats_address
+                            .with_context(|| {
+                                format!(
+                                    "The Pragma TWAP price source needs the Pragma summary stats address of {}",
+                                    config.network
+                                )
+                            })?,
+                        window: Duration::from_secs(*window_secs),
+                        asset_map: config.asset_map.clone(),
+                        latest_prices: latest_prices.clone(),
+                    }),
+                    PriceSourceKind::EkuboPool { quote_asset } => Arc::new(EkuboPoolPrice {
+                        pools: EkuboPools::new(rpc_client.clone(), config.ekubo_pools.clone()),
+                        config: config.clone(),
+                        quote_asset: quote_asset.clone(),
+                        latest_prices: latest_prices.clone(),
+                    }),
+                    PriceSourceKind::Http { url, price_pointer } => Arc::new(HttpPriceApi {
+                        http_client: http_client.clone(),
+                        url: url.clone(),
+                        price_pointer: price_pointer.clone(),
+                    }),
+                };
+                Ok((price_source, source.weight.clone()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            sources,
+            failing: Arc::new(DashSet::new()),
+        })
+    }
+
+    /// Fetches the price of an asset from every source & aggregates them. Sources
+    /// without a price for the asset are ignored.
+    pub async fn get_price(&self, ticker: &str) -> Result<OraclePrice> {
+        let tickers = [ticker.to_string()];
+        self.get_prices(&tickers, BlockId::Tag(BlockTag::PreConfirmed))
+            .await
+            .remove(0)
+    }
+
+    /// Fetches the prices of several assets from every source & aggregates them,
+    /// the on-chain sources reading all the prices on the provided block.
+    pub async fn get_prices(
+        &self,
+        tickers: &[String],
+        block_id: BlockId,
+    ) -> Vec<Result<OraclePrice>> {
+        let fetch_tasks = self.sources.iter().map(|(source, weight)| async move {
+            (
+                source.name(),
+                weight,
+                source.get_prices(tickers, block_id).await,
+            )
+        });
+        let sources_prices = join_all(fetch_tasks).await;
+
+        let mut prices: Vec<Vec<(BigDecimal, OraclePrice)>> = vec![vec![]; tickers.len()];
+        for (index, (name, weight, source_prices)) in sources_prices.into_iter().enumerate() {
+            for ((ticker, price), ticker_prices) in
+                tickers.iter().zip(source_prices).zip(prices.iter_mut())
+            {
+                let error = match price {
+                    Ok(price) if price.price > BigDecimal::from(0) => {
+                        ticker_prices.push((weight.clone(), price));
+                        if self.failing.remove(&(index, ticker.clone())).is_some() {
+                            tracing::info!("[🔮 Oracle] The {name} price for {ticker} is back");
+                        }
+                        continue;
+                    }
+                    Ok(_) => anyhow!("zero price"),
+                    Err(e) => e,
+                };
+                // The sources without a price for some assets, like the Ekubo pools
+                // for their quote asset, fail on every fetch.
+                if self.failing.insert((index, ticker.clone())) {
+                    tracing::warn!(
+                        "[🔮 Oracle] No {name} price for {ticker}, excluded from the aggregation until it is back: {error}"
+                    );
+                } else {
+                    tracing::debug!("[🔮 Oracle] Still no {name} price for {ticker}: {error}");
+                }
+            }
+        }
+
+        tickers
+            .iter()
+            .zip(prices)
+            .map(|(ticker, ticker_prices)| {
+                aggregate(&ticker_prices).with_context(|| format!("No price source for {ticker}"))
+            })
+            .collect()
+    }
+}
+
+/// Weighted mean of the prices, along with the largest relative deviation of a
+/// price to the mean.
+fn aggregate(prices: &[(BigDecimal, OraclePrice)]) -> Option<OraclePrice> {
+    let total_weight: BigDecimal = prices.iter().map(|(weight, _)| weight).sum();
+    if total_weight <= BigDecimal::from(0) {
+        return None;
+    }
+
+    let price: BigDecimal = prices
+        .iter()
+        .map(|(weight, oracle_price)| weight * &oracle_price.price)
+        .sum::<BigDecimal>()
+        / total_weight;
+    let deviation = prices
+        .iter()
+        .map(|(_, oracle_price)| ((&oracle_price.price - &price) / &price).abs())
+        .max()
+        .unwrap_or_default();
+
+    Some(OraclePrice {
+        timestamp: prices
+            .iter()
+            .map(|(_, p)| p.timestamp)
+            .min()
+            .unwrap_or_default(),
+        num_sources: prices.iter().map(|(_, p)| p.num_sources).sum(),
+        price,
+        deviation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+    };
+
+    use anyhow::{Result, bail};
+    use bigdecimal::BigDecimal;
+    use dashmap::DashSet;
+    use starknet::core::types::{BlockId, BlockTag};
+
+    use crate::services::oracle::OraclePrice;
+
+    use super::{PriceSource, PriceSources, aggregate};
+
+    /// Source pricing every asset at 1, except the quote asset & the assets while
+    /// it is down.
+    struct StubSource {
+        down: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl PriceSource for StubSource {
+        fn name(&self) -> &'static str {
+            "stub"
+        }
+
+        async fn get_price(&self, ticker: &str) -> Result<OraclePrice> {
+            if ticker == "usdc" || self.down.load(Ordering::SeqCst) {
+                bail!("no price");
+            }
+            Ok(price(1, 10, 1))
+        }
+    }
+
+    fn price(price: u32, timestamp: u64, num_sources: u32) -> OraclePrice {
+        OraclePrice {
+            price: BigDecimal::from(price),
+            timestamp,
+            num_sources,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_aggregate_prices() {
+        let prices = vec![
+            (BigDecimal::from(3), price(100, 20, 5)),
+            (BigDecimal::from(1), price(104, 10, 1)),
+        ];
+        let aggregated = aggregate(&prices).unwrap();
+        assert_eq!(aggregated.price, BigDecimal::from(101));
+        assert_eq!(
+            aggregated.deviation,
+            BigDecimal::from(3) / BigDecimal::from(101)
+        );
+        assert_eq!(aggregated.timestamp, 10);
+        assert_eq!(aggregated.num_sources, 6);
+    }
+
+    #[test]
+    fn test_aggregate_single_price() {
+        let prices = vec![(BigDecimal::from_str("0.5").unwrap(), price(2000, 10, 3))];
+        let aggregated = aggregate(&prices).unwrap();
+        assert_eq!(aggregated.price, BigDecimal::from(2000));
+        assert_eq!(aggregated.deviation, BigDecimal::from(0));
+        assert!(aggregate(&[]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failing_sources_tracking() {
+        let source = Arc::new(StubSource {
+            down: AtomicBool::new(false),
+        });
+        let sources = PriceSources {
+            sources: vec![(source.clone(), BigDecimal::from(1))],
+            failing: Arc::new(DashSet::new()),
+        };
+        let tickers = ["eth".to_string(), "usdc".to_string()];
+        let block_id = BlockId::Tag(BlockTag::Latest);
+
+        let prices = sources.get_prices(&tickers, block_id).await;
+        assert!(prices[0].is_ok() && prices[1].is_err());
+        assert!(sources.failing.contains(&(0, "usdc".to_string())));
+        assert_eq!(sources.failing.len(), 1);
+
+        source.down.store(true, Ordering::SeqCst);
+        sources.get_prices(&tickers, block_id).await;
+        assert_eq!(sources.failing.len(), 2);
+
+        source.down.store(false, Ordering::SeqCst);
+        sources.get_prices(&tickers, block_id).await;
+        assert!(!sources.failing.contains(&(0, "eth".to_string())));
+        assert_eq!(sources.failing.len(), 1);
+    }
+}
@@ -0,0 +1,570 @@
+use std::{sync::Arc, time::Instant};
+
+use anyhow::{Result, bail};
+use bigdecimal::{BigDecimal, num_bigint::BigInt};
+use dashmap::{DashMap, DashSet};
+use starknet::{
+    core::types::Felt,
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+
+use crate::{
+    bindings::liquidate::Swap,
+    config::{Config, RouteCacheRules, RouteProviderKind},
+    services::oracle::LatestOraclePrices,
+    types::{asset::Asset, position::Position},
+    utils::ekubo::{EkuboPools, EkuboQuoter},
+};
+
+/// Swaps & their weights, as expected by the Liquidate contract.
+pub type Route = (Vec<Swap>, Vec<u128>);
+
+/// Source of the swap routes used by the liquidations.
+#[async_trait::async_trait]
+pub trait RouteProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Returns the route swapping `to_token` into exactly `amount` of `from_token`.
+    async fn get_route(
+        &self,
+        from_token: Felt,
+        to_token: Felt,
+        amount: &BigDecimal,
+    ) -> Result<Route>;
+
+    /// Returns the route swapping exactly `amount` of `from_token` into `to_token`.
+    async fn get_exact_input_route(
+        &self,
+        from_token: Felt,
+        to_token: Felt,
+        amount: &BigDecimal,
+    ) -> Result<Route>;
+}
+
+/// Returns the pool fees paid by a swap through the route, relative to its output,
+/// weighted across the splits. The fee of an Ekubo pool is a 0.128 fixed point number.
+pub fn route_fee(route: &Route) -> BigDecimal {
+    let (swaps, weights) = route;
+    let total_weight: u128 = weights.iter().sum();
+    if total_weight == 0 {
+        return BigDecimal::from(0);
+    }
+    let fee_scale = BigDecimal::from(BigInt::from(1) << 128);
+    let weighted_fees: BigDecimal = swaps
+        .iter()
+        .zip(weights)
+        .map(|(swap, weight)| {
+            let kept = swap.route.iter().fold(BigDecimal::from(1), |kept, node| {
+                kept * (BigDecimal::from(1) - BigDecimal::from(node.pool_key.fee) / &fee_scale)
+            });
+            if kept <= BigDecimal::from(0) {
+                return BigDecimal::from(0);
+            }
+            (BigDecimal::from(1) / kept - BigDecimal::from(1)) * BigDecimal::from(*weight)
+        })
+        .sum();
+    weighted_fees / BigDecimal::from(total_weight)
+}
+
+/// Conversion of the collateral left after a liquidation into another asset.
+#[derive(Debug, Clone)]
+pub struct WithdrawPlan {
+    pub collateral: Felt,
+    pub target: Asset,
+    /// Collateral expected to be left after the liquidation.
+    pub residual: BigDecimal,
+    /// Collateral price, denominated in the target asset.
+    pub price: BigDecimal,
+    /// Minimum amount of the target asset to receive.
+    pub min_amount_out: u128,
+}
+
+impl WithdrawPlan {
+    fn route_key(&self) -> RouteKey {
+        RouteKey::new(self.collateral, self.target.address, &self.residual)
+    }
+}
+
+/// Swap of the collateral withdrawn by a liquidation into another asset.
+#[derive(Debug, Clone)]
+pub struct WithdrawSwap {
+    pub route: Route,
+    /// Minimum amount of the target asset to receive.
+    pub min_amount_out: u128,
+}
+
+/// Route providers of the config, tried in order until one of them returns a route.
+#[derive(Clone)]
+pub struct RouteProviders(Vec<Arc<dyn RouteProvider>>);
+
+impl RouteProviders {
+    pub fn from_config(
+        config: &Config,
+        http_client: &reqwest::Client,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    ) -> Self {
+        let providers = config
+            .route_providers
+            .iter()
+            .map(|kind| -> Arc<dyn RouteProvider> {
+                match kind {
+                    RouteProviderKind::EkuboQuoter => Arc::new(EkuboQuoter::new(
+                        http_client.clone(),
+                        config.ekubo_quoter.clone(),
+                    )),
+                    RouteProviderKind::EkuboPools => Arc::new(EkuboPools::new(
+                        rpc_client.clone(),
+                        config.ekubo_pools.clone(),
+                    )),
+                }
+            })
+            .collect();
+        Self(providers)
+    }
+}
+
+#[async_trait::async_trait]
+impl RouteProvider for RouteProviders {
+    fn name(&self) -> &'static str {
+        "route providers"
+    }
+
+    async fn get_route(
+        &self,
+        from_token: Felt,
+        to_token: Felt,
+        amount: &BigDecimal,
+    ) -> Result<Route> {
+        for provider in self.0.iter() {
+            match provider.get_route(from_token, to_token, amount).await {
+                Ok(route) => return Ok(route),
+                Err(e) => tracing::warn!("No route from the {}: {e}", provider.name()),
+            }
+        }
+        bail!("No route found from {to_token:#x} to {from_token:#x}")
+    }
+
+    async fn get_exact_input_route(
+        &self,
+        from_token: Felt,
+        to_token: Felt,
+        amount: &BigDecimal,
+    ) -> Result<Route> {
+        for provider in self.0.iter() {
+            match provider
+                .get_exact_input_route(from_token, to_token, amount)
+                .await
+            {
+                Ok(route) => return Ok(route),
+                Err(e) => tracing::warn!("No route from the {}: {e}", provider.name()),
+            }
+        }
+        bail!("No route found from {from_token:#x} to {to_token:#x}")
+    }
+}
+
+/// Scale the amounts are normalised to before being bucketed, the largest number of
+/// decimals of the tokens.
+const ROUTE_KEY_AMOUNT_SCALE: i64 = 18;
+
+/// Key of a cached route. Amounts are bucketed by power of two, so positions of
+/// the same pair & of a similar size share the same route.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct RouteKey {
+    pub from_token: Felt,
+    pub to_token: Felt,
+    pub size_bucket: u64,
+}
+
+impl RouteKey {
+    pub fn new(from_token: Felt, to_token: Felt, amount: &BigDecimal) -> Self {
+        // The bucket must not depend on how many decimals the amount is written with.
+        let (scaled_amount, _) = amount
+            .with_scale(ROUTE_KEY_AMOUNT_SCALE)
+            .as_bigint_and_exponent();
+        Self {
+            from_token,
+            to_token,
+            size_bucket: scaled_amount.bits(),
+        }
+    }
+
+    /// Key of the route swapping the collateral of a position into its debt.
+    pub fn for_position(position: &Position) -> Self {
+        Self::new(
+            position.debt.address,
+            position.collateral.address,
+            &position.debt.amount,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedRoute {
+    route: Route,
+    fetched_at: Instant,
+    /// Collateral/debt price when the route was fetched.
+    price: BigDecimal,
+}
+
+impl CachedRoute {
+    fn is_fresh(&self, price: &BigDecimal, rules: &RouteCacheRules) -> bool {
+        if self.fetched_at.elapsed() >= rules.ttl {
+            return false;
+        }
+        if self.price == BigDecimal::from(0) {
+            return false;
+        }
+        let price_change = ((price - &self.price) / &self.price).abs();
+        price_change <= rules.refresh_price_change
+    }
+}
+
+/// Thread-safe cache of the liquidation & withdraw routes, pre-computed for the
+/// almost liquidable positions so that liquidating only needs a lookup.
+#[derive(Clone, Default)]
+pub struct RouteCache {
+    routes: Arc<DashMap<RouteKey, CachedRoute>>,
+    withdraw_routes: Arc<DashMap<RouteKey, CachedRoute>>,
+    in_flight: Arc<DashSet<RouteKey>>,
+}
+
+impl RouteCache {
+    /// Returns the cached route of a position, if it is still fresh.
+    pub fn get(
+        &self,
+        position: &Position,
+        oracle_prices: &LatestOraclePrices,
+        rules: &RouteCacheRules,
+    ) -> Option<Route> {
+        let price = position.collateral_price_in_debt(oracle_prices).ok()?;
+        self.routes
+            .get(&RouteKey::for_position(position))
+            .filter(|cached| cached.is_fresh(&price, rules))
+            .map(|cached| cached.route.clone())
+    }
+
+    /// Returns the route of a position, from the cache or from the providers on a miss.
+    pub async fn get_or_fetch(
+        &self,
+        route_provider: &RouteProviders,
+        position: &Position,
+        oracle_prices: &LatestOraclePrices,
+        rules: &RouteCacheRules,
+    ) -> Result<Route> {
+        if let Some(route) = self.get(position, oracle_prices, rules) {
+            tracing::info!("[🔭 Monitoring] Using cached route for #{}", position.key());
+            return Ok(route);
+        }
+        tracing::info!(
+            "[🔭 Monitoring] No cached route for #{}, fetching it",
+            position.key()
+        );
+        self.fetch(route_provider, position, oracle_prices).await
+    }
+
+    /// Refreshes the route of a position in the background if it is missing,
+    /// expired or if the price moved too much since it was fetched.
+    pub fn prefetch(
+        &self,
+        route_provider: &RouteProviders,
+        position: &Position,
+        oracle_prices: &LatestOraclePrices,
+        rules: &RouteCacheRules,
+    ) {
+        let key = RouteKey::for_position(position);
+        if self.get(position, oracle_prices, rules).is_some() || !self.in_flight.insert(key) {
+            return;
+        }
+
+        let cache = self.clone();
+        let route_provider = route_provider.clone();
+        let position = position.clone();
+        let oracle_prices = oracle_prices.clone();
+        tokio::spawn(async move {
+            if let Err(e) = cache
+                .fetch(&route_provider, &position, &oracle_prices)
+                .await
+            {
+                tracing::warn!(
+                    "[🔭 Monitoring] Could not pre-compute route for #{}: {e}",
+                    position.key()
+                );
+            }
+            cache.in_flight.remove(&key);
+        });
+    }
+
+    /// Returns the withdraw swap of a plan, if its route has been pre-computed & is
+    /// still fresh. It's never fetched when liquidating, to not wait on the providers.
+    pub fn get_withdraw_swap(
+        &self,
+        plan: &WithdrawPlan,
+        rules: &RouteCacheRules,
+    ) -> Option<WithdrawSwap> {
+        self.withdraw_routes
+            .get(&plan.route_key())
+            .filter(|cached| cached.is_fresh(&plan.price, rules))
+            .map(|cached| WithdrawSwap {
+                route: cached.route.clone(),
+                min_amount_out: plan.min_amount_out,
+            })
+    }
+
+    /// Refreshes the withdraw route of a plan in the background if it is missing,
+    /// expired or if the price moved too much since it was fetched.
+    pub fn prefetch_withdraw(
+        &self,
+        route_provider: &RouteProviders,
+        plan: &WithdrawPlan,
+        rules: &RouteCacheRules,
+    ) {
+        let key = plan.route_key();
+        if self.get_withdraw_swap(plan, rules).is_some() || !self.in_flight.insert(key) {
+            return;
+        }
+
+        let cache = self.clone();
+        let route_provider = route_provider.clone();
+        let plan = plan.clone();
+        tokio::spawn(async move {
+            match route_provider
+                .get_exact_input_route(plan.collateral, plan.target.address, &plan.residual)
+                .await
+            {
+                Ok(route) => {
+                    cache.withdraw_routes.insert(
+                        key,
+                        CachedRoute {
+                            route,
+                            fetched_at: Instant::now(),
+                            price: plan.price,
+                        },
+                    );
+                }
+                Err(e) => tracing::warn!(
+                    "[🔭 Monitoring] Could not pre-compute the withdraw route of {:#x}: {e}",
+                    plan.collateral
+                ),
+            }
+            cache.in_flight.remove(&key);
+        });
+    }
+
+    /// Removes the expired routes.
+    pub fn prune(&self, rules: &RouteCacheRules) {
+        self.routes
+            .retain(|_, cached| cached.fetched_at.elapsed() < rules.ttl);
+        self.withdraw_routes
+            .retain(|_, cached| cached.fetched_at.elapsed() < rules.ttl);
+    }
+
+    async fn fetch(
+        &self,
+        route_provider: &RouteProviders,
+        position: &Position,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Result<Route> {
+        let price = position.collateral_price_in_debt(oracle_prices)?;
+        let route = position.get_liquidation_route(route_provider).await?;
+        self.routes.insert(
+            RouteKey::for_position(position),
+            CachedRoute {
+                route: route.clone(),
+                fetched_at: Instant::now(),
+                price,
+            },
+        );
+        Ok(route)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use bigdecimal::BigDecimal;
+    use cainome::cairo_serde::ContractAddress;
+    use starknet::core::types::Felt;
+
+    use crate::{
+        bindings::liquidate::{PoolKey, RouteNode, Swap, TokenAmount},
+        config::{EkuboPoolsRules, EkuboQuoterRules, RouteCacheRules},
+        utils::{
+            constants::I129_ZERO,
+            ekubo::{EkuboPools, EkuboQuoter},
+            test_utils::{serve_ekubo_core, serve_http},
+        },
+    };
+
+    use super::{CachedRoute, RouteKey, RouteProvider, RouteProviders, route_fee};
+
+    const SINGLE_SPLIT_QUOTE: &str = include_str!("../../tests/fixtures/ekubo/single_split.json");
+
+    /// Route providers trying the Ekubo quoter stub, answering with a 503 if it is
+    /// down, then the pool between the tokens 0x1 & 0x2 on the Ekubo core stub.
+    /// Returns the number of requests served by the quoter & the core.
+    async fn route_providers(
+        quoter_down: bool,
+    ) -> (RouteProviders, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let quotes = Arc::new(AtomicUsize::new(0));
+        let served_quotes = quotes.clone();
+        let url = serve_http(move |_| {
+            served_quotes.fetch_add(1, Ordering::SeqCst);
+            if quoter_down {
+                ("503 Service Unavailable", String::new())
+            } else {
+                ("200 OK", SINGLE_SPLIT_QUOTE.to_string())
+            }
+        })
+        .await;
+        let (rpc_client, core_calls) = serve_ekubo_core(1000).await;
+
+        let quoter = EkuboQuoter::new(
+            reqwest::Client::new(),
+            EkuboQuoterRules {
+                url: url.to_string(),
+                timeout: Duration::from_millis(500),
+                retries: 0,
+            },
+        );
+        let pools = EkuboPools::new(
+            rpc_client,
+            EkuboPoolsRules {
+                core_address: Some(Felt::from(0xc0)),
+                pools: vec![PoolKey {
+                    token0: ContractAddress(Felt::ONE),
+                    token1: ContractAddress(Felt::TWO),
+                    fee: 0,
+                    tick_spacing: 1,
+                    extension: ContractAddress(Felt::ZERO),
+                }],
+                max_reserve_share: BigDecimal::from_str("0.02").unwrap(),
+            },
+        );
+        let providers = RouteProviders(vec![Arc::new(quoter), Arc::new(pools)]);
+        (providers, quotes, core_calls)
+    }
+
+    #[tokio::test]
+    async fn test_route_providers_order() {
+        let (providers, quotes, core_calls) = route_providers(false).await;
+        assert!(
+            providers
+                .get_route(Felt::ONE, Felt::TWO, &BigDecimal::from(10))
+                .await
+                .is_ok()
+        );
+        assert_eq!(quotes.load(Ordering::SeqCst), 1);
+        // The pools are not tried once the quoter returned a route.
+        assert_eq!(core_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_route_providers_fallback() {
+        let (providers, quotes, core_calls) = route_providers(true).await;
+        let (swaps, _) = providers
+            .get_route(Felt::ONE, Felt::TWO, &BigDecimal::from(10))
+            .await
+            .unwrap();
+        assert_eq!(quotes.load(Ordering::SeqCst), 1);
+        assert!(core_calls.load(Ordering::SeqCst) > 0);
+        assert_eq!(swaps[0].route[0].pool_key.token1.0, Felt::TWO);
+
+        // Too large for the pool.
+        assert!(
+            providers
+                .get_route(Felt::ONE, Felt::TWO, &BigDecimal::from(100))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_route_freshness() {
+        let rules = RouteCacheRules {
+            ttl: Duration::from_secs(30),
+            refresh_price_change: BigDecimal::from_str("0.005").unwrap(),
+        };
+        let cached = CachedRoute {
+            route: (vec![], vec![]),
+            fetched_at: std::time::Instant::now(),
+            price: BigDecimal::from(2000),
+        };
+        assert!(cached.is_fresh(&BigDecimal::from(2005), &rules));
+        assert!(cached.is_fresh(&BigDecimal::from(1995), &rules));
+        assert!(!cached.is_fresh(&BigDecimal::from(2020), &rules));
+
+        let expired_rules = RouteCacheRules {
+            ttl: Duration::ZERO,
+            ..rules
+        };
+        assert!(!cached.is_fresh(&BigDecimal::from(2000), &expired_rules));
+    }
+
+    #[test]
+    fn test_route_key_size_buckets() {
+        let key = |amount: &str| {
+            RouteKey::new(Felt::ONE, Felt::TWO, &BigDecimal::from_str(amount).unwrap())
+        };
+        assert_eq!(key("1.5"), key("1.2"));
+        assert_ne!(key("1.5"), key("3.5"));
+        // Same amounts & buckets written with different scales.
+        assert_eq!(key("1.5"), key("1.500000"));
+        assert_eq!(key("1.2"), key("1.50"));
+        assert_eq!(key("3"), key("3.5000"));
+        assert_eq!(key("0.000001"), key("0.00000100"));
+        assert_ne!(key("0.000001"), key("0.000003"));
+    }
+
+    #[test]
+    fn test_route_fee() {
+        let swap = |fees: &[u128]| Swap {
+            route: fees
+                .iter()
+                .map(|fee| RouteNode {
+                    pool_key: PoolKey {
+                        token0: ContractAddress(Felt::ONE),
+                        token1: ContractAddress(Felt::TWO),
+                        fee: *fee,
+                        tick_spacing: 1,
+                        extension: ContractAddress(Felt::ZERO),
+                    },
+                    sqrt_ratio_limit: cainome::cairo_serde::U256 { low: 0, high: 0 },
+                    skip_ahead: 0,
+                })
+                .collect(),
+            token_amount: TokenAmount {
+                token: ContractAddress(Felt::ONE),
+                amount: I129_ZERO,
+            },
+        };
+        // 0.5% fee, 0.128 fixed point.
+        let half_percent = u128::MAX / 200;
+        let approx = |fee: BigDecimal, expected: &str| {
+            let delta = fee - BigDecimal::from_str(expected).unwrap();
+            assert!(delta.abs() < BigDecimal::from_str("0.000001").unwrap());
+        };
+
+        assert_eq!(route_fee(&(vec![], vec![])), BigDecimal::from(0));
+        approx(
+            route_fee(&(vec![swap(&[half_percent])], vec![1])),
+            "0.005025",
+        );
+        approx(
+            route_fee(&(vec![swap(&[half_percent, half_percent])], vec![1])),
+            "0.010076",
+        );
+        approx(
+            route_fee(&(vec![swap(&[half_percent]), swap(&[0])], vec![1, 3])),
+            "0.001256",
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::{fmt, time::Duration};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::{crypto::Signature, types::Felt},
+    signers::{LocalWallet, Signer, SignerInteractivityContext, VerifyingKey},
+};
+use url::Url;
+
+/// Timeout of the requests to the signing service.
+const REMOTE_SIGNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Error returned by the signers of the liquidator accounts.
+#[derive(Debug)]
+pub enum SignerError {
+    /// The local key could not sign the hash.
+    Local(String),
+    /// The signing service could not be reached or rejected the request.
+    Request(reqwest::Error),
+    /// The signing service returned an unexpected response.
+    InvalidResponse(String),
+}
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerError::Local(e) => write!(f, "local signer error: {e}"),
+            SignerError::Request(e) => write!(f, "remote signer request failed: {e}"),
+            SignerError::InvalidResponse(e) => write!(f, "invalid remote signer response: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+impl From<reqwest::Error> for SignerError {
+    fn from(e: reqwest::Error) -> Self {
+        SignerError::Request(e)
+    }
+}
+
+/// Signer delegating the signature of the transaction hashes of an account to an
+/// HTTP signing service, so that its private key never lives on the liquidator host.
+///
+/// The service exposes, for each account it holds the key of:
+/// - `GET {url}/accounts/{address}/public_key`, returning `{"public_key": "0x..."}`,
+/// - `POST {url}/accounts/{address}/sign` with `{"hash": "0x..."}`, returning
+///   `{"r": "0x...", "s": "0x..."}`.
+///
+/// The requests are authenticated with the `Authorization: Bearer {auth_token}` header.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    http_client: reqwest::Client,
+    url: Url,
+    auth_token: Option<String>,
+    account_address: Felt,
+}
+
+#[derive(Serialize)]
+struct SignRequest {
+    hash: Felt,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    r: Felt,
+    s: Felt,
+}
+
+#[derive(Deserialize)]
+struct PublicKeyResponse {
+    public_key: Felt,
+}
+
+impl RemoteSigner {
+    pub fn new(url: Url, auth_token: Option<String>, account_address: Felt) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            url,
+            auth_token,
+            account_address,
+        }
+    }
+
+    /// Returns the url of an endpoint of the account on the signing service.
+    fn endpoint(&self, path: &str) -> String {
+        format!(
+            "{}/accounts/{:#x}/{path}",
+            self.url.as_str().trim_end_matches('/'),
+            self.account_address
+        )
+    }
+
+    fn authenticated(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let request = request.timeout(REMOTE_SIGNER_TIMEOUT);
+        match &self.auth_token {
+            Some(auth_token) => request.bearer_auth(auth_token),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    type GetPublicKeyError = SignerError;
+    type SignError = SignerError;
+
+    async fn get_public_key(&self) -> Result<VerifyingKey, Self::GetPublicKeyError> {
+        let response: PublicKeyResponse = self
+            .authenticated(self.http_client.get(self.endpoint("public_key")))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .map_err(|e| SignerError::InvalidResponse(e.to_string()))?;
+        Ok(VerifyingKey::from_scalar(response.public_key))
+    }
+
+    async fn sign_hash(&self, hash: &Felt) -> Result<Signature, Self::SignError> {
+        let response: SignResponse = self
+            .authenticated(self.http_client.post(self.endpoint("sign")))
+            .json(&SignRequest { hash: *hash })
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .map_err(|e| SignerError::InvalidResponse(e.to_string()))?;
+        Ok(Signature {
+            r: response.r,
+            s: response.s,
+        })
+    }
+
+    /// Fee estimations are sent unsigned, so that only the executed transactions
+    /// go through the signing service.
+    fn is_interactive(&self, _context: SignerInteractivityContext<'_>) -> bool {
+        true
+    }
+}
+
+/// Signer of a liquidator account: either a local key or a remote signing service.
+#[derive(Debug, Clone)]
+pub enum LiquidatorSigner {
+    Local(LocalWallet),
+    Remote(RemoteSigner),
+}
+
+#[async_trait]
+impl Signer for LiquidatorSigner {
+    type GetPublicKeyError = SignerError;
+    type SignError = SignerError;
+
+    async fn get_public_key(&self) -> Result<VerifyingKey, Self::GetPublicKeyError> {
+        match self {
+            LiquidatorSigner::Local(wallet) => wallet
+                .get_public_key()
+                .await
+                .map_err(|e| SignerError::Local(e.to_string())),
+            LiquidatorSigner::Remote(signer) => signer.get_public_key().await,
+        }
+    }
+
+    async fn sign_hash(&self, hash: &Felt) -> Result<Signature, Self::SignError> {
+        match self {
+            LiquidatorSigner::Local(wallet) => wallet
+                .sign_hash(hash)
+                .await
+                .map_err(|e| SignerError::Local(e.to_string())),
+            LiquidatorSigner::Remote(signer) => signer.sign_hash(hash).await,
+        }
+    }
+
+    fn is_interactive(&self, context: SignerInteractivityContext<'_>) -> bool {
+        match self {
+            LiquidatorSigner::Local(wallet) => wallet.is_interactive(context),
+            LiquidatorSigner::Remote(signer) => signer.is_interactive(context),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use starknet::{
+        core::{crypto::ecdsa_verify, types::Felt},
+        signers::{Signer, SigningKey},
+    };
+    use url::Url;
+
+    use crate::utils::test_utils::{StubRequest, serve_http};
+
+    use super::RemoteSigner;
+
+    const AUTH_TOKEN: &str = "secret-token";
+
+    /// Starts a local signing service holding `signing_key`.
+    async fn mock_signer_server(signing_key: SigningKey) -> Url {
+        serve_http(move |request| handle_request(request, &signing_key)).await
+    }
+
+    fn handle_request(request: StubRequest, signing_key: &SigningKey) -> (&'static str, String) {
+        let request_line = request.request_line();
+        let (status, response) =
+            if !request.has_header("authorization", &format!("Bearer {AUTH_TOKEN}")) {
+                ("401 Unauthorized", json!({ "error": "unauthorized" }))
+            } else if request_line.starts_with("GET ") && request_line.contains("/public_key ") {
+                let public_key = signing_key.verifying_key().scalar();
+                ("200 OK", json!({ "public_key": public_key }))
+            } else if request_line.starts_with("POST ") && request_line.contains("/sign ") {
+                let body: Value = serde_json::from_str(&request.body).unwrap();
+                let hash: Felt = serde_json::from_value(body["hash"].clone()).unwrap();
+                let signature = signing_key.sign(&hash).unwrap();
+                ("200 OK", json!({ "r": signature.r, "s": signature.s }))
+            } else {
+                ("404 Not Found", json!({ "error": "not found" }))
+            };
+        (status, response.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer() {
+        let signing_key = SigningKey::from_secret_scalar(Felt::from(0x1234_u64));
+        let public_key = signing_key.verifying_key().scalar();
+        let url = mock_signer_server(signing_key).await;
+        let signer = RemoteSigner::new(url, Some(AUTH_TOKEN.to_string()), Felt::ONE);
+
+        assert_eq!(signer.get_public_key().await.unwrap().scalar(), public_key);
+
+        let hash = Felt::from(0xabcdef_u64);
+        let signature = signer.sign_hash(&hash).await.unwrap();
+        assert!(ecdsa_verify(&public_key, &hash, &signature).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_authentication() {
+        let url = mock_signer_server(SigningKey::from_secret_scalar(Felt::ONE)).await;
+
+        let unauthenticated = RemoteSigner::new(url.clone(), None, Felt::ONE);
+        assert!(unauthenticated.sign_hash(&Felt::TWO).await.is_err());
+
+        let wrong_token = RemoteSigner::new(url, Some("wrong".to_string()), Felt::ONE);
+        assert!(wrong_token.sign_hash(&Felt::TWO).await.is_err());
+    }
+}
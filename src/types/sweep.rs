@@ -0,0 +1,48 @@
+use std::fmt;
+
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+/// Final outcome of a sweep.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum SweepOutcome {
+    /// The transfers landed & succeeded.
+    Succeeded,
+    /// The transaction landed but has been reverted.
+    Reverted(String),
+    /// The transaction could not be sent, or was dropped.
+    Failed(String),
+}
+
+impl fmt::Display for SweepOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SweepOutcome::Succeeded => write!(f, "succeeded"),
+            SweepOutcome::Reverted(reason) => write!(f, "reverted ({reason})"),
+            SweepOutcome::Failed(reason) => write!(f, "failed ({reason})"),
+        }
+    }
+}
+
+/// Token transferred to the treasury by a sweep.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweptToken {
+    pub ticker: String,
+    pub address: Felt,
+    pub amount: BigDecimal,
+}
+
+/// Record of a sweep of the excess balances of a liquidator account to the treasury.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepRecord {
+    pub account: Felt,
+    pub treasury: Felt,
+    pub tx_hash: Option<Felt>,
+    pub block_number: Option<u64>,
+    /// Unix timestamp (in seconds) of the sweep.
+    pub timestamp: u64,
+    pub outcome: SweepOutcome,
+    pub tokens: Vec<SweptToken>,
+}
@@ -0,0 +1,269 @@
+use std::{fmt, sync::Arc, time::Instant};
+
+use anyhow::{Result, bail};
+use starknet::{
+    core::types::{
+        ExecutionResult, Felt, StarknetError, TransactionReceiptWithBlockInfo, TransactionStatus,
+    },
+    providers::{JsonRpcClient, Provider, ProviderError, jsonrpc::HttpTransport},
+};
+
+use crate::config::TransactionRules;
+
+/// Stage of a submitted transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxStatus {
+    /// Sent, not yet known by the node.
+    Submitted,
+    /// In the mempool of the node.
+    Received,
+    /// Executed in the pre-confirmed block.
+    PreConfirmed,
+    /// Included in an L2 block.
+    AcceptedOnL2,
+    /// Executed & reverted, in the pre-confirmed block or an L2 block.
+    Reverted(String),
+    /// Forgotten by the node before being included, it can be sent again.
+    Dropped,
+}
+
+impl TxStatus {
+    /// Returns the status of a transaction known by the node.
+    fn from_node(status: &TransactionStatus) -> Self {
+        let (status, execution_result) = match status {
+            TransactionStatus::Received | TransactionStatus::Candidate => {
+                return TxStatus::Received;
+            }
+            TransactionStatus::PreConfirmed(execution_result) => {
+                (TxStatus::PreConfirmed, execution_result)
+            }
+            TransactionStatus::AcceptedOnL2(execution_result)
+            | TransactionStatus::AcceptedOnL1(execution_result) => {
+                (TxStatus::AcceptedOnL2, execution_result)
+            }
+        };
+        match execution_result {
+            ExecutionResult::Succeeded => status,
+            ExecutionResult::Reverted { reason } => TxStatus::Reverted(reason.clone()),
+        }
+    }
+}
+
+impl fmt::Display for TxStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxStatus::Submitted => write!(f, "submitted"),
+            TxStatus::Received => write!(f, "received"),
+            TxStatus::PreConfirmed => write!(f, "pre-confirmed"),
+            TxStatus::AcceptedOnL2 => write!(f, "accepted on L2"),
+            TxStatus::Reverted(reason) => write!(f, "reverted: {reason}"),
+            TxStatus::Dropped => write!(f, "dropped"),
+        }
+    }
+}
+
+/// Final status of a submitted transaction.
+#[derive(Debug, Clone)]
+pub enum TxFinalStatus {
+    /// Included in an L2 block, succeeded or reverted.
+    Included(Box<TransactionReceiptWithBlockInfo>),
+    /// Forgotten by the node before being included.
+    Dropped,
+}
+
+/// Follows the submitted transactions until they are included in an L2 block or
+/// dropped by the node.
+#[derive(Clone)]
+pub struct TxTracker {
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+}
+
+impl TxTracker {
+    pub fn new(rpc_client: Arc<JsonRpcClient<HttpTransport>>) -> Self {
+        Self { rpc_client }
+    }
+
+    /// Polls the status of a transaction until it's final, and returns its receipt if
+    /// it was included. A transaction unknown by the node for longer than the drop
+    /// timeout, since its submission or since the node last knew about it, is dropped.
+    /// A transaction known by the node is followed until it's included, as it could
+    /// still be included as long as it's in the mempool. Fails if its status can't be
+    /// fetched for as long as the drop timeout.
+    pub async fn follow(&self, tx_hash: Felt, rules: &TransactionRules) -> Result<TxFinalStatus> {
+        self.follow_with(tx_hash, rules, || {}).await
+    }
+
+    /// Follows a transaction like [`TxTracker::follow`], calling `on_received` once the
+    /// node knows about it, its nonce being then taken.
+    pub async fn follow_with(
+        &self,
+        tx_hash: Felt,
+        rules: &TransactionRules,
+        on_received: impl FnOnce() + Send,
+    ) -> Result<TxFinalStatus> {
+        let mut on_received = Some(on_received);
+        let mut status = TxStatus::Submitted;
+        let mut last_known = Instant::now();
+        let mut last_answer = Instant::now();
+
+        loop {
+            match self.rpc_client.get_transaction_status(tx_hash).await {
+                Ok(node_status) => {
+                    last_answer = Instant::now();
+                    last_known = last_answer;
+                    if let Some(on_received) = on_received.take() {
+                        on_received();
+                    }
+                    let new_status = TxStatus::from_node(&node_status);
+                    if new_status != status {
+                        tracing::debug!("Transaction {tx_hash:#064x} {new_status}");
+                        status = new_status;
+                    }
+                    if is_included(&node_status) {
+                        let receipt = self.rpc_client.get_transaction_receipt(tx_hash).await?;
+                        return Ok(TxFinalStatus::Included(Box::new(receipt)));
+                    }
+                }
+                Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {
+                    last_answer = Instant::now();
+                    if last_known.elapsed() >= rules.drop_timeout {
+                        tracing::warn!(
+                            "Transaction {tx_hash:#064x} has been dropped, last {status} {:?} ago",
+                            last_known.elapsed()
+                        );
+                        return Ok(TxFinalStatus::Dropped);
+                    }
+                }
+                Err(e) => {
+                    if last_answer.elapsed() >= rules.drop_timeout {
+                        bail!("Could not follow transaction {tx_hash:#064x}, last {status}: {e:?}");
+                    }
+                    tracing::debug!("Could not fetch the status of {tx_hash:#064x}: {e:?}");
+                }
+            }
+            tokio::time::sleep(rules.poll_interval).await;
+        }
+    }
+}
+
+/// Returns true if the transaction has been included in an L2 block.
+fn is_included(status: &TransactionStatus) -> bool {
+    matches!(
+        status,
+        TransactionStatus::AcceptedOnL2(_) | TransactionStatus::AcceptedOnL1(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{Duration, Instant},
+    };
+
+    use serde_json::json;
+    use starknet::core::types::{ExecutionResult, Felt, TransactionStatus};
+
+    use crate::{config::TransactionRules, utils::test_utils::serve_json_rpc};
+
+    use super::{TxFinalStatus, TxStatus, TxTracker, is_included};
+
+    fn rules() -> TransactionRules {
+        TransactionRules {
+            poll_interval: Duration::from_millis(10),
+            drop_timeout: Duration::from_millis(200),
+        }
+    }
+
+    #[test]
+    fn test_tx_status() {
+        let reverted = ExecutionResult::Reverted {
+            reason: "not-undercollateralized".to_string(),
+        };
+        assert_eq!(
+            TxStatus::from_node(&TransactionStatus::Received),
+            TxStatus::Received
+        );
+        assert_eq!(
+            TxStatus::from_node(&TransactionStatus::PreConfirmed(ExecutionResult::Succeeded)),
+            TxStatus::PreConfirmed
+        );
+        assert_eq!(
+            TxStatus::from_node(&TransactionStatus::PreConfirmed(reverted.clone())),
+            TxStatus::Reverted("not-undercollateralized".to_string())
+        );
+        assert_eq!(
+            TxStatus::from_node(&TransactionStatus::AcceptedOnL1(ExecutionResult::Succeeded)),
+            TxStatus::AcceptedOnL2
+        );
+
+        assert!(!is_included(&TransactionStatus::PreConfirmed(
+            ExecutionResult::Succeeded
+        )));
+        assert!(is_included(&TransactionStatus::AcceptedOnL2(reverted)));
+    }
+
+    #[tokio::test]
+    async fn test_follow_keeps_transactions_in_the_mempool() {
+        let rpc_client = serve_json_rpc(|method, _| {
+            assert_eq!(method, "starknet_getTransactionStatus");
+            Ok(json!({ "finality_status": "RECEIVED" }))
+        })
+        .await;
+        let tracker = TxTracker::new(rpc_client);
+        let rules = rules();
+        let follow = tracker.follow(Felt::ONE, &rules);
+        assert!(
+            tokio::time::timeout(rules.drop_timeout * 3, follow)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_follow_drops_transactions_evicted_from_the_mempool() {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let rpc_client = serve_json_rpc({
+            let polls = polls.clone();
+            move |_, _| {
+                if polls.fetch_add(1, Ordering::SeqCst) < 5 {
+                    Ok(json!({ "finality_status": "RECEIVED" }))
+                } else {
+                    Err(json!({ "code": 29, "message": "Transaction hash not found" }))
+                }
+            }
+        })
+        .await;
+        let started_at = Instant::now();
+        let received = AtomicUsize::new(0);
+        let status = TxTracker::new(rpc_client)
+            .follow_with(Felt::ONE, &rules(), || {
+                received.fetch_add(1, Ordering::SeqCst);
+            })
+            .await
+            .unwrap();
+        assert!(matches!(status, TxFinalStatus::Dropped));
+        assert_eq!(received.load(Ordering::SeqCst), 1);
+        // Dropped once unknown for the drop timeout, counted from the last poll it
+        // was known.
+        assert!(started_at.elapsed() >= rules().drop_timeout + rules().poll_interval * 5);
+    }
+
+    #[tokio::test]
+    async fn test_follow_drops_unknown_transactions() {
+        let rpc_client = serve_json_rpc(|_, _| {
+            Err(json!({ "code": 29, "message": "Transaction hash not found" }))
+        })
+        .await;
+        let started_at = Instant::now();
+        let status = TxTracker::new(rpc_client)
+            .follow(Felt::ONE, &rules())
+            .await
+            .unwrap();
+        assert!(matches!(status, TxFinalStatus::Dropped));
+        assert!(started_at.elapsed() >= rules().drop_timeout);
+    }
+}
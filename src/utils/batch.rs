@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use futures_util::future::join_all;
+use starknet::{
+    core::types::{BlockId, Felt, FunctionCall, requests::CallRequest},
+    providers::{
+        JsonRpcClient, Provider, ProviderRequestData, ProviderResponseData, jsonrpc::HttpTransport,
+    },
+};
+
+/// Executes the calls on the provided block through a single JSON-RPC batch request,
+/// returning their results in order.
+/// A batch fails as a whole if one of its calls fails, in which case the calls are
+/// sent one by one so that a failing call doesn't fail the others.
+pub async fn batch_calls(
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    calls: &[FunctionCall],
+    block_id: BlockId,
+) -> Vec<Result<Vec<Felt>>> {
+    if calls.is_empty() {
+        return vec![];
+    }
+
+    let requests: Vec<ProviderRequestData> = calls
+        .iter()
+        .map(|call| {
+            ProviderRequestData::Call(CallRequest {
+                request: call.clone(),
+                block_id,
+            })
+        })
+        .collect();
+
+    match rpc_client.batch_requests(&requests).await {
+        Ok(responses) if responses.len() == calls.len() => {
+            return responses
+                .into_iter()
+                .map(|response| match response {
+                    ProviderResponseData::Call(result) => Ok(result),
+                    _ => Err(anyhow!("Unexpected response to a call in a batch")),
+                })
+                .collect();
+        }
+        Ok(responses) => tracing::warn!(
+            "Batch of {} calls returned {} responses, sending them one by one",
+            calls.len(),
+            responses.len()
+        ),
+        Err(e) => tracing::debug!("Batch of {} calls failed: {e}", calls.len()),
+    }
+
+    join_all(
+        calls
+            .iter()
+            .map(|call| async move { Ok(rpc_client.call(call, block_id).await?) }),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use serde_json::{Value, json};
+    use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
+
+    use crate::utils::test_utils::serve_json_rpc;
+
+    use super::batch_calls;
+
+    fn call(value: u64) -> FunctionCall {
+        FunctionCall {
+            contract_address: Felt::ONE,
+            entry_point_selector: Felt::TWO,
+            calldata: vec![Felt::from(value)],
+        }
+    }
+
+    /// Answers a `starknet_call` with its calldata, or with an error if it is 0,
+    /// counting the answered calls.
+    fn echo(params: &Value, answered: &AtomicUsize) -> Result<Value, Value> {
+        answered.fetch_add(1, Ordering::SeqCst);
+        let calldata = params["request"]["calldata"].clone();
+        if calldata[0] == json!("0x0") {
+            return Err(json!({ "code": 40, "message": "Contract error" }));
+        }
+        Ok(calldata)
+    }
+
+    #[tokio::test]
+    async fn test_batch_calls() {
+        let answered = Arc::new(AtomicUsize::new(0));
+        let counter = answered.clone();
+        let rpc_client = serve_json_rpc(move |_, params| echo(params, &counter)).await;
+
+        let calls = [call(1), call(2), call(3)];
+        let results = batch_calls(&rpc_client, &calls, BlockId::Tag(BlockTag::PreConfirmed)).await;
+        let results: Vec<Vec<Felt>> = results.into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(
+            results,
+            vec![
+                vec![Felt::from(1)],
+                vec![Felt::from(2)],
+                vec![Felt::from(3)]
+            ]
+        );
+        // A single batch.
+        assert_eq!(answered.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failing_batch_falls_back_to_single_calls() {
+        let answered = Arc::new(AtomicUsize::new(0));
+        let counter = answered.clone();
+        let rpc_client = serve_json_rpc(move |_, params| echo(params, &counter)).await;
+
+        let calls = [call(1), call(0), call(3)];
+        let results = batch_calls(&rpc_client, &calls, BlockId::Tag(BlockTag::PreConfirmed)).await;
+        assert_eq!(results[0].as_ref().unwrap(), &vec![Felt::from(1)]);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap(), &vec![Felt::from(3)]);
+        // The batch, then the calls one by one.
+        assert_eq!(answered.load(Ordering::SeqCst), 6);
+    }
+
+    #[tokio::test]
+    async fn test_empty_batch() {
+        let rpc_client = serve_json_rpc(|_, _| panic!("No request expected")).await;
+        let results = batch_calls(&rpc_client, &[], BlockId::Tag(BlockTag::PreConfirmed)).await;
+        assert!(results.is_empty());
+    }
+}
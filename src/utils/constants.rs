@@ -6,6 +6,9 @@ use crate::bindings::liquidate::I129;
 pub const VESU_RESPONSE_DECIMALS: i64 = 18;
 pub const MAX_RETRIES_VERIFY_TX_FINALITY: usize = 10;
 pub const INTERVAL_CHECK_TX_FINALITY: u64 = 3;
+// Ticker of the token used to pay V3 transaction fees
+pub const FEE_TOKEN_TICKER: &str = "STRK";
+pub const FEE_TOKEN_DECIMALS: i64 = 18;
 
 pub const U256_ZERO: U256 = U256 { low: 0, high: 0 };
 pub const I129_ZERO: I129 = I129 {
@@ -1,7 +1,12 @@
+use anyhow::{Result, anyhow};
 use apibara_core::starknet::v1alpha2::FieldElement;
 use bigdecimal::BigDecimal;
 use bigdecimal::num_bigint::BigInt;
 use starknet::core::types::{Felt, U256};
+use starknet::core::utils::parse_cairo_short_string;
+
+/// Number of bytes stored in each full word of a Cairo ByteArray.
+const BYTE_ARRAY_WORD_LEN: usize = 31;
 
 /// Converts an hexadecimal string with decimals to BigDecimal.
 pub fn hex_str_to_big_decimal(hex_price: &str, decimals: i64) -> BigDecimal {
@@ -25,18 +30,55 @@ pub fn big_decimal_to_u256(value: BigDecimal) -> U256 {
     U256::from(big_decimal_to_felt(value))
 }
 
+/// Converts the (low, high) felts of a serialized u256 to BigDecimal.
+pub fn u256_felts_to_big_decimal(low: &Felt, high: &Felt, decimals: i64) -> BigDecimal {
+    let value: BigInt = (high.to_bigint() << 128) + low.to_bigint();
+    BigDecimal::new(value, decimals)
+}
+
 pub fn big_decimal_to_felt(value: BigDecimal) -> Felt {
     let (amount, _): (BigInt, _) = value.as_bigint_and_exponent();
     Felt::from(amount.clone())
 }
 
+/// Converts a Cairo string response to a String. The response is either a single
+/// short string felt or a serialized ByteArray.
+pub fn felts_as_string(felts: &[Felt]) -> Result<String> {
+    let (words_len, rest): (usize, _) = match felts {
+        [] => return Err(anyhow!("Empty string response")),
+        [short_string] => return Ok(parse_cairo_short_string(short_string)?),
+        [words_len, rest @ ..] => (words_len.to_bigint().try_into()?, rest),
+    };
+    anyhow::ensure!(
+        rest.len() == words_len + 2,
+        "Invalid ByteArray length: {} words for {} felts",
+        words_len,
+        rest.len()
+    );
+
+    let mut bytes = Vec::with_capacity(words_len * BYTE_ARRAY_WORD_LEN);
+    for word in &rest[..words_len] {
+        bytes.extend_from_slice(&word.to_bytes_be()[32 - BYTE_ARRAY_WORD_LEN..]);
+    }
+    let pending_len: usize = rest[words_len + 1].to_bigint().try_into()?;
+    anyhow::ensure!(
+        pending_len < BYTE_ARRAY_WORD_LEN,
+        "Invalid ByteArray pending word length: {}",
+        pending_len
+    );
+    bytes.extend_from_slice(&rest[words_len].to_bytes_be()[32 - pending_len..]);
+
+    Ok(String::from_utf8(bytes)?)
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
 
     use bigdecimal::{BigDecimal, num_bigint::BigInt};
+    use starknet::core::{types::Felt, utils::cairo_short_string_to_felt};
 
-    use crate::utils::conversions::hex_str_to_big_decimal;
+    use crate::utils::conversions::{felts_as_string, hex_str_to_big_decimal};
 
     #[test]
     fn test_hex_str_to_decimal() {
@@ -49,4 +91,27 @@ mod test {
             BigDecimal::new(BigInt::from_str("17592186044416").unwrap(), 3)
         );
     }
+
+    #[test]
+    fn test_felts_as_string() {
+        let short_string = cairo_short_string_to_felt("USDC").unwrap();
+        assert_eq!(felts_as_string(&[short_string]).unwrap(), "USDC");
+
+        // ByteArray with no full word & a 4 bytes pending word.
+        assert_eq!(
+            felts_as_string(&[Felt::ZERO, short_string, Felt::from(4)]).unwrap(),
+            "USDC"
+        );
+
+        // ByteArray with one full word of 31 bytes & a 2 bytes pending word.
+        let full_word = cairo_short_string_to_felt("abcdefghijklmnopqrstuvwxyz01234").unwrap();
+        let pending_word = cairo_short_string_to_felt("56").unwrap();
+        assert_eq!(
+            felts_as_string(&[Felt::ONE, full_word, pending_word, Felt::TWO]).unwrap(),
+            "abcdefghijklmnopqrstuvwxyz0123456"
+        );
+
+        assert!(felts_as_string(&[]).is_err());
+        assert!(felts_as_string(&[Felt::TWO, full_word, pending_word, Felt::TWO]).is_err());
+    }
 }
@@ -1,119 +1,174 @@
-use anyhow::{Context, Result};
-use bigdecimal::BigDecimal;
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use bigdecimal::{
+    BigDecimal,
+    num_bigint::{BigInt, BigUint},
+};
 use cainome::cairo_serde::{ContractAddress, U256};
 use serde_json::Value;
-use starknet::core::types::Felt;
+use starknet::{
+    core::types::{BlockId, BlockTag, Felt, FunctionCall},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+};
 
 use crate::{
     bindings::liquidate::{PoolKey, RouteNode, Swap, TokenAmount},
+    config::{
+        EKUBO_GET_POOL_LIQUIDITY_SELECTOR, EKUBO_GET_POOL_PRICE_SELECTOR, EkuboPoolsRules,
+        EkuboQuoterRules,
+    },
+    types::route::{Route, RouteProvider},
     utils::constants::I129_ZERO,
 };
 
-const EKUBO_QUOTE_ENDPOINT: &str = "https://quoter-mainnet-api.ekubo.org";
 const SCALE: u128 = 1_000_000_000_000_000_000;
+/// Bounds of the sqrt ratio of the Ekubo pools.
+const MIN_SQRT_RATIO: &str = "0x1000003f7f1380b75";
+const MAX_SQRT_RATIO: &str = "0xfffffc080ed7b4556f3528fe26840249f4b191ef6dff7928";
+/// Delay before retrying a failed quote, multiplied by the attempt number.
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Client of the Ekubo quoter API of a network.
+#[derive(Debug, Clone)]
+pub struct EkuboQuoter {
+    http_client: reqwest::Client,
+    rules: EkuboQuoterRules,
+}
+
+impl EkuboQuoter {
+    pub fn new(http_client: reqwest::Client, rules: EkuboQuoterRules) -> Self {
+        Self { http_client, rules }
+    }
 
-pub async fn get_ekubo_route(
-    http_client: &reqwest::Client,
-    from_token: Felt,
-    to_token: Felt,
-    amount: &BigDecimal,
-) -> Result<(Vec<Swap>, Vec<u128>)> {
-    let (scaled_amount, _) = amount.as_bigint_and_exponent();
+    /// Returns the route of a quote, `amount` being negative for an exact output.
+    /// Failed requests are retried, invalid responses are not.
+    async fn quote(&self, token: Felt, other_token: Felt, amount: &BigInt) -> Result<Route> {
+        let mut attempt = 0;
+        let quote = loop {
+            match self.fetch_quote(token, other_token, amount).await {
+                Ok(quote) => break quote,
+                Err(e) if attempt < self.rules.retries => {
+                    attempt += 1;
+                    tracing::warn!(
+                        "Ekubo quote failed (attempt {attempt}/{}): {e}",
+                        self.rules.retries + 1
+                    );
+                    tokio::time::sleep(RETRY_DELAY * attempt).await;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        parse_quote(token, &quote)
+    }
+
+    async fn fetch_quote(&self, token: Felt, other_token: Felt, amount: &BigInt) -> Result<Value> {
+        let ekubo_api_endpoint = format!(
+            "{}/{}/{}/{}",
+            self.rules.url.trim_end_matches('/'),
+            amount,
+            token.to_fixed_hex_string(),
+            other_token.to_fixed_hex_string()
+        );
 
-    let ekubo_api_endpoint = format!(
-        "{}/-{}/{}/{}",
-        EKUBO_QUOTE_ENDPOINT,
-        scaled_amount,
-        from_token.to_fixed_hex_string(),
-        to_token.to_fixed_hex_string()
-    );
+        let response = self
+            .http_client
+            .get(ekubo_api_endpoint)
+            .timeout(self.rules.timeout)
+            .send()
+            .await?;
 
-    let response = http_client.get(ekubo_api_endpoint).send().await?;
+        if !response.status().is_success() {
+            bail!("API request failed with status: {}", response.status());
+        }
+
+        let response_text = response.text().await?;
+        Ok(serde_json::from_str(&response_text)?)
+    }
+}
+
+#[async_trait::async_trait]
+impl RouteProvider for EkuboQuoter {
+    fn name(&self) -> &'static str {
+        "Ekubo quoter"
+    }
 
-    if !response.status().is_success() {
-        anyhow::bail!("API request failed with status: {}", response.status());
+    async fn get_route(
+        &self,
+        from_token: Felt,
+        to_token: Felt,
+        amount: &BigDecimal,
+    ) -> Result<Route> {
+        let (scaled_amount, _) = amount.as_bigint_and_exponent();
+        self.quote(from_token, to_token, &-scaled_amount).await
     }
 
-    let response_text = response.text().await?;
-    let json_value: Value = serde_json::from_str(&response_text)?;
+    async fn get_exact_input_route(
+        &self,
+        from_token: Felt,
+        to_token: Felt,
+        amount: &BigDecimal,
+    ) -> Result<Route> {
+        let (scaled_amount, _) = amount.as_bigint_and_exponent();
+        self.quote(from_token, to_token, &scaled_amount).await
+    }
+}
 
-    let splits = json_value["splits"]
+/// Converts a quote of the Ekubo API into swaps & weights for the Liquidate contract.
+pub fn parse_quote(from_token: Felt, quote: &Value) -> Result<Route> {
+    let splits = quote["splits"]
         .as_array()
         .context("'splits' is not an array")?;
 
     if splits.is_empty() {
-        anyhow::bail!("No splits returned from Ekubo API");
-    }
-
-    // Handle single split case (100% weight)
-    if splits.len() == 1 {
-        let route = parse_route(&splits[0])?;
-        return Ok((
-            vec![Swap {
-                route,
-                token_amount: TokenAmount {
-                    token: ContractAddress(from_token),
-                    amount: I129_ZERO,
-                },
-            }],
-            vec![SCALE], // Single weight of 100%
-        ));
+        bail!("No splits returned from Ekubo API");
     }
 
-    // Calculate total amount for weight calculation
-    let total_amount: i128 = splits
+    let split_amounts = splits
         .iter()
         .map(|split| {
-            split["amount_specified"]
+            Ok(split["amount_specified"]
                 .as_str()
-                .unwrap_or("0")
-                .parse::<i128>()
-                .unwrap_or(0)
+                .context("amount_specified is not a string")?
+                .parse::<BigInt>()
+                .context("amount_specified is not an integer")?
+                .magnitude()
+                .clone())
         })
-        .sum();
+        .collect::<Result<Vec<_>>>()?;
+    let total_amount: BigUint = split_amounts.iter().sum();
+    if total_amount == BigUint::from(0u32) {
+        bail!("The splits returned from Ekubo API have no amount");
+    }
 
-    let mut swaps = Vec::with_capacity(splits.len());
+    // Weights are the share of each split, the last one taking the rounding
+    // remainder so that they sum to exactly SCALE.
     let mut weights = Vec::with_capacity(splits.len());
     let mut running_weight_sum: u128 = 0;
-
-    // Process all splits except the last one
-    for split in splits.iter().take(splits.len() - 1) {
-        let split_amount = split["amount_specified"]
-            .as_str()
-            .context("amount_specified is not a string")?
-            .parse::<i128>()?;
-
-        let weight = (split_amount.unsigned_abs() * SCALE) / (total_amount.unsigned_abs());
+    for split_amount in split_amounts.iter().take(splits.len() - 1) {
+        let weight: u128 = (split_amount * SCALE / &total_amount)
+            .try_into()
+            .context("split weight does not fit in a u128")?;
         running_weight_sum += weight;
         weights.push(weight);
-
-        let route = parse_route(split)?;
-        swaps.push(Swap {
-            route,
-            token_amount: TokenAmount {
-                token: ContractAddress(from_token),
-                amount: I129_ZERO,
-            },
-        });
     }
-
-    // Handle the last split - ensure exact SCALE total
-    let last_split = splits.last().unwrap();
-    let last_weight = SCALE - running_weight_sum;
+    let last_weight = SCALE
+        .checked_sub(running_weight_sum)
+        .context("split weights exceed SCALE")?;
     weights.push(last_weight);
 
-    let route = parse_route(last_split)?;
-    swaps.push(Swap {
-        route,
-        token_amount: TokenAmount {
-            token: ContractAddress(from_token),
-            amount: I129_ZERO,
-        },
-    });
-
-    // Verify total is exactly SCALE
-    let total_weight: u128 = weights.iter().sum();
-    assert!(total_weight == SCALE, "Weights do not sum to SCALE");
+    let swaps = splits
+        .iter()
+        .map(|split| {
+            Ok(Swap {
+                route: parse_route(split)?,
+                token_amount: TokenAmount {
+                    token: ContractAddress(from_token),
+                    amount: I129_ZERO,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
 
     Ok((swaps, weights))
 }
@@ -169,3 +224,372 @@ fn parse_route(split: &Value) -> Result<Vec<RouteNode>> {
         })
         .collect()
 }
+
+/// Builds direct single-pool routes from the Ekubo pools of the config, after
+/// checking on-chain that the pool has enough liquidity for the swap.
+#[derive(Clone)]
+pub struct EkuboPools {
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    rules: EkuboPoolsRules,
+}
+
+impl EkuboPools {
+    pub fn new(rpc_client: Arc<JsonRpcClient<HttpTransport>>, rules: EkuboPoolsRules) -> Self {
+        Self { rpc_client, rules }
+    }
+
+    /// Returns the spot price of `token` in `other_token`, both in their smallest unit,
+    /// read on the provided block.
+    pub async fn spot_price(
+        &self,
+        token: Felt,
+        other_token: Felt,
+        block_id: BlockId,
+    ) -> Result<BigDecimal> {
+        let (core_address, pool_key) = self.find_pool(token, other_token)?;
+        let (sqrt_ratio, _) = self
+            .fetch_pool_state(core_address, pool_key, block_id)
+            .await?;
+        if sqrt_ratio == BigUint::from(0u32) {
+            bail!("The Ekubo pool is not initialized");
+        }
+
+        // Price of token0 in token1, the sqrt ratio being a 128.128 fixed point number.
+        let price = BigDecimal::from(BigInt::from(&sqrt_ratio * &sqrt_ratio))
+            / BigDecimal::from(BigInt::from(BigUint::from(1u32) << 256));
+        if pool_key.token0.0 == token {
+            Ok(price)
+        } else {
+            Ok(price.inverse())
+        }
+    }
+
+    /// Returns the Ekubo core address & the configured pool between both tokens.
+    fn find_pool(&self, token: Felt, other_token: Felt) -> Result<(Felt, &PoolKey)> {
+        let core_address = self
+            .rules
+            .core_address
+            .context("No Ekubo core address configured")?;
+        let pool_key = self
+            .rules
+            .pools
+            .iter()
+            .find(|pool_key| {
+                (pool_key.token0.0, pool_key.token1.0) == (token, other_token)
+                    || (pool_key.token0.0, pool_key.token1.0) == (other_token, token)
+            })
+            .with_context(|| format!("No Ekubo pool configured for {token:#x}/{other_token:#x}"))?;
+        Ok((core_address, pool_key))
+    }
+
+    /// Returns the (sqrt ratio, liquidity) of a pool on the provided block, from the
+    /// Ekubo core contract.
+    async fn fetch_pool_state(
+        &self,
+        core_address: Felt,
+        pool_key: &PoolKey,
+        block_id: BlockId,
+    ) -> Result<(BigUint, BigUint)> {
+        let calldata = vec![
+            pool_key.token0.0,
+            pool_key.token1.0,
+            Felt::from(pool_key.fee),
+            Felt::from(pool_key.tick_spacing),
+            pool_key.extension.0,
+        ];
+
+        let pool_price_request = &FunctionCall {
+            contract_address: core_address,
+            entry_point_selector: *EKUBO_GET_POOL_PRICE_SELECTOR,
+            calldata: calldata.clone(),
+        };
+        let pool_liquidity_request = &FunctionCall {
+            contract_address: core_address,
+            entry_point_selector: *EKUBO_GET_POOL_LIQUIDITY_SELECTOR,
+            calldata,
+        };
+        let price = self.rpc_client.call(pool_price_request, block_id).await?;
+        let liquidity = self
+            .rpc_client
+            .call(pool_liquidity_request, block_id)
+            .await?;
+
+        let (Some(low), Some(high), Some(liquidity)) =
+            (price.first(), price.get(1), liquidity.first())
+        else {
+            bail!("Invalid pool state returned by the Ekubo core");
+        };
+        let sqrt_ratio = (high.to_biguint() << 128) + low.to_biguint();
+        Ok((sqrt_ratio, liquidity.to_biguint()))
+    }
+
+    /// Returns the direct route of a swap of `amount` of `token`, which is the
+    /// output of the swap if `is_exact_output`, its input otherwise.
+    async fn direct_route(
+        &self,
+        token: Felt,
+        other_token: Felt,
+        amount: &BigDecimal,
+        is_exact_output: bool,
+    ) -> Result<Route> {
+        let (core_address, pool_key) = self.find_pool(token, other_token)?;
+        let (sqrt_ratio, liquidity) = self
+            .fetch_pool_state(core_address, pool_key, BlockId::Tag(BlockTag::PreConfirmed))
+            .await?;
+        if liquidity == BigUint::from(0u32) || sqrt_ratio == BigUint::from(0u32) {
+            bail!("The Ekubo pool has no liquidity");
+        }
+
+        // Virtual reserve of the specified token around the current price, with the
+        // sqrt ratio being a 128.128 fixed point number.
+        let is_token1 = pool_key.token1.0 == token;
+        let reserve = if is_token1 {
+            (&liquidity * &sqrt_ratio) >> 128
+        } else {
+            (&liquidity << 128) / &sqrt_ratio
+        };
+        let (scaled_amount, _) = amount.as_bigint_and_exponent();
+        let scaled_amount = BigDecimal::from(BigInt::from(scaled_amount.magnitude().clone()));
+        let max_amount = BigDecimal::from(BigInt::from(reserve)) * &self.rules.max_reserve_share;
+        if scaled_amount > max_amount {
+            bail!("Not enough liquidity in the Ekubo pool for this swap");
+        }
+
+        // Selling token0 or buying token1 pushes the price down, the opposite pushes it up.
+        let is_price_increasing = is_exact_output != is_token1;
+        let sqrt_ratio_limit = if is_price_increasing {
+            MAX_SQRT_RATIO
+        } else {
+            MIN_SQRT_RATIO
+        };
+        let route = vec![RouteNode {
+            pool_key: pool_key.clone(),
+            sqrt_ratio_limit: U256::from_bytes_be(&Felt::from_hex(sqrt_ratio_limit)?.to_bytes_be()),
+            skip_ahead: 0,
+        }];
+        let swap = Swap {
+            route,
+            token_amount: TokenAmount {
+                token: ContractAddress(token),
+                amount: I129_ZERO,
+            },
+        };
+        Ok((vec![swap], vec![SCALE]))
+    }
+}
+
+#[async_trait::async_trait]
+impl RouteProvider for EkuboPools {
+    fn name(&self) -> &'static str {
+        "Ekubo pools"
+    }
+
+    async fn get_route(
+        &self,
+        from_token: Felt,
+        to_token: Felt,
+        amount: &BigDecimal,
+    ) -> Result<Route> {
+        self.direct_route(from_token, to_token, amount, true).await
+    }
+
+    async fn get_exact_input_route(
+        &self,
+        from_token: Felt,
+        to_token: Felt,
+        amount: &BigDecimal,
+    ) -> Result<Route> {
+        self.direct_route(from_token, to_token, amount, false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use bigdecimal::BigDecimal;
+    use cainome::cairo_serde::ContractAddress;
+    use starknet::{
+        core::types::Felt,
+        providers::{JsonRpcClient, jsonrpc::HttpTransport},
+    };
+    use tokio::net::TcpListener;
+
+    use crate::bindings::liquidate::PoolKey;
+    use crate::config::{EkuboPoolsRules, EkuboQuoterRules};
+    use crate::types::route::RouteProvider;
+    use crate::utils::test_utils::{serve_ekubo_core, serve_http};
+
+    use super::{EkuboPools, EkuboQuoter, SCALE};
+
+    const SINGLE_SPLIT_QUOTE: &str = include_str!("../../tests/fixtures/ekubo/single_split.json");
+    const MULTI_SPLITS_QUOTE: &str = include_str!("../../tests/fixtures/ekubo/multi_splits.json");
+    const NO_SPLITS_QUOTE: &str = include_str!("../../tests/fixtures/ekubo/no_splits.json");
+    const MALFORMED_QUOTE: &str = include_str!("../../tests/fixtures/ekubo/malformed.json");
+
+    /// Serves a recorded quoter response over HTTP, the first `failures`
+    /// requests being answered with a 503.
+    async fn serve_quote(body: &'static str, failures: usize) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served_requests = requests.clone();
+        let url = serve_http(move |_| {
+            if served_requests.fetch_add(1, Ordering::SeqCst) < failures {
+                ("503 Service Unavailable", String::new())
+            } else {
+                ("200 OK", body.to_string())
+            }
+        })
+        .await;
+        (url.to_string(), requests)
+    }
+
+    fn quoter(url: String, retries: u32) -> EkuboQuoter {
+        EkuboQuoter::new(
+            reqwest::Client::new(),
+            EkuboQuoterRules {
+                url,
+                timeout: Duration::from_millis(500),
+                retries,
+            },
+        )
+    }
+
+    async fn get_route(quoter: &EkuboQuoter) -> anyhow::Result<super::Route> {
+        quoter
+            .get_route(Felt::ONE, Felt::TWO, &BigDecimal::from_str("1.5").unwrap())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_single_split_quote() {
+        let (url, _) = serve_quote(SINGLE_SPLIT_QUOTE, 0).await;
+        let (swaps, weights) = get_route(&quoter(url, 0)).await.unwrap();
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].route.len(), 1);
+        assert_eq!(swaps[0].token_amount.token.0, Felt::ONE);
+        assert_eq!(weights, vec![SCALE]);
+    }
+
+    #[tokio::test]
+    async fn test_multi_splits_quote() {
+        let (url, _) = serve_quote(MULTI_SPLITS_QUOTE, 0).await;
+        let (swaps, weights) = get_route(&quoter(url, 0)).await.unwrap();
+        assert_eq!(swaps.len(), 3);
+        assert_eq!(swaps[1].route.len(), 2);
+        assert_eq!(weights.iter().sum::<u128>(), SCALE);
+        assert_eq!(weights[0], SCALE / 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_quotes_fail_cleanly() {
+        for quote in [NO_SPLITS_QUOTE, MALFORMED_QUOTE] {
+            let (url, requests) = serve_quote(quote, 0).await;
+            assert!(get_route(&quoter(url, 2)).await.is_err());
+            // Invalid responses are not retried.
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_retries() {
+        let (url, requests) = serve_quote(SINGLE_SPLIT_QUOTE, 2).await;
+        assert!(get_route(&quoter(url.clone(), 1)).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        let (url, requests) = serve_quote(SINGLE_SPLIT_QUOTE, 2).await;
+        assert!(get_route(&quoter(url, 2)).await.is_ok());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_quote_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        // Accepts the connections but never answers.
+        tokio::spawn(async move {
+            let mut streams = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+        assert!(get_route(&quoter(url, 0)).await.is_err());
+    }
+
+    /// Ekubo pools with the pool between the tokens 0x1 & 0x2, on the core stub.
+    fn pools(rpc_client: Arc<JsonRpcClient<HttpTransport>>) -> EkuboPools {
+        EkuboPools::new(
+            rpc_client,
+            EkuboPoolsRules {
+                core_address: Some(Felt::from(0xc0)),
+                pools: vec![PoolKey {
+                    token0: ContractAddress(Felt::ONE),
+                    token1: ContractAddress(Felt::TWO),
+                    fee: 0,
+                    tick_spacing: 1,
+                    extension: ContractAddress(Felt::ZERO),
+                }],
+                max_reserve_share: BigDecimal::from_str("0.02").unwrap(),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_direct_route() {
+        // Virtual reserves of 1000 of both tokens at a price of 1.
+        let (rpc_client, _) = serve_ekubo_core(1000).await;
+        let pools = pools(rpc_client);
+
+        let (swaps, weights) = pools
+            .get_route(Felt::ONE, Felt::TWO, &BigDecimal::from(20))
+            .await
+            .unwrap();
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].route[0].pool_key.token0.0, Felt::ONE);
+        assert_eq!(swaps[0].token_amount.token.0, Felt::ONE);
+        assert_eq!(weights, vec![SCALE]);
+        // Buying token0 pushes the price up.
+        assert_ne!(swaps[0].route[0].sqrt_ratio_limit.high, 0);
+
+        let (swaps, _) = pools
+            .get_exact_input_route(Felt::ONE, Felt::TWO, &BigDecimal::from(20))
+            .await
+            .unwrap();
+        // Selling token0 pushes the price down.
+        assert_eq!(swaps[0].route[0].sqrt_ratio_limit.high, 0);
+    }
+
+    #[tokio::test]
+    async fn test_direct_route_liquidity() {
+        let (rpc_client, _) = serve_ekubo_core(1000).await;
+        let pools = pools(rpc_client);
+        // More than 2% of the reserve.
+        assert!(
+            pools
+                .get_route(Felt::ONE, Felt::TWO, &BigDecimal::from(21))
+                .await
+                .is_err()
+        );
+        // No pool configured.
+        assert!(
+            pools
+                .get_route(Felt::ONE, Felt::THREE, &BigDecimal::from(1))
+                .await
+                .is_err()
+        );
+
+        let (rpc_client, _) = serve_ekubo_core(0).await;
+        assert!(
+            pools(rpc_client)
+                .get_route(Felt::ONE, Felt::TWO, &BigDecimal::from(1))
+                .await
+                .is_err()
+        );
+    }
+}
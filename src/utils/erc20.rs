@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use bigdecimal::{BigDecimal, RoundingMode, num_bigint::BigInt};
+use starknet::{
+    core::types::{BlockId, BlockTag, Call, Felt, FunctionCall},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+};
+
+use crate::{
+    config::{
+        ERC20_BALANCE_OF_SELECTOR, ERC20_DECIMALS_SELECTOR, ERC20_SYMBOL_SELECTOR,
+        ERC20_TRANSFER_SELECTOR,
+    },
+    utils::conversions::{big_decimal_to_u256, felts_as_string, u256_felts_to_big_decimal},
+};
+
+/// Fetches the `decimals` of an ERC20 token.
+pub async fn fetch_decimals(
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    token_address: Felt,
+) -> Result<i64> {
+    let result = call_token(rpc_client, token_address, *ERC20_DECIMALS_SELECTOR).await?;
+    let decimals = result
+        .first()
+        .ok_or_else(|| anyhow!("Empty decimals response for {token_address:#x}"))?;
+    Ok(decimals.to_bigint().try_into()?)
+}
+
+/// Fetches the `symbol` of an ERC20 token, either encoded as a short string or
+/// as a ByteArray.
+pub async fn fetch_symbol(
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    token_address: Felt,
+) -> Result<String> {
+    let result = call_token(rpc_client, token_address, *ERC20_SYMBOL_SELECTOR).await?;
+    felts_as_string(&result)
+}
+
+/// Returns the request of the `balance_of` an account.
+pub fn balance_of_request(token_address: Felt, owner: Felt) -> FunctionCall {
+    FunctionCall {
+        contract_address: token_address,
+        entry_point_selector: *ERC20_BALANCE_OF_SELECTOR,
+        calldata: vec![owner],
+    }
+}
+
+/// Parses the u256 response of a `balance_of` request.
+pub fn parse_balance(response: &[Felt], decimals: i64) -> Result<BigDecimal> {
+    match response {
+        [low, high, ..] => Ok(u256_felts_to_big_decimal(low, high, decimals)),
+        _ => Err(anyhow!("Invalid balance_of response: {response:?}")),
+    }
+}
+
+/// Returns the call transferring `amount` of a token to `recipient`, the amount
+/// being rounded down to the token decimals.
+pub fn transfer_call(
+    token_address: Felt,
+    recipient: Felt,
+    amount: &BigDecimal,
+    decimals: i64,
+) -> Call {
+    let raw_amount = (amount * BigDecimal::new(BigInt::from(1), -decimals))
+        .with_scale_round(0, RoundingMode::Down);
+    let raw_amount = big_decimal_to_u256(raw_amount);
+    Call {
+        to: token_address,
+        selector: *ERC20_TRANSFER_SELECTOR,
+        calldata: vec![
+            recipient,
+            Felt::from(raw_amount.low),
+            Felt::from(raw_amount.high),
+        ],
+    }
+}
+
+async fn call_token(
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    token_address: Felt,
+    selector: Felt,
+) -> Result<Vec<Felt>> {
+    let request = FunctionCall {
+        contract_address: token_address,
+        entry_point_selector: selector,
+        calldata: vec![],
+    };
+    Ok(rpc_client
+        .call(request, BlockId::Tag(BlockTag::PreConfirmed))
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use super::{parse_balance, transfer_call};
+
+    #[test]
+    fn test_transfer_call() {
+        let amount = BigDecimal::from_str("12.3456789").unwrap();
+        let call = transfer_call(Felt::ONE, Felt::TWO, &amount, 6);
+        assert_eq!(
+            call.calldata,
+            vec![Felt::TWO, Felt::from(12_345_678_u64), Felt::ZERO]
+        );
+
+        let balance = parse_balance(&call.calldata[1..], 6).unwrap();
+        assert_eq!(balance, BigDecimal::from_str("12.345678").unwrap());
+
+        // 10^24 doesn't fit in a u64.
+        let call = transfer_call(Felt::ONE, Felt::TWO, &BigDecimal::from(3), 24);
+        let balance = parse_balance(&call.calldata[1..], 24).unwrap();
+        assert_eq!(balance, BigDecimal::from(3));
+    }
+}
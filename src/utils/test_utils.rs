@@ -0,0 +1,154 @@
+//! Local HTTP stubs of the services the bot talks to, for the tests.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use serde_json::{Value, json};
+use starknet::{
+    core::types::Felt,
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+use crate::config::{EKUBO_GET_POOL_LIQUIDITY_SELECTOR, EKUBO_GET_POOL_PRICE_SELECTOR};
+
+/// HTTP request received by a stub.
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    pub head: String,
+    pub body: String,
+}
+
+impl StubRequest {
+    /// First line of the request, e.g `POST /sign HTTP/1.1`.
+    pub fn request_line(&self) -> &str {
+        self.head.lines().next().unwrap_or_default()
+    }
+
+    /// Returns true if the request holds the header, its name compared case-insensitively.
+    pub fn has_header(&self, name: &str, value: &str) -> bool {
+        self.head.lines().any(|line| {
+            line.split_once(':').is_some_and(|(header, header_value)| {
+                header.eq_ignore_ascii_case(name) && header_value.trim() == value
+            })
+        })
+    }
+}
+
+/// Serves HTTP on a local port, answering each request with the status & the JSON
+/// body returned by `handler`. Returns the URL of the stub.
+pub async fn serve_http<F>(handler: F) -> Url
+where
+    F: Fn(StubRequest) -> (&'static str, String) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+    let handler = Arc::new(handler);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let request = read_request(&mut stream).await;
+                let (status, body) = handler(request);
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    url
+}
+
+/// Serves the Starknet JSON-RPC API with `handler`, called with the method & the
+/// params of each request, batched or not. Returns a client of the stub.
+///
+/// The errors returned by the handler are JSON-RPC error objects, e.g
+/// `{ "code": 29, "message": "Transaction hash not found" }`.
+pub async fn serve_json_rpc<F>(handler: F) -> Arc<JsonRpcClient<HttpTransport>>
+where
+    F: Fn(&str, &Value) -> Result<Value, Value> + Send + Sync + 'static,
+{
+    let answer = move |request: &Value| {
+        let method = request["method"].as_str().unwrap_or_default();
+        match handler(method, &request["params"]) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": request["id"], "error": error }),
+        }
+    };
+    let url = serve_http(move |request| {
+        let response = match serde_json::from_str::<Value>(&request.body) {
+            Ok(Value::Array(requests)) => Value::Array(requests.iter().map(&answer).collect()),
+            Ok(request) => answer(&request),
+            Err(_) => return ("400 Bad Request", String::new()),
+        };
+        ("200 OK", response.to_string())
+    })
+    .await;
+    Arc::new(JsonRpcClient::new(HttpTransport::new(url)))
+}
+
+/// Serves the Ekubo core contract, every pool being at a price of 1 with the provided
+/// liquidity. Returns a client of the stub & the number of calls served.
+pub async fn serve_ekubo_core(
+    liquidity: u64,
+) -> (Arc<JsonRpcClient<HttpTransport>>, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let served_calls = calls.clone();
+    let rpc_client = serve_json_rpc(move |_, params| {
+        served_calls.fetch_add(1, Ordering::SeqCst);
+        let selector = params["request"]["entry_point_selector"]
+            .as_str()
+            .and_then(|selector| Felt::from_hex(selector).ok());
+        if selector == Some(*EKUBO_GET_POOL_PRICE_SELECTOR) {
+            // Sqrt ratio of 2^128, as a u256, & tick of 0, as an i129.
+            Ok(json!(["0x0", "0x1", "0x0", "0x0"]))
+        } else if selector == Some(*EKUBO_GET_POOL_LIQUIDITY_SELECTOR) {
+            Ok(json!([Felt::from(liquidity).to_hex_string()]))
+        } else {
+            Err(json!({ "code": 21, "message": "Invalid message selector" }))
+        }
+    })
+    .await;
+    (rpc_client, calls)
+}
+
+/// Reads the head & the body of an HTTP request.
+async fn read_request(stream: &mut TcpStream) -> StubRequest {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    loop {
+        let read = stream.read(&mut buffer).await.unwrap_or(0);
+        request.extend_from_slice(&buffer[..read]);
+        let text = String::from_utf8_lossy(&request).to_string();
+        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if body.len() >= content_length || read == 0 {
+                return StubRequest {
+                    head: head.to_string(),
+                    body: body.to_string(),
+                };
+            }
+        }
+        if read == 0 {
+            return StubRequest {
+                head: text,
+                body: String::new(),
+            };
+        }
+    }
+}